@@ -67,6 +67,37 @@ pub async fn delete_accounts(
     Ok(())
 }
 
+/// 列出回收站中的账号
+#[tauri::command]
+pub async fn list_trash() -> Result<Vec<modules::trash::TrashItem>, String> {
+    modules::trash::list_trash()
+}
+
+/// 从回收站恢复账号（恢复到原索引位置）
+#[tauri::command]
+pub async fn restore_from_trash(
+    app: tauri::AppHandle,
+    entry_id: String,
+) -> Result<Account, String> {
+    let account = modules::account::restore_account_from_trash(&entry_id)?;
+    crate::modules::tray::update_tray_menus(&app);
+    Ok(account)
+}
+
+/// 永久删除回收站条目，未指定 ID 时清空回收站
+#[tauri::command]
+pub async fn purge_trash(entry_ids: Option<Vec<String>>) -> Result<usize, String> {
+    modules::trash::purge_trash(entry_ids)
+}
+
+/// 查询账号审计日志（按时间倒序）
+#[tauri::command]
+pub async fn query_audit_log(
+    query: modules::audit::AuditQuery,
+) -> Result<Vec<modules::audit::AuditRecord>, String> {
+    modules::audit::query(&query)
+}
+
 /// 重新排序账号列表
 /// 根据传入的账号ID数组顺序更新账号排列
 #[tauri::command]
@@ -103,6 +134,25 @@ pub async fn switch_account(
     Ok(())
 }
 
+/// 按选择策略为账号排序（可临时覆盖策略与参考模型）
+#[tauri::command]
+pub async fn rank_accounts(
+    strategy: Option<crate::models::config::SelectionStrategy>,
+    model: Option<String>,
+) -> Result<modules::selection::AccountRanking, String> {
+    modules::selection::rank_accounts(strategy, model)
+}
+
+/// 切换到排名最高的其他账号，返回切换到的账号 ID
+#[tauri::command]
+pub async fn switch_to_best_account(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    let Some(best) = modules::selection::best_next_account()? else {
+        return Ok(None);
+    };
+    switch_account(app, best.account_id.clone()).await?;
+    Ok(Some(best.account_id))
+}
+
 /// 获取当前账号
 #[tauri::command]
 pub async fn get_current_account() -> Result<Option<Account>, String> {
@@ -129,6 +179,82 @@ pub async fn export_accounts(account_ids: Vec<String>) -> Result<AccountExportRe
     modules::account::export_accounts_by_ids(&account_ids)
 }
 
+/// 导出加密账号包（密码保护，可选包含标签 / 设备指纹 / 配额快照）
+#[tauri::command]
+pub async fn export_account_bundle(
+    account_ids: Vec<String>,
+    password: String,
+    options: Option<modules::account_bundle::BundleExportOptions>,
+) -> Result<String, String> {
+    modules::account_bundle::export_bundle(&account_ids, &password, options.unwrap_or_default())
+}
+
+/// 预览加密账号包头部信息（版本、创建时间、账号数量），无需密码
+#[tauri::command]
+pub async fn inspect_account_bundle(
+    content: String,
+) -> Result<modules::account_bundle::BundleHeader, String> {
+    modules::account_bundle::inspect_bundle(&content)
+}
+
+/// 校验并导入加密账号包，返回逐账号导入结果
+#[tauri::command]
+pub async fn import_account_bundle(
+    app: tauri::AppHandle,
+    content: String,
+    password: String,
+) -> Result<modules::account_bundle::BundleImportReport, String> {
+    let report = modules::account_bundle::import_bundle(&content, &password)?;
+    if report.imported > 0 {
+        crate::modules::tray::update_tray_menus(&app);
+    }
+    Ok(report)
+}
+
+/// 批量导入 refresh_token（JSON / CSV / 每行一个），dry_run 时仅校验不写入
+#[tauri::command]
+pub async fn bulk_import_accounts(
+    app: tauri::AppHandle,
+    content: String,
+    dry_run: bool,
+) -> Result<modules::bulk_import::BulkImportReport, String> {
+    let report = modules::bulk_import::bulk_import(&content, dry_run).await?;
+    if !dry_run && report.added + report.updated > 0 {
+        crate::modules::tray::update_tray_menus(&app);
+    }
+    Ok(report)
+}
+
+/// 查看当前静态加密密钥状态（密钥 ID 与主密钥来源）
+#[tauri::command]
+pub async fn get_encryption_key_status() -> Result<crate::utils::crypto::KeyStatus, String> {
+    crate::utils::crypto::key_status()
+}
+
+/// 查看账号存储后端状态（JSON 文件 / SQLite）
+#[tauri::command]
+pub async fn get_account_store_status() -> Result<modules::account_store::AccountStoreStatus, String> {
+    modules::account::get_account_store_status()
+}
+
+/// 将 accounts.json + accounts/*.json 一次性迁移到 SQLite 存储（accounts.db）
+#[tauri::command]
+pub async fn migrate_account_store() -> Result<modules::account_store::AccountStoreReport, String> {
+    modules::account::migrate_to_account_store()
+}
+
+/// 将 SQLite 存储导出回 JSON 文件布局并停用 SQLite 存储
+#[tauri::command]
+pub async fn export_account_store() -> Result<modules::account_store::AccountStoreReport, String> {
+    modules::account::export_account_store_to_json()
+}
+
+/// 轮换静态加密密钥，并使用新密钥重新加密所有账号凭据
+#[tauri::command]
+pub async fn rotate_encryption_key() -> Result<modules::account::KeyRotationResult, String> {
+    modules::account::rotate_encryption_key()
+}
+
 /// 内部辅助功能：在添加或导入账号后自动刷新一次额度
 async fn internal_refresh_account_quota(
     app: &tauri::AppHandle,
@@ -140,7 +266,7 @@ async fn internal_refresh_account_quota(
     match modules::account::fetch_quota_with_retry(account).await {
         Ok(quota) => {
             // 更新账号配额
-            let _ = modules::update_account_quota(&account.id, quota.clone());
+            let _ = modules::update_account_quota_async(&account.id, quota.clone()).await;
             // 更新托盘菜单
             crate::modules::tray::update_tray_menus(app);
             Ok(quota)
@@ -166,10 +292,12 @@ pub async fn fetch_account_quota(
     let quota = modules::account::fetch_quota_with_retry(&mut account).await?;
 
     // 更新账号配额
-    modules::update_account_quota(&account_id, quota.clone())
+    modules::update_account_quota_async(&account_id, quota.clone())
+        .await
         .map_err(crate::error::AppError::Account)?;
 
     crate::modules::tray::update_tray_menus(&app);
+    modules::auto_switch::spawn_pending(crate::modules::integration::SystemManager::Desktop(app));
 
     Ok(quota)
 }
@@ -186,6 +314,7 @@ pub async fn refresh_all_quotas_internal(
     if let Some(handle) = app_handle {
         use tauri::Emitter;
         let _ = handle.emit("accounts://refreshed", ());
+        modules::auto_switch::spawn_pending(crate::modules::integration::SystemManager::Desktop(handle));
     }
 
     Ok(stats)
@@ -198,6 +327,81 @@ pub async fn refresh_all_quotas(
 ) -> Result<RefreshStats, String> {
     refresh_all_quotas_internal(Some(app_handle)).await
 }
+
+/// 获取账号各模型的配额历史曲线
+#[tauri::command]
+pub async fn get_quota_history(
+    account_id: String,
+    model: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<modules::quota_history::QuotaSeries>, String> {
+    modules::quota_history::usage_curves(&account_id, model.as_deref(), since, until)
+}
+
+/// 获取每日配额消耗（账号 / 模型为空时返回全部）
+#[tauri::command]
+pub async fn get_quota_daily_consumption(
+    account_id: Option<String>,
+    model: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<modules::quota_history::DailyConsumption>, String> {
+    modules::quota_history::daily_consumption(account_id.as_deref(), model.as_deref(), since, until)
+}
+
+/// 获取账号的订阅等级缓存与变化历史
+#[tauri::command]
+pub async fn get_account_tier(
+    account_id: String,
+) -> Result<Option<modules::tier::TierRecord>, String> {
+    modules::tier::get_record(&account_id)
+}
+
+/// 立即重新检测账号的订阅等级
+#[tauri::command]
+pub async fn refresh_account_tier(account_id: String) -> Result<modules::tier::TierRecord, String> {
+    modules::tier::refresh_account_tier(&account_id).await
+}
+
+/// 汇总号池内各模型的可用账号与剩余额度
+#[tauri::command]
+pub async fn get_quota_pool_summary(
+    max_age_minutes: Option<u32>,
+) -> Result<modules::quota_pool::PoolSummary, String> {
+    modules::quota_pool::pool_summary(max_age_minutes)
+}
+
+/// 预测各账号及号池的配额耗尽时间
+#[tauri::command]
+pub async fn get_quota_forecast() -> Result<modules::forecast::QuotaForecast, String> {
+    modules::forecast::quota_forecast()
+}
+
+/// 查询 webhook 投递日志（按时间倒序）
+#[tauri::command]
+pub async fn get_webhook_deliveries(
+    limit: Option<usize>,
+) -> Result<Vec<modules::webhooks::DeliveryRecord>, String> {
+    modules::webhooks::deliveries(limit)
+}
+
+/// 向 webhook 地址发送一条测试事件
+#[tauri::command]
+pub async fn send_test_webhook(url: String) -> Result<modules::webhooks::DeliveryRecord, String> {
+    modules::webhooks::send_test(&url).await
+}
+
+/// 获取各账号额度耗尽次数
+#[tauri::command]
+pub async fn get_quota_exhaustion_stats(
+    account_id: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<modules::quota_history::ExhaustionStat>, String> {
+    modules::quota_history::exhaustion_stats(account_id.as_deref(), since, until)
+}
+
 /// 获取设备指纹（当前 storage.json + 账号绑定）
 #[tauri::command]
 pub async fn get_device_profiles(
@@ -481,7 +685,8 @@ fn validate_path(path: &str) -> Result<(), String> {
 #[tauri::command]
 pub async fn save_text_file(path: String, content: String) -> Result<(), String> {
     validate_path(&path)?;
-    std::fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))
+    crate::utils::atomic_file::write_atomic(std::path::Path::new(&path), content)
+        .map_err(|e| format!("写入文件失败: {}", e))
 }
 
 /// 读取文本文件 (绕过前端 Scope 限制)
@@ -552,6 +757,12 @@ pub async fn get_data_dir_path() -> Result<String, String> {
     Ok(path.to_string_lossy().to_string())
 }
 
+/// 获取所有已解析的路径及其来源（命令行 / 环境变量 / XDG / 默认）
+#[tauri::command]
+pub async fn get_resolved_paths() -> Result<Vec<modules::paths::PathEntry>, String> {
+    modules::paths::describe()
+}
+
 /// 显示主窗口
 #[tauri::command]
 pub async fn show_main_window(window: tauri::Window) -> Result<(), String> {
@@ -669,33 +880,19 @@ pub async fn update_account_label(account_id: String, label: String) -> Result<(
         if label.is_empty() { "无" } else { &label }
     ));
 
-    // 1. 读取账号文件
-    let data_dir = modules::account::get_data_dir()?;
-    let account_path = data_dir
-        .join("accounts")
-        .join(format!("{}.json", account_id));
-
-    if !account_path.exists() {
-        return Err(format!("账号文件不存在: {}", account_id));
-    }
-
-    let content =
-        std::fs::read_to_string(&account_path).map_err(|e| format!("读取账号文件失败: {}", e))?;
-
-    let mut account_json: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| format!("解析账号文件失败: {}", e))?;
+    // 1. 读取账号数据
+    let mut account = modules::account::load_account(&account_id)
+        .map_err(|e| format!("账号不存在: {} ({})", account_id, e))?;
 
     // 2. 更新 custom_label 字段
-    if label.is_empty() {
-        account_json["custom_label"] = serde_json::Value::Null;
+    account.custom_label = if label.is_empty() {
+        None
     } else {
-        account_json["custom_label"] = serde_json::Value::String(label.clone());
-    }
+        Some(label.clone())
+    };
 
-    // 3. 保存到磁盘
-    let json_str = serde_json::to_string_pretty(&account_json)
-        .map_err(|e| format!("序列化账号数据失败: {}", e))?;
-    std::fs::write(&account_path, json_str).map_err(|e| format!("写入账号文件失败: {}", e))?;
+    // 3. 保存（JSON 文件或 SQLite 存储）
+    modules::account::save_account(&account).map_err(|e| format!("写入账号数据失败: {}", e))?;
 
     modules::logger::log_info(&format!(
         "账号标签已更新: {} ({})",
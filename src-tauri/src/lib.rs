@@ -112,6 +112,14 @@ pub fn run() {
     // Initialize logger
     logger::init_logger();
 
+    // 一次性重写明文或旧密钥加密的账号（读取时不再回写）
+    if let Err(e) = modules::account::reseal_accounts() {
+        warn!("Failed to re-encrypt stored accounts: {}", e);
+    }
+
+    // 可选的 Prometheus 指标端点：不依赖窗口与 setup，GUI 与 headless 启动都会经过这里
+    modules::metrics::start_server();
+
     #[cfg(target_os = "linux")]
     configure_linux_gdk_backend();
 
@@ -127,6 +135,7 @@ pub fn run() {
         ))
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_window_state::Builder::default().build())
         .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
             let _ = app.get_webview_window("main")
@@ -178,6 +187,7 @@ pub fn run() {
 
             // 启动智能调度器
             modules::scheduler::start_scheduler(Some(app.handle().clone()));
+            modules::webhooks::start_delivery_loop();
 
             info!("Setup completed");
 
@@ -212,9 +222,24 @@ pub fn run() {
             commands::add_account,
             commands::delete_account,
             commands::delete_accounts,
+            commands::list_trash,
+            commands::restore_from_trash,
+            commands::purge_trash,
+            commands::query_audit_log,
             commands::reorder_accounts,
             commands::switch_account,
+            commands::rank_accounts,
+            commands::switch_to_best_account,
             commands::export_accounts,
+            commands::export_account_bundle,
+            commands::inspect_account_bundle,
+            commands::import_account_bundle,
+            commands::bulk_import_accounts,
+            commands::get_encryption_key_status,
+            commands::rotate_encryption_key,
+            commands::get_account_store_status,
+            commands::migrate_account_store,
+            commands::export_account_store,
             // Device fingerprint
             commands::get_device_profiles,
             commands::bind_device_profile,
@@ -230,6 +255,15 @@ pub fn run() {
             // Quota commands
             commands::fetch_account_quota,
             commands::refresh_all_quotas,
+            commands::get_quota_history,
+            commands::get_quota_daily_consumption,
+            commands::get_quota_exhaustion_stats,
+            commands::get_quota_forecast,
+            commands::get_quota_pool_summary,
+            commands::get_webhook_deliveries,
+            commands::send_test_webhook,
+            commands::get_account_tier,
+            commands::refresh_account_tier,
             // Config commands
             commands::load_config,
             commands::save_config,
@@ -250,6 +284,7 @@ pub fn run() {
             commands::get_antigravity_cache_paths,
             commands::open_data_folder,
             commands::get_data_dir_path,
+            commands::get_resolved_paths,
             commands::show_main_window,
             commands::set_window_theme,
             commands::get_antigravity_path,
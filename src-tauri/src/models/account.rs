@@ -1,10 +1,22 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use super::{token::TokenData, quota::QuotaData};
 
+/// 账号记录的 schema 版本（迁移见 `modules::schema`）
+pub const ACCOUNT_SCHEMA_VERSION: u32 = 2;
+/// accounts.json 的主版本号，写入时为 "<n>.0"
+pub const ACCOUNT_INDEX_VERSION: u32 = 3;
+
+fn current_account_schema_version() -> u32 {
+    ACCOUNT_SCHEMA_VERSION
+}
+
 /// 账号数据结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
+    /// 内存中的账号始终为当前 schema，旧数据在反序列化前由迁移处理
+    #[serde(default = "current_account_schema_version")]
+    pub schema_version: u32,
     pub id: String,
     pub email: String,
     pub name: Option<String>,
@@ -25,18 +37,12 @@ pub struct Account {
     /// Unix timestamp when the account was disabled.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disabled_at: Option<i64>,
-    /// deprecated: 反代功能已移除，保留字段以兼容旧数据反序列化
-    #[serde(default)]
-    pub proxy_disabled: bool,
-    /// deprecated: 反代功能已移除，保留字段以兼容旧数据反序列化
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub proxy_disabled_reason: Option<String>,
-    /// deprecated: 反代功能已移除，保留字段以兼容旧数据反序列化
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub proxy_disabled_at: Option<i64>,
     /// 受配额保护禁用的模型列表 [NEW #621]
     #[serde(default, skip_serializing_if = "HashSet::is_empty")]
     pub protected_models: HashSet<String>,
+    /// 受保护模型的触发记录（何时、为何、保护到何时）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub protection: HashMap<String, ModelProtection>,
     /// [NEW] 403 验证阻止状态 (VALIDATION_REQUIRED)
     #[serde(default)]
     pub validation_blocked: bool,
@@ -51,12 +57,6 @@ pub struct Account {
     pub validation_url: Option<String>,
     pub created_at: i64,
     pub last_used: i64,
-    /// deprecated: 反代功能已移除，保留字段以兼容旧数据反序列化
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub proxy_id: Option<String>,
-    /// deprecated: 反代功能已移除，保留字段以兼容旧数据反序列化
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub proxy_bound_at: Option<i64>,
     /// 用户自定义标签
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub custom_label: Option<String>,
@@ -66,6 +66,7 @@ impl Account {
     pub fn new(id: String, email: String, token: TokenData) -> Self {
         let now = chrono::Utc::now().timestamp();
         Self {
+            schema_version: ACCOUNT_SCHEMA_VERSION,
             id,
             email,
             name: None,
@@ -76,18 +77,14 @@ impl Account {
             disabled: false,
             disabled_reason: None,
             disabled_at: None,
-            proxy_disabled: false,
-            proxy_disabled_reason: None,
-            proxy_disabled_at: None,
             protected_models: HashSet::new(),
+            protection: HashMap::new(),
             validation_blocked: false,
             validation_blocked_until: None,
             validation_blocked_reason: None,
             validation_url: None,
             created_at: now,
             last_used: now,
-            proxy_id: None,
-            proxy_bound_at: None,
             custom_label: None,
         }
     }
@@ -101,6 +98,35 @@ impl Account {
     }
 }
 
+/// 模型保护状态变化的原因
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProtectionReason {
+    /// 剩余额度降到触发阈值及以下
+    BelowThreshold { remaining: i32, threshold: u32 },
+    /// 剩余额度回升到解除阈值以上
+    Recovered { remaining: i32, release: u32 },
+    /// 已过保护截止的重置时间
+    ResetPassed { reset_at: i64, remaining: i32 },
+    /// 模型不再在监控列表中
+    NotMonitored,
+    /// 配额数据中已没有该分组的模型（下线或改名）
+    NoLongerReported,
+    /// 配额保护已关闭
+    ProtectionDisabled,
+}
+
+/// 单个受保护模型的触发信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelProtection {
+    /// 触发时间
+    pub since: i64,
+    /// protect_until_reset 模式下的保护截止（触发时该模型的重置时间）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<i64>,
+    pub reason: ProtectionReason,
+}
+
 /// 账号索引数据（accounts.json）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountIndex {
@@ -117,9 +143,6 @@ pub struct AccountSummary {
     pub name: Option<String>,
     #[serde(default)]
     pub disabled: bool,
-    /// deprecated: 反代功能已移除，保留字段以兼容旧数据反序列化
-    #[serde(default)]
-    pub proxy_disabled: bool,
     /// 受保护的模型列表 [NEW] 供 UI 显示锁定图标
     #[serde(default, skip_serializing_if = "HashSet::is_empty")]
     pub protected_models: HashSet<String>,
@@ -130,7 +153,7 @@ pub struct AccountSummary {
 impl AccountIndex {
     pub fn new() -> Self {
         Self {
-            version: "2.0".to_string(),
+            version: format!("{}.0", ACCOUNT_INDEX_VERSION),
             accounts: Vec::new(),
             current_account_id: None,
         }
@@ -1,8 +1,17 @@
 use serde::{Deserialize, Serialize};
 
+/// gui_config.json 的 schema 版本（迁移见 `modules::schema`）
+pub const APP_CONFIG_SCHEMA_VERSION: u32 = 2;
+
+fn current_config_schema_version() -> u32 {
+    APP_CONFIG_SCHEMA_VERSION
+}
+
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
+    #[serde(default = "current_config_schema_version")]
+    pub schema_version: u32,
     pub language: String,
     pub theme: String,
     pub auto_refresh: bool,
@@ -14,15 +23,53 @@ pub struct AppConfig {
     pub antigravity_args: Option<Vec<String>>, // [NEW] Antigravity startup arguments
     #[serde(default)]
     pub auto_launch: bool,  // Launch on startup
-    /// deprecated: 反代预热功能已移除，保留字段以兼容旧配置反序列化
-    #[serde(default)]
-    pub scheduled_warmup: serde_json::Value,
     #[serde(default)]
     pub quota_protection: QuotaProtectionConfig, // [NEW] Quota protection configuration
     #[serde(default)]
     pub pinned_quota_models: PinnedQuotaModelsConfig, // [NEW] Pinned quota models list
     #[serde(default)]
     pub hidden_menu_items: Vec<String>, // Hidden menu item path list
+    /// 回收站保留天数，到期自动清除（0 表示永久保留）
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u32,
+    /// 配额历史记录与降采样设置
+    #[serde(default)]
+    pub quota_history: QuotaHistoryConfig,
+    /// 保存配额时保留哪些模型
+    #[serde(default)]
+    pub model_filter: ModelFilterConfig,
+    /// 当前账号进入配额保护时自动切换
+    #[serde(default)]
+    pub auto_switch: AutoSwitchConfig,
+    /// "切换到最佳账号" 的排序策略
+    #[serde(default)]
+    pub account_selection: AccountSelectionConfig,
+    /// 订阅等级缓存有效期（分钟），过期后刷新配额时重新查询
+    #[serde(default = "default_tier_cache_ttl_minutes")]
+    pub tier_cache_ttl_minutes: u32,
+    /// 配额请求的重试、熔断与批量刷新并发
+    #[serde(default)]
+    pub refresh_policy: RefreshPolicyConfig,
+    /// 覆盖 Cloud Code / OAuth 服务地址（环境变量优先）
+    #[serde(default)]
+    pub endpoints: EndpointsConfig,
+    /// 通知渠道、事件开关与免打扰时段
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    /// 账号与配额事件的出站 webhook
+    #[serde(default)]
+    pub webhooks: WebhooksConfig,
+    /// Prometheus 指标导出
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+}
+
+pub fn default_trash_retention_days() -> u32 {
+    30
+}
+
+fn default_tier_cache_ttl_minutes() -> u32 {
+    360
 }
 
 /// Quota protection configuration
@@ -37,8 +84,33 @@ pub struct QuotaProtectionConfig {
     /// List of monitored models (e.g. gemini-3-flash, gemini-3-pro-high, gemini-3.1-pro-high, claude-sonnet-4-6)
     #[serde(default = "default_monitored_models")]
     pub monitored_models: Vec<String>,
+
+    /// 解除保护的阈值（需高于触发阈值，形成回差）；未设置时为触发阈值 + 5
+    #[serde(default)]
+    pub release_percentage: Option<u32>,
+
+    /// 按模型（或模型前缀）覆盖的阈值，最长匹配优先
+    #[serde(default)]
+    pub model_thresholds: Vec<ModelThreshold>,
+
+    /// 触发后一直保护到该模型的 reset_time 之后
+    #[serde(default)]
+    pub protect_until_reset: bool,
 }
 
+/// Per-model protection threshold override
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelThreshold {
+    /// 模型名或前缀，如 "claude" / "gemini-3-pro-high"
+    pub pattern: String,
+    pub threshold_percentage: u32,
+    #[serde(default)]
+    pub release_percentage: Option<u32>,
+}
+
+/// 未配置解除阈值时，在触发阈值之上留出的回差
+const DEFAULT_RELEASE_MARGIN: u32 = 5;
+
 fn default_monitored_models() -> Vec<String> {
     vec![
         "claude".to_string(),
@@ -54,8 +126,54 @@ impl QuotaProtectionConfig {
             enabled: false,
             threshold_percentage: 10, // Default 10% reserve
             monitored_models: default_monitored_models(),
+            release_percentage: None,
+            model_thresholds: Vec::new(),
+            protect_until_reset: false,
         }
     }
+
+    /// Whether a model is monitored (entries also match as prefixes, e.g. "claude")
+    pub fn monitors(&self, model: &str) -> bool {
+        self.monitored_models
+            .iter()
+            .any(|m| model == m || model.starts_with(m.as_str()))
+    }
+
+    /// Monitored group (name or prefix) a model belongs to; the longest entry wins.
+    /// Unmonitored models are their own group.
+    pub fn group_of<'a>(&'a self, model: &'a str) -> &'a str {
+        self.monitored_models
+            .iter()
+            .filter(|m| model.starts_with(m.as_str()))
+            .max_by_key(|m| m.len())
+            .map_or(model, |m| m.as_str())
+    }
+
+    /// Threshold rule for a model: the longest pattern its name starts with
+    fn threshold_rule(&self, model: &str) -> Option<&ModelThreshold> {
+        self.model_thresholds
+            .iter()
+            .filter(|t| model.starts_with(t.pattern.as_str()))
+            .max_by_key(|t| t.pattern.len())
+    }
+
+    /// Protection threshold for a model
+    pub fn threshold_for(&self, model: &str) -> u32 {
+        self.threshold_rule(model)
+            .map_or(self.threshold_percentage, |t| t.threshold_percentage)
+    }
+
+    /// Release threshold for a model, never below its protection threshold
+    pub fn release_for(&self, model: &str) -> u32 {
+        let threshold = self.threshold_for(model);
+        let release = match self.threshold_rule(model) {
+            Some(rule) => rule.release_percentage,
+            None => self.release_percentage,
+        };
+        release
+            .unwrap_or((threshold + DEFAULT_RELEASE_MARGIN).min(100))
+            .max(threshold)
+    }
 }
 
 impl Default for QuotaProtectionConfig {
@@ -64,6 +182,484 @@ impl Default for QuotaProtectionConfig {
     }
 }
 
+/// Quota history configuration
+///
+/// 原始快照保留 `raw_retention_days` 天，之后合并为每小时一个点；
+/// 小时数据保留 `hourly_retention_days` 天后合并为每天一个点，每日数据保留 `daily_retention_days` 天（0 表示永久）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaHistoryConfig {
+    /// Whether quota snapshots are recorded
+    pub enabled: bool,
+    #[serde(default = "default_raw_retention_days")]
+    pub raw_retention_days: u32,
+    #[serde(default = "default_hourly_retention_days")]
+    pub hourly_retention_days: u32,
+    #[serde(default = "default_daily_retention_days")]
+    pub daily_retention_days: u32,
+}
+
+fn default_raw_retention_days() -> u32 {
+    7
+}
+
+fn default_hourly_retention_days() -> u32 {
+    30
+}
+
+fn default_daily_retention_days() -> u32 {
+    365
+}
+
+impl QuotaHistoryConfig {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            raw_retention_days: default_raw_retention_days(),
+            hourly_retention_days: default_hourly_retention_days(),
+            daily_retention_days: default_daily_retention_days(),
+        }
+    }
+}
+
+impl Default for QuotaHistoryConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Model filter applied when quota data is fetched
+///
+/// 名称包含 `include` 中任一关键字（为空表示全部保留）且不包含 `exclude` 中任一关键字的模型会被保存，不区分大小写。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelFilterConfig {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl ModelFilterConfig {
+    pub fn allows(&self, model: &str) -> bool {
+        let name = model.to_lowercase();
+        let matches = |keyword: &String| name.contains(&keyword.to_lowercase());
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+/// Automatic account switch configuration
+///
+/// 当前账号有监控模型进入配额保护时，切换到剩余额度最多的可用账号。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoSwitchConfig {
+    pub enabled: bool,
+    /// 两次自动切换之间的最短间隔（分钟）
+    #[serde(default = "default_auto_switch_cooldown_minutes")]
+    pub cooldown_minutes: u32,
+    /// Antigravity 仍在活动时推迟切换（切换会重启 Antigravity）
+    #[serde(default = "default_defer_while_busy")]
+    pub defer_while_busy: bool,
+    /// Antigravity 状态数据库在该时长（秒）内没有写入才视为空闲
+    #[serde(default = "default_busy_idle_secs")]
+    pub busy_idle_secs: u64,
+    /// 允许切换到的账号（ID 或邮箱），为空表示全部
+    #[serde(default)]
+    pub allowed_accounts: Vec<String>,
+}
+
+fn default_auto_switch_cooldown_minutes() -> u32 {
+    30
+}
+
+fn default_defer_while_busy() -> bool {
+    true
+}
+
+fn default_busy_idle_secs() -> u64 {
+    120
+}
+
+impl AutoSwitchConfig {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            cooldown_minutes: default_auto_switch_cooldown_minutes(),
+            defer_while_busy: default_defer_while_busy(),
+            busy_idle_secs: default_busy_idle_secs(),
+            allowed_accounts: Vec::new(),
+        }
+    }
+
+    /// Whether the account may be switched to
+    pub fn allows(&self, account_id: &str, email: &str) -> bool {
+        self.allowed_accounts.is_empty()
+            || self
+                .allowed_accounts
+                .iter()
+                .any(|a| a == account_id || a.eq_ignore_ascii_case(email))
+    }
+}
+
+impl Default for AutoSwitchConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Account ranking strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategy {
+    /// 所选模型剩余额度最高
+    #[default]
+    HighestRemaining,
+    /// 所选模型最早重置
+    SoonestReset,
+    /// 订阅等级偏好
+    TierPreference,
+    /// 最久未使用
+    LeastRecentlyUsed,
+    /// 按 `weights` 加权混合
+    Weighted,
+}
+
+/// Factor weights for the weighted strategy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectionWeights {
+    pub remaining: f64,
+    pub reset: f64,
+    pub tier: f64,
+    pub least_recently_used: f64,
+}
+
+impl Default for SelectionWeights {
+    fn default() -> Self {
+        Self {
+            remaining: 1.0,
+            reset: 0.5,
+            tier: 0.25,
+            least_recently_used: 0.25,
+        }
+    }
+}
+
+/// Account selection configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountSelectionConfig {
+    #[serde(default)]
+    pub strategy: SelectionStrategy,
+    /// 按剩余额度、重置时间排序时参考的模型（名称或前缀）
+    #[serde(default = "default_selection_model")]
+    pub model: String,
+    /// 订阅等级偏好，靠前优先（按 subscription_tier 子串匹配，不区分大小写）
+    #[serde(default = "default_tier_preference")]
+    pub tier_preference: Vec<String>,
+    #[serde(default)]
+    pub weights: SelectionWeights,
+}
+
+fn default_selection_model() -> String {
+    "claude".to_string()
+}
+
+fn default_tier_preference() -> Vec<String> {
+    vec!["ultra".to_string(), "pro".to_string()]
+}
+
+impl AccountSelectionConfig {
+    pub fn new() -> Self {
+        Self {
+            strategy: SelectionStrategy::default(),
+            model: default_selection_model(),
+            tier_preference: default_tier_preference(),
+            weights: SelectionWeights::default(),
+        }
+    }
+}
+
+impl Default for AccountSelectionConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Retry, circuit breaker and batch concurrency limits for quota requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshPolicyConfig {
+    /// 批量刷新的并发上限；遇到 429/5xx 时自动减半，成功后逐步恢复
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+    /// 并发下限
+    #[serde(default = "default_min_concurrency")]
+    pub min_concurrency: usize,
+    /// 单个请求的最大重试次数（不含首次请求）
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// 指数退避的初始间隔（毫秒）
+    #[serde(default = "default_base_delay_ms")]
+    pub base_delay_ms: u64,
+    /// 退避间隔上限（毫秒），也用于限制 Retry-After
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// 同一 host 连续失败多少次后熔断
+    #[serde(default = "default_breaker_threshold")]
+    pub breaker_threshold: u32,
+    /// 熔断持续时间（秒），之后放行一次试探请求
+    #[serde(default = "default_breaker_cooldown_secs")]
+    pub breaker_cooldown_secs: u64,
+}
+
+fn default_max_concurrency() -> usize {
+    8
+}
+
+fn default_min_concurrency() -> usize {
+    1
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_base_delay_ms() -> u64 {
+    500
+}
+
+fn default_max_delay_ms() -> u64 {
+    30_000
+}
+
+fn default_breaker_threshold() -> u32 {
+    8
+}
+
+fn default_breaker_cooldown_secs() -> u64 {
+    30
+}
+
+impl RefreshPolicyConfig {
+    pub fn new() -> Self {
+        Self {
+            max_concurrency: default_max_concurrency(),
+            min_concurrency: default_min_concurrency(),
+            max_retries: default_max_retries(),
+            base_delay_ms: default_base_delay_ms(),
+            max_delay_ms: default_max_delay_ms(),
+            breaker_threshold: default_breaker_threshold(),
+            breaker_cooldown_secs: default_breaker_cooldown_secs(),
+        }
+    }
+}
+
+impl Default for RefreshPolicyConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Upstream endpoint overrides
+///
+/// 未设置的地址使用内置默认值；对应的 `ABV_*_URL` 环境变量优先于这里的设置。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EndpointsConfig {
+    /// fetchAvailableModels 完整地址
+    #[serde(default)]
+    pub quota_api_url: Option<String>,
+    /// loadCodeAssist 所在的 Cloud Code 基础地址
+    #[serde(default)]
+    pub cloud_code_base_url: Option<String>,
+    #[serde(default)]
+    pub token_url: Option<String>,
+    #[serde(default)]
+    pub userinfo_url: Option<String>,
+    #[serde(default)]
+    pub auth_url: Option<String>,
+}
+
+/// 通知事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    ProtectionTriggered,
+    AccountDisabled,
+    ValidationRequired,
+    SwitchCompleted,
+    SwitchFailed,
+}
+
+/// 免打扰时段（本地时间 "HH:MM"，可跨午夜），期间不弹出系统通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+}
+
+impl QuietHours {
+    /// `time` 是否落在免打扰时段内；时间格式无效时视为不在时段内
+    pub fn contains(&self, time: chrono::NaiveTime) -> bool {
+        let parse = |s: &str| chrono::NaiveTime::parse_from_str(s.trim(), "%H:%M").ok();
+        let (Some(start), Some(end)) = (parse(&self.start), parse(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+/// Notification configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    /// 系统原生通知
+    #[serde(default = "default_true")]
+    pub desktop: bool,
+    /// 向前端发送 `notifications://new` 事件
+    #[serde(default = "default_true")]
+    pub in_app: bool,
+    /// 写入应用日志
+    #[serde(default = "default_true")]
+    pub log: bool,
+    /// 关闭的事件类型
+    #[serde(default)]
+    pub disabled_events: Vec<NotificationKind>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl NotificationConfig {
+    pub fn new() -> Self {
+        Self {
+            desktop: true,
+            in_app: true,
+            log: true,
+            disabled_events: Vec::new(),
+            quiet_hours: None,
+        }
+    }
+
+    pub fn event_enabled(&self, kind: NotificationKind) -> bool {
+        !self.disabled_events.contains(&kind)
+    }
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Webhook 事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    AccountDisabled,
+    ValidationBlocked,
+    ProtectionEntered,
+    ProtectionLeft,
+    AccountSwitched,
+    /// 批量刷新结束且有失败
+    RefreshFailed,
+}
+
+/// 单个 webhook 接收地址
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    /// HMAC-SHA256 签名密钥，未设置时不签名；写入配置文件时加密
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// 订阅的事件，为空表示全部
+    #[serde(default)]
+    pub events: Vec<WebhookEventKind>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl WebhookEndpoint {
+    pub fn subscribes(&self, kind: WebhookEventKind) -> bool {
+        self.enabled && (self.events.is_empty() || self.events.contains(&kind))
+    }
+}
+
+/// Webhook configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhooksConfig {
+    #[serde(default)]
+    pub endpoints: Vec<WebhookEndpoint>,
+    /// 每次投递的最大尝试次数（含首次）
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
+    /// 首次重试的等待秒数，之后每次翻倍
+    #[serde(default = "default_webhook_retry_base_secs")]
+    pub retry_base_secs: u64,
+    #[serde(default = "default_webhook_retry_max_secs")]
+    pub retry_max_secs: u64,
+}
+
+fn default_webhook_max_attempts() -> u32 {
+    8
+}
+
+fn default_webhook_retry_base_secs() -> u64 {
+    30
+}
+
+fn default_webhook_retry_max_secs() -> u64 {
+    3600
+}
+
+impl WebhooksConfig {
+    pub fn new() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            max_attempts: default_webhook_max_attempts(),
+            retry_base_secs: default_webhook_retry_base_secs(),
+            retry_max_secs: default_webhook_retry_max_secs(),
+        }
+    }
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Metrics exporter configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// 启动本地 HTTP 端点 `/metrics`（修改后重启生效）
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_metrics_listen_addr")]
+    pub listen_addr: String,
+}
+
+fn default_metrics_listen_addr() -> String {
+    "127.0.0.1:9464".to_string()
+}
+
+impl MetricsConfig {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            listen_addr: default_metrics_listen_addr(),
+        }
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Pinned quota models configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PinnedQuotaModelsConfig {
@@ -98,6 +694,7 @@ impl Default for PinnedQuotaModelsConfig {
 impl AppConfig {
     pub fn new() -> Self {
         Self {
+            schema_version: APP_CONFIG_SCHEMA_VERSION,
             language: "zh".to_string(),
             theme: "system".to_string(),
             auto_refresh: true,
@@ -108,10 +705,20 @@ impl AppConfig {
             antigravity_executable: None,
             antigravity_args: None,
             auto_launch: false,
-            scheduled_warmup: serde_json::Value::default(),
             quota_protection: QuotaProtectionConfig::default(),
             pinned_quota_models: PinnedQuotaModelsConfig::default(),
             hidden_menu_items: Vec::new(),
+            trash_retention_days: default_trash_retention_days(),
+            quota_history: QuotaHistoryConfig::default(),
+            model_filter: ModelFilterConfig::default(),
+            auto_switch: AutoSwitchConfig::default(),
+            account_selection: AccountSelectionConfig::default(),
+            tier_cache_ttl_minutes: default_tier_cache_ttl_minutes(),
+            refresh_policy: RefreshPolicyConfig::default(),
+            endpoints: EndpointsConfig::default(),
+            notifications: NotificationConfig::default(),
+            webhooks: WebhooksConfig::default(),
+            metrics: MetricsConfig::default(),
         }
     }
 }
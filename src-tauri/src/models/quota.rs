@@ -4,8 +4,41 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelQuota {
     pub name: String,
-    pub percentage: i32,  // 剩余百分比 0-100
+    pub percentage: i32,  // 剩余百分比 0-100（向下取整）
     pub reset_time: String,
+    /// 原始剩余比例 0.0-1.0；模型不计配额时为空
+    #[serde(default)]
+    pub remaining_fraction: Option<f64>,
+    /// 解析后的重置时间（Unix 秒）
+    #[serde(default)]
+    pub reset_timestamp: Option<i64>,
+    /// fetchAvailableModels 返回的该模型完整字段
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub details: serde_json::Map<String, serde_json::Value>,
+    /// 没有 quotaInfo 的模型不计配额，汇总、保护与调度均忽略
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unmetered: bool,
+}
+
+/// 解析 RFC 3339 时间为 Unix 秒
+pub fn parse_reset_time(reset_time: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(reset_time)
+        .ok()
+        .map(|t| t.timestamp())
+}
+
+impl ModelQuota {
+    /// 重置时间（Unix 秒），旧数据没有解析结果时从 reset_time 解析
+    pub fn reset_at(&self) -> Option<i64> {
+        self.reset_timestamp
+            .or_else(|| parse_reset_time(&self.reset_time))
+    }
+
+    /// 剩余比例，旧数据没有原始值时由百分比换算
+    pub fn remaining(&self) -> f64 {
+        self.remaining_fraction
+            .unwrap_or(self.percentage.clamp(0, 100) as f64 / 100.0)
+    }
 }
 
 /// 配额数据结构
@@ -18,6 +51,9 @@ pub struct QuotaData {
     /// 禁止访问的原因 (403 详细信息)
     #[serde(default)]
     pub forbidden_reason: Option<String>,
+    /// 403 VALIDATION_REQUIRED 时需要用户打开的验证地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_url: Option<String>,
     /// 订阅等级 (FREE/PRO/ULTRA)
     #[serde(default)]
     pub subscription_tier: Option<String>,
@@ -30,6 +66,7 @@ impl QuotaData {
             last_updated: chrono::Utc::now().timestamp(),
             is_forbidden: false,
             forbidden_reason: None,
+            validation_url: None,
             subscription_tier: None,
         }
     }
@@ -38,9 +75,18 @@ impl QuotaData {
         self.models.push(ModelQuota {
             name,
             percentage,
+            reset_timestamp: parse_reset_time(&reset_time),
             reset_time,
+            remaining_fraction: None,
+            details: serde_json::Map::new(),
+            unmetered: false,
         });
     }
+
+    /// 计配额的模型（排除没有 quotaInfo 的模型）
+    pub fn metered_models(&self) -> impl Iterator<Item = &ModelQuota> {
+        self.models.iter().filter(|m| !m.unmetered)
+    }
 }
 
 impl Default for QuotaData {
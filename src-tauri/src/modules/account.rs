@@ -2,7 +2,7 @@ use serde::Serialize;
 use serde_json;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 use std::collections::HashSet;
 
@@ -11,48 +11,23 @@ use crate::models::{
     TokenData,
 };
 use crate::modules;
+use crate::modules::account_store;
+use crate::modules::notifications::NotificationEvent;
+use crate::modules::webhooks::WebhookEvent;
+use crate::modules::schema::{self, SchemaKind};
+use crate::models::config::RefreshPolicyConfig;
+use crate::utils::atomic_file;
+use crate::utils::limiter::AdaptiveLimiter;
+use crate::utils::retry;
 use once_cell::sync::Lazy;
 use std::sync::Mutex;
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::sync::Mutex as StdMutex;
-
-    // Global mutex to prevent concurrent test execution
-    static TEST_MUTEX: Lazy<StdMutex<()>> = Lazy::new(|| StdMutex::new(()));
-
-    struct TestDataDir {
-        path: PathBuf,
-    }
-
-    impl TestDataDir {
-        fn new() -> Self {
-            let temp_path = std::env::temp_dir().join(format!(
-                "antigravity_test_{}_{}",
-                std::process::id(),
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis()
-            ));
-            fs::create_dir_all(&temp_path).expect("Failed to create temp dir");
-            
-            Self {
-                path: temp_path,
-            }
-        }
-
-        fn path(&self) -> &PathBuf {
-            &self.path
-        }
-    }
-
-    impl Drop for TestDataDir {
-        fn drop(&mut self) {
-            let _ = fs::remove_dir_all(&self.path);
-        }
-    }
+    use crate::error::AppError;
+    use crate::modules::mock_server::{MockResponse, MockServer, Route, MOCK_ACCESS_TOKEN};
+    use crate::modules::test_support::{EnvOverride, TestDataDir};
 
     /// Helper to write corrupted content to accounts.json
     fn write_corrupted_index(path: &PathBuf, content: &[u8]) {
@@ -85,8 +60,8 @@ mod tests {
 
     #[test]
     fn test_load_account_index_with_bom_prefix() {
-        let _guard = TEST_MUTEX.lock().unwrap();
         let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
 
         // UTF-8 BOM followed by valid JSON
         let bom = [0xEF, 0xBB, 0xBF];
@@ -108,8 +83,8 @@ mod tests {
 
     #[test]
     fn test_load_account_index_with_nul_prefix() {
-        let _guard = TEST_MUTEX.lock().unwrap();
         let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
 
         // NUL byte prefix followed by valid JSON
         let nul = [0x00];
@@ -131,8 +106,8 @@ mod tests {
 
     #[test]
     fn test_load_account_index_with_garbage_content() {
-        let _guard = TEST_MUTEX.lock().unwrap();
         let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
 
         // Non-JSON garbage content - should trigger recovery
         write_corrupted_index(dir.path(), b"\0\0not json");
@@ -148,8 +123,8 @@ mod tests {
 
     #[test]
     fn test_load_account_index_with_empty_file() {
-        let _guard = TEST_MUTEX.lock().unwrap();
         let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
 
         // Empty file
         write_corrupted_index(dir.path(), b"");
@@ -164,8 +139,8 @@ mod tests {
 
     #[test]
     fn test_load_account_index_with_whitespace_only() {
-        let _guard = TEST_MUTEX.lock().unwrap();
         let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
 
         // Whitespace-only file
         write_corrupted_index(dir.path(), b"   \n\t  ");
@@ -180,8 +155,8 @@ mod tests {
 
     #[test]
     fn test_missing_index_with_existing_accounts() {
-        let _guard = TEST_MUTEX.lock().unwrap();
         let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
 
         // Create accounts directory with account files but NO accounts.json index
         create_account_file(dir.path(), "test-id-1", "user1@example.com");
@@ -216,21 +191,19 @@ mod tests {
 
     #[test]
     fn test_save_account_index_roundtrip() {
-        let _guard = TEST_MUTEX.lock().unwrap();
         let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
 
         // Build an AccountIndex with 2 accounts
         let now = chrono::Utc::now().timestamp();
         let index = AccountIndex {
-            version: "2.0".to_string(),
+            version: AccountIndex::new().version,
             accounts: vec![
                 AccountSummary {
                     id: "acc-1".to_string(),
                     email: "user1@example.com".to_string(),
                     name: Some("User One".to_string()),
                     disabled: false,
-                    // deprecated: 反代功能已移除，字段保留仅为兼容旧数据
-                    proxy_disabled: false,
                     protected_models: HashSet::new(),
                     created_at: now,
                     last_used: now,
@@ -240,8 +213,6 @@ mod tests {
                     email: "user2@example.com".to_string(),
                     name: None,
                     disabled: true,
-                    // deprecated: 反代功能已移除，字段保留仅为兼容旧数据
-                    proxy_disabled: false,
                     protected_models: HashSet::new(),
                     created_at: now - 100,
                     last_used: now - 50,
@@ -277,8 +248,8 @@ mod tests {
 
     #[test]
     fn test_backup_created_on_parse_failure() {
-        let _guard = TEST_MUTEX.lock().unwrap();
         let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
 
         // Create a valid account file
         create_account_file(dir.path(), "recovered-acc", "recovered@example.com");
@@ -317,51 +288,242 @@ mod tests {
 
         println!("Backup creation on parse failure: successfully created backup");
     }
+
+    #[test]
+    fn test_plaintext_tokens_encrypted_by_reseal() {
+        let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
+
+        // Legacy layout: tokens stored in plaintext
+        create_account_file(dir.path(), "legacy-acc", "legacy@example.com");
+        let account_path = dir.path().join("accounts").join("legacy-acc.json");
+        let legacy = fs::read_to_string(&account_path).unwrap();
+
+        let account = load_account_at_path(&account_path).expect("Should load legacy account");
+        assert_eq!(account.token.refresh_token, "test_refresh_token");
+        assert_eq!(account.token.access_token, "test_access_token");
+        // Reads never write back
+        assert_eq!(fs::read_to_string(&account_path).unwrap(), legacy);
+
+        assert_eq!(reseal_accounts_in_dir(dir.path()).unwrap(), 1);
+        assert_eq!(reseal_accounts_in_dir(dir.path()).unwrap(), 0);
+
+        // File on disk no longer contains plaintext tokens
+        let on_disk = fs::read_to_string(&account_path).unwrap();
+        assert!(!on_disk.contains("test_refresh_token"));
+        assert!(!on_disk.contains("test_access_token"));
+        let raw: Account = serde_json::from_str(&on_disk).unwrap();
+        assert!(crate::utils::crypto::is_encrypted_secret(&raw.token.refresh_token));
+        assert!(crate::utils::crypto::is_encrypted_secret(&raw.token.access_token));
+
+        // Second load decrypts transparently
+        let reloaded = load_account_at_path(&account_path).expect("Should load encrypted account");
+        assert_eq!(reloaded.token.refresh_token, "test_refresh_token");
+    }
+
+    #[test]
+    fn test_migrate_to_account_store_and_export_back() {
+        let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
+
+        create_account_file(dir.path(), "acc-1", "one@example.com");
+        create_account_file(dir.path(), "acc-2", "two@example.com");
+        let index = rebuild_index_from_accounts_in_dir(dir.path()).unwrap();
+        let mut reordered = index.clone();
+        reordered.accounts.reverse();
+        reordered.current_account_id = Some("acc-2".to_string());
+        save_account_index_in_dir(dir.path(), &reordered).unwrap();
+
+        let report = migrate_to_account_store_in_dir(dir.path()).expect("Should migrate");
+        assert_eq!(report.accounts, 2);
+        assert!(account_store::is_enabled(dir.path()));
+        assert!(migrate_to_account_store_in_dir(dir.path()).is_err());
+
+        let stored = account_store::load_index(dir.path()).unwrap();
+        let order: Vec<_> = stored.accounts.iter().map(|s| s.id.clone()).collect();
+        let expected: Vec<_> = reordered.accounts.iter().map(|s| s.id.clone()).collect();
+        assert_eq!(order, expected);
+        assert_eq!(stored.current_account_id.as_deref(), Some("acc-2"));
+        assert_eq!(stored.version, AccountIndex::new().version);
+
+        // Tokens stay encrypted inside the store
+        let raw = account_store::load_account(dir.path(), "acc-1").unwrap().unwrap();
+        assert!(crate::utils::crypto::is_encrypted_secret(&raw.token.refresh_token));
+
+        export_account_store_in_dir(dir.path()).expect("Should export");
+        assert!(!account_store::is_enabled(dir.path()));
+        let exported = load_account_index_in_dir(dir.path()).unwrap();
+        assert_eq!(exported.version, AccountIndex::new().version);
+        let order: Vec<_> = exported.accounts.iter().map(|s| s.id.clone()).collect();
+        assert_eq!(order, expected);
+        let account =
+            load_account_at_path(&dir.path().join("accounts").join("acc-1.json")).unwrap();
+        assert_eq!(account.token.refresh_token, "test_refresh_token");
+    }
+
+    #[test]
+    fn test_legacy_index_is_migrated_with_backup() {
+        let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
+
+        let legacy = r#"{"version":"2.0","accounts":[{"id":"acc-1","email":"a@example.com","name":null,"disabled":false,"proxy_disabled":true,"created_at":1,"last_used":2}],"current_account_id":"acc-1"}"#;
+        write_corrupted_index(dir.path(), legacy.as_bytes());
+
+        let index = load_account_index_in_dir(dir.path()).expect("Should migrate legacy index");
+        assert_eq!(index.accounts.len(), 1);
+        assert_eq!(index.version, AccountIndex::new().version);
+
+        let on_disk = fs::read_to_string(dir.path().join("accounts.json")).unwrap();
+        assert!(!on_disk.contains("proxy_disabled"));
+        let backup = schema::backup_path(dir.path(), SchemaKind::AccountIndex, Path::new("accounts.json"), 2);
+        assert_eq!(fs::read_to_string(backup).unwrap(), legacy);
+    }
+
+    #[test]
+    fn test_newer_index_is_refused_not_recovered() {
+        let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
+        create_account_file(dir.path(), "acc-1", "one@example.com");
+
+        let newer = r#"{"version":"99.0","accounts":[],"current_account_id":null,"future_field":1}"#;
+        write_corrupted_index(dir.path(), newer.as_bytes());
+
+        let err = load_account_index_in_dir(dir.path()).unwrap_err();
+        assert!(err.starts_with("unsupported_schema_version"), "{}", err);
+        assert!(save_account_index_in_dir(dir.path(), &AccountIndex::new()).is_err());
+        assert_eq!(fs::read_to_string(dir.path().join("accounts.json")).unwrap(), newer);
+    }
+
+    /// 在 mock 服务上创建一个账号并运行测试
+    fn with_mock_account<F, Fut>(test: F)
+    where
+        F: FnOnce(MockServer, Account) -> Fut,
+        Fut: std::future::Future<Output = ()>,
+    {
+        let dir = TestDataDir::new();
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let server = MockServer::start().await;
+            // 数据目录与上游地址指向临时目录和 mock 服务，drop 时恢复原环境变量
+            let _env = EnvOverride::data_dir_with(&dir, server.endpoint_env());
+            let email = "mock@example.com".to_string();
+            let account = upsert_account(
+                email.clone(),
+                Some("Mock User".into()),
+                TokenData::new("stale-access".into(), "refresh-1".into(), 3600, Some(email), None, None),
+            )
+            .expect("create account");
+            test(server, account).await;
+        });
+    }
+
+    #[test]
+    fn test_upsert_matches_email_case_insensitively() {
+        with_mock_account(|_server, account| async move {
+            let token = TokenData::new("at".into(), "refresh-2".into(), 3600, None, None, None);
+            let updated = upsert_account("Mock@Example.com".into(), None, token).unwrap();
+            assert_eq!(updated.id, account.id);
+            assert_eq!(load_account_index().unwrap().accounts.len(), 1);
+        });
+    }
+
+    #[test]
+    fn test_fetch_quota_refreshes_token_after_401() {
+        with_mock_account(|server, mut account| async move {
+            server.enqueue(Route::FetchAvailableModels, MockResponse::unauthorized());
+
+            let quota = fetch_quota_with_retry(&mut account).await.expect("quota after refresh");
+            let claude = quota.models.iter().find(|m| m.name == "claude-sonnet-4-6").unwrap();
+            assert_eq!(claude.percentage, 80);
+            assert_eq!(quota.subscription_tier.as_deref(), Some("g1-pro-tier"));
+
+            let token_requests = server.requests(Route::Token);
+            assert_eq!(token_requests.len(), 1);
+            assert!(token_requests[0].body.contains("refresh_token=refresh-1"));
+            let quota_requests = server.requests(Route::FetchAvailableModels);
+            assert_eq!(quota_requests.len(), 2);
+            assert_eq!(quota_requests[0].authorization.as_deref(), Some("Bearer stale-access"));
+            let expected = format!("Bearer {}", MOCK_ACCESS_TOKEN);
+            assert_eq!(quota_requests[1].authorization.as_deref(), Some(expected.as_str()));
+            assert_eq!(load_account(&account.id).unwrap().token.access_token, MOCK_ACCESS_TOKEN);
+        });
+    }
+
+    #[test]
+    fn test_fetch_quota_disables_account_on_invalid_grant() {
+        with_mock_account(|server, mut account| async move {
+            server.enqueue(Route::FetchAvailableModels, MockResponse::unauthorized());
+            server.set_default(Route::Token, MockResponse::invalid_grant());
+
+            let result = fetch_quota_with_retry(&mut account).await;
+            assert!(matches!(result, Err(AppError::OAuth(_))), "{:?}", result);
+            let stored = load_account(&account.id).unwrap();
+            assert!(stored.disabled);
+            assert!(stored.disabled_reason.unwrap().starts_with("invalid_grant"));
+            assert_eq!(server.requests(Route::FetchAvailableModels).len(), 1);
+        });
+    }
+
+    #[test]
+    fn test_fetch_quota_retries_429_and_stops_on_403() {
+        with_mock_account(|server, mut account| async move {
+            server.enqueue(Route::FetchAvailableModels, MockResponse::rate_limited(0));
+            let (result, throttled) = retry::count_throttles(fetch_quota_with_retry(&mut account)).await;
+            let quota = result.expect("quota after 429");
+            assert!(!quota.is_forbidden);
+            assert_eq!(throttled, 1);
+            assert_eq!(server.requests(Route::FetchAvailableModels).len(), 2);
+            assert!(server.requests(Route::Token).is_empty());
+
+            // 403 不重试也不刷新 Token
+            server.enqueue(
+                Route::FetchAvailableModels,
+                MockResponse::validation_required("https://accounts.google.com/signin/continue"),
+            );
+            let quota = fetch_quota_with_retry(&mut account).await.expect("forbidden quota");
+            assert!(quota.is_forbidden);
+            assert_eq!(server.requests(Route::FetchAvailableModels).len(), 3);
+            assert!(server.requests(Route::Token).is_empty());
+        });
+    }
 }
 
 /// Global account write lock to prevent corruption during concurrent operations
 static ACCOUNT_INDEX_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
-// ... existing constants ...
-const DATA_DIR: &str = ".antigravity_tools";
+/// Holds the in-process index mutex together with the cross-process data directory lock
+struct AccountIndexGuard {
+    _data_dir: modules::data_lock::DataDirLock,
+    _process: std::sync::MutexGuard<'static, ()>,
+}
+
+/// Acquire exclusive access to the account index for a read-modify-write cycle
+fn lock_account_index() -> Result<AccountIndexGuard, String> {
+    let process = ACCOUNT_INDEX_LOCK
+        .lock()
+        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let data_dir = modules::data_lock::acquire()?;
+    Ok(AccountIndexGuard {
+        _data_dir: data_dir,
+        _process: process,
+    })
+}
+
 const ACCOUNTS_INDEX: &str = "accounts.json";
 const ACCOUNTS_DIR: &str = "accounts";
 
-/// Get data directory path
+/// Get data directory path (see `modules::paths` for resolution order)
 pub fn get_data_dir() -> Result<PathBuf, String> {
-    // [NEW] Support custom data directory via environment variable
-    if let Ok(env_path) = std::env::var("ABV_DATA_DIR") {
-        if !env_path.trim().is_empty() {
-            let data_dir = PathBuf::from(env_path);
-            if !data_dir.exists() {
-                fs::create_dir_all(&data_dir).map_err(|e| format!("failed_to_create_custom_data_dir: {}", e))?;
-            }
-            return Ok(data_dir);
-        }
-    }
-
-    let home = dirs::home_dir().ok_or("failed_to_get_home_dir")?;
-    let data_dir = home.join(DATA_DIR);
-
-    // Ensure directory exists
-    if !data_dir.exists() {
-        fs::create_dir_all(&data_dir).map_err(|e| format!("failed_to_create_data_dir: {}", e))?;
-    }
-
-    Ok(data_dir)
+    modules::paths::data_dir()
 }
 
 /// Get accounts directory path
 pub fn get_accounts_dir() -> Result<PathBuf, String> {
-    let data_dir = get_data_dir()?;
-    let accounts_dir = data_dir.join(ACCOUNTS_DIR);
-
-    if !accounts_dir.exists() {
-        fs::create_dir_all(&accounts_dir)
-            .map_err(|e| format!("failed_to_create_accounts_dir: {}", e))?;
-    }
-
-    Ok(accounts_dir)
+    modules::paths::accounts_dir()
 }
 
 /// Load account index from a specific directory (internal helper)
@@ -404,51 +566,53 @@ fn load_account_index_in_dir(data_dir: &PathBuf) -> Result<AccountIndex, String>
     }
 
     // Try to parse sanitized content
-    match serde_json::from_str::<AccountIndex>(&sanitized) {
-        Ok(index) => {
-            crate::modules::logger::log_info(&format!(
-                "Successfully loaded index with {} accounts",
-                index.accounts.len()
-            ));
-            Ok(index)
-        }
-        Err(parse_err) => {
-            crate::modules::logger::log_error(&format!(
-                "Failed to parse account index: {}. Attempting recovery from accounts directory",
-                parse_err
-            ));
-            let recovered = rebuild_index_from_accounts_in_dir(data_dir)?;
-            try_save_recovered_index(data_dir, &index_path, &recovered, Some(&raw_content))?;
-            Ok(recovered)
+    let parse_err = match serde_json::from_str::<serde_json::Value>(&sanitized) {
+        Ok(mut value) => {
+            // An index written by a newer version is refused outright, never "recovered" over
+            let migrated = schema::migrate_loaded(
+                SchemaKind::AccountIndex,
+                data_dir,
+                &index_path,
+                &raw_content,
+                &mut value,
+            )?;
+            match serde_json::from_value::<AccountIndex>(value) {
+                Ok(index) => {
+                    crate::modules::logger::log_info(&format!(
+                        "Successfully loaded index with {} accounts",
+                        index.accounts.len()
+                    ));
+                    if migrated {
+                        try_save_index(data_dir, &index, "migrated");
+                    }
+                    return Ok(index);
+                }
+                Err(e) => e,
+            }
         }
-    }
+        Err(e) => e,
+    };
+
+    crate::modules::logger::log_error(&format!(
+        "Failed to parse account index: {}. Attempting recovery from accounts directory",
+        parse_err
+    ));
+    let recovered = rebuild_index_from_accounts_in_dir(data_dir)?;
+    try_save_recovered_index(data_dir, &index_path, &recovered, Some(&raw_content))?;
+    Ok(recovered)
 }
 
 /// Save account index to a specific directory (internal helper)
-fn save_account_index_in_dir(data_dir: &PathBuf, index: &AccountIndex) -> Result<(), String> {
+fn save_account_index_in_dir(data_dir: &Path, index: &AccountIndex) -> Result<(), String> {
     let index_path = data_dir.join(ACCOUNTS_INDEX);
-    // Use unique temp file name per write to avoid collision
-    let temp_filename = format!("{}.tmp.{}", ACCOUNTS_INDEX, Uuid::new_v4());
-    let temp_path = data_dir.join(&temp_filename);
+    schema::ensure_supported_file(SchemaKind::AccountIndex, &index_path)?;
 
     let content = serde_json::to_string_pretty(index)
         .map_err(|e| format!("failed_to_serialize_account_index: {}", e))?;
 
-    // Write to temporary file
-    if let Err(e) = fs::write(&temp_path, content) {
-        // Clean up temp file on failure
-        let _ = fs::remove_file(&temp_path);
-        return Err(format!("failed_to_write_temp_index_file: {}", e));
-    }
-
-    // Atomic rename with platform-specific handling
-    if let Err(e) = atomic_replace_file(&temp_path, &index_path) {
-        // Clean up temp file on failure
-        let _ = fs::remove_file(&temp_path);
-        return Err(format!("failed_to_replace_index_file: {}", e));
-    }
-
-    Ok(())
+    // Durable temp file + rename, keeping the previous index as accounts.json.bak
+    atomic_file::write_atomic_with_backup(&index_path, content)
+        .map_err(|e| format!("failed_to_replace_index_file: {}", e))
 }
 
 /// Rebuild AccountIndex by scanning accounts/*.json files in specific directory
@@ -469,8 +633,6 @@ fn rebuild_index_from_accounts_in_dir(data_dir: &PathBuf) -> Result<AccountIndex
                                         email: account.email,
                                         name: account.name,
                                         disabled: account.disabled,
-                                        // deprecated: 反代功能已移除，始终为 false
-                                        proxy_disabled: false,
                                         protected_models: account.protected_models,
                                         created_at: account.created_at,
                                         last_used: account.last_used,
@@ -504,22 +666,150 @@ fn rebuild_index_from_accounts_in_dir(data_dir: &PathBuf) -> Result<AccountIndex
     ));
 
     Ok(AccountIndex {
-        version: "2.0".to_string(),
         accounts: summaries,
         current_account_id,
+        ..AccountIndex::new()
     })
 }
 
 /// Load account from a specific path (internal helper)
-fn load_account_at_path(account_path: &PathBuf) -> Result<Account, String> {
+fn load_account_at_path(account_path: &Path) -> Result<Account, String> {
+    read_account_at_path(account_path).map(|(account, _)| account)
+}
+
+/// Parse, migrate and decrypt an account file. Returns true if the file should be
+/// rewritten; reads never write back, see [`reseal_accounts`].
+fn read_account_at_path(account_path: &Path) -> Result<(Account, bool), String> {
     let content = fs::read_to_string(account_path)
         .map_err(|e| format!("failed_to_read_account_data: {}", e))?;
-    serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_account_data: {}", e))
+    let mut value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("failed_to_parse_account_data: {}", e))?;
+
+    // accounts/<id>.json -> data dir
+    let data_dir = account_path
+        .parent()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new("."));
+    let migrated = schema::migrate_loaded(
+        SchemaKind::Account,
+        data_dir,
+        account_path,
+        content.as_bytes(),
+        &mut value,
+    )?;
+    let mut account: Account = serde_json::from_value(value)
+        .map_err(|e| format!("failed_to_parse_account_data: {}", e))?;
+
+    let needs_reseal = open_account_tokens(&mut account)?;
+    Ok((account, needs_reseal || migrated))
+}
+
+/// Rewrite accounts whose tokens are plaintext or sealed with a retired key, or whose
+/// file still has an old schema. Runs once at startup under the account lock, so a
+/// concurrent save is never overwritten by a stale copy; afterwards every save reseals.
+pub fn reseal_accounts() -> Result<usize, String> {
+    let _lock = lock_account_index()?;
+    reseal_accounts_in_dir(&get_data_dir()?)
+}
+
+fn reseal_accounts_in_dir(data_dir: &Path) -> Result<usize, String> {
+    let mut rewritten = 0;
+    if account_store::is_enabled(data_dir) {
+        for mut account in account_store::list_accounts(data_dir)? {
+            let result = open_account_tokens(&mut account).and_then(|needs_reseal| {
+                needs_reseal
+                    .then(|| write_stored_account(data_dir, &account))
+                    .transpose()
+            });
+            match result {
+                Ok(Some(())) => rewritten += 1,
+                Ok(None) => {}
+                Err(e) => crate::modules::logger::log_warn(&format!(
+                    "Failed to re-encrypt tokens for account {}: {}",
+                    account.email, e
+                )),
+            }
+        }
+        return Ok(rewritten);
+    }
+
+    let accounts_dir = data_dir.join(ACCOUNTS_DIR);
+    if !accounts_dir.exists() {
+        return Ok(0);
+    }
+    let entries =
+        fs::read_dir(&accounts_dir).map_err(|e| format!("failed_to_read_accounts_dir: {}", e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let result = read_account_at_path(&path).and_then(|(account, needs_rewrite)| {
+            needs_rewrite
+                .then(|| write_account_at_path(&path, &account))
+                .transpose()
+        });
+        match result {
+            Ok(Some(())) => rewritten += 1,
+            Ok(None) => {}
+            Err(e) => crate::modules::logger::log_warn(&format!(
+                "Failed to rewrite account file {:?}: {}",
+                path.file_name().unwrap_or_default(),
+                e
+            )),
+        }
+    }
+    Ok(rewritten)
+}
+
+/// Serialize account with encrypted tokens and write to a specific path (internal helper)
+fn write_account_at_path(account_path: &Path, account: &Account) -> Result<(), String> {
+    schema::ensure_supported_file(SchemaKind::Account, account_path)?;
+    let mut sealed = account.clone();
+    seal_account_tokens(&mut sealed)?;
+
+    let content = serde_json::to_string_pretty(&sealed)
+        .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
+
+    atomic_file::write_atomic(account_path, content)
+        .map_err(|e| format!("failed_to_save_account_data: {}", e))
+}
+
+/// Encrypt access_token / refresh_token before they are written to disk
+fn seal_account_tokens(account: &mut Account) -> Result<(), String> {
+    use crate::utils::crypto;
+
+    for secret in [&mut account.token.access_token, &mut account.token.refresh_token] {
+        if !secret.is_empty() && !crypto::is_encrypted_secret(secret) {
+            *secret = crypto::encrypt_secret(secret)
+                .map_err(|e| format!("failed_to_encrypt_account_token: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Decrypt tokens loaded from disk. Returns true if any token was stored in plaintext
+/// or under a retired key and should be written back.
+fn open_account_tokens(account: &mut Account) -> Result<bool, String> {
+    use crate::utils::crypto;
+
+    let mut needs_reseal = false;
+    for secret in [&mut account.token.access_token, &mut account.token.refresh_token] {
+        needs_reseal |= crypto::needs_reencryption(secret);
+        if crypto::is_encrypted_secret(secret) {
+            *secret = crypto::decrypt_secret(secret)
+                .map_err(|e| format!("failed_to_decrypt_account_token: {}", e))?;
+        }
+    }
+    Ok(needs_reseal)
 }
 
 /// Load account index with recovery support
 pub fn load_account_index() -> Result<AccountIndex, String> {
     let data_dir = get_data_dir()?;
+    if account_store::is_enabled(&data_dir) {
+        return account_store::load_index(&data_dir);
+    }
     load_account_index_in_dir(&data_dir)
 }
 
@@ -545,7 +835,7 @@ fn sanitize_index_content(raw: &[u8]) -> String {
 
 /// Best-effort save of recovered index without deadlocking
 fn try_save_recovered_index(
-    data_dir: &PathBuf,
+    data_dir: &Path,
     _index_path: &PathBuf,
     index: &AccountIndex,
     corrupt_content: Option<&[u8]>,
@@ -555,7 +845,7 @@ fn try_save_recovered_index(
         let timestamp = chrono::Utc::now().timestamp();
         let backup_name = format!("accounts.json.corrupt-{}-{}", timestamp, Uuid::new_v4());
         let backup_path = data_dir.join(&backup_name);
-        if let Err(e) = fs::write(&backup_path, content) {
+        if let Err(e) = atomic_file::write_atomic(&backup_path, content) {
             crate::modules::logger::log_warn(&format!(
                 "Failed to backup corrupt index to {}: {}",
                 backup_name, e
@@ -568,102 +858,345 @@ fn try_save_recovered_index(
         }
     }
 
+    try_save_index(data_dir, index, "recovered");
+    Ok(())
+}
+
+/// Best-effort save of a recovered or migrated index; skipped if any lock is busy
+fn try_save_index(data_dir: &Path, index: &AccountIndex, kind: &str) {
     // Try to acquire lock without blocking - if we can't get it, skip saving
-    match ACCOUNT_INDEX_LOCK.try_lock() {
-        Ok(_guard) => {
+    // Also skip if another process currently holds the data directory lock
+    let data_lock = modules::data_lock::acquire_in_dir_with_timeout(data_dir, std::time::Duration::ZERO);
+    match (ACCOUNT_INDEX_LOCK.try_lock(), data_lock) {
+        (Ok(_guard), Ok(_data_lock)) => {
             if let Err(e) = save_account_index_in_dir(data_dir, index) {
                 crate::modules::logger::log_warn(&format!(
-                    "Failed to save recovered index: {}. Will retry on next load.",
-                    e
+                    "Failed to save {} index: {}. Will retry on next load.",
+                    kind, e
                 ));
             } else {
-                crate::modules::logger::log_info("Successfully saved recovered index");
+                crate::modules::logger::log_info(&format!("Successfully saved {} index", kind));
             }
         }
-        Err(_) => {
-            crate::modules::logger::log_warn(
-                "Could not acquire lock to save recovered index. Will retry on next load."
-            );
+        _ => {
+            crate::modules::logger::log_warn(&format!(
+                "Could not acquire lock to save {} index. Will retry on next load.",
+                kind
+            ));
         }
     }
-
-    Ok(())
 }
 
 /// Save account index (atomic write)
 pub fn save_account_index(index: &AccountIndex) -> Result<(), String> {
     let data_dir = get_data_dir()?;
+    let _data_lock = modules::data_lock::acquire()?;
+    if account_store::is_enabled(&data_dir) {
+        return account_store::save_index(&data_dir, index);
+    }
     save_account_index_in_dir(&data_dir, index)
 }
 
-/// Platform-specific atomic file replacement
-#[cfg(target_os = "windows")]
-fn atomic_replace_file(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
-    use std::os::windows::ffi::OsStrExt;
+/// Load account data
+pub fn load_account(account_id: &str) -> Result<Account, String> {
+    let data_dir = get_data_dir()?;
+    if account_store::is_enabled(&data_dir) {
+        let account = account_store::load_account(&data_dir, account_id)?
+            .ok_or_else(|| format!("failed_to_read_account_data: account {} not found", account_id))?;
+        return open_stored_account(account);
+    }
 
-    type Bool = i32;
-    type Dword = u32;
+    let accounts_dir = get_accounts_dir()?;
+    let account_path = accounts_dir.join(format!("{}.json", account_id));
+    load_account_at_path(&account_path)
+}
 
-    #[link(name = "Kernel32")]
-    extern "system" {
-        fn MoveFileExW(lp_existing_file_name: *const u16, lp_new_file_name: *const u16, dw_flags: Dword) -> Bool;
+/// Save account data
+pub fn save_account(account: &Account) -> Result<(), String> {
+    let data_dir = get_data_dir()?;
+    let _data_lock = modules::data_lock::acquire()?;
+    if account_store::is_enabled(&data_dir) {
+        return write_stored_account(&data_dir, account);
     }
 
-    let src_wide: Vec<u16> = src
-        .as_os_str()
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect();
-    let dst_wide: Vec<u16> = dst
-        .as_os_str()
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect();
+    let accounts_dir = get_accounts_dir()?;
+    let account_path = accounts_dir.join(format!("{}.json", account.id));
+    write_account_at_path(&account_path, account)
+}
+
+/// Save account data and index; the SQLite store writes both in one transaction
+fn save_account_with_index(account: &Account, index: &AccountIndex) -> Result<(), String> {
+    let data_dir = get_data_dir()?;
+    let _data_lock = modules::data_lock::acquire()?;
+    if account_store::is_enabled(&data_dir) {
+        let mut sealed = account.clone();
+        seal_account_tokens(&mut sealed)?;
+        return account_store::save_account_with_index(&data_dir, &sealed, index);
+    }
+
+    save_account(account)?;
+    save_account_index_in_dir(&data_dir, index)
+}
+
+/// Decrypt tokens of an account read from the SQLite store
+fn open_stored_account(mut account: Account) -> Result<Account, String> {
+    open_account_tokens(&mut account)?;
+    Ok(account)
+}
+
+/// Encrypt tokens and write account to the SQLite store
+fn write_stored_account(data_dir: &Path, account: &Account) -> Result<(), String> {
+    let mut sealed = account.clone();
+    seal_account_tokens(&mut sealed)?;
+    account_store::save_account(data_dir, &sealed)
+}
 
-    // MOVEFILE_REPLACE_EXISTING = 0x1
-    // MOVEFILE_WRITE_THROUGH = 0x8
-    const MOVEFILE_REPLACE_EXISTING: u32 = 0x1;
-    const MOVEFILE_WRITE_THROUGH: u32 = 0x8;
-    let flags = MOVEFILE_REPLACE_EXISTING | MOVEFILE_WRITE_THROUGH;
+/// Save the index without the deleted accounts, then remove their data.
+/// The SQLite store does both in one transaction; for JSON files the index is the source of truth.
+fn remove_accounts_with_index(
+    data_dir: &Path,
+    index: &AccountIndex,
+    account_ids: &[String],
+) -> Result<(), String> {
+    let _data_lock = modules::data_lock::acquire()?;
+    if account_store::is_enabled(data_dir) {
+        return account_store::delete_accounts_with_index(data_dir, account_ids, index);
+    }
 
-    let result = unsafe { MoveFileExW(src_wide.as_ptr(), dst_wide.as_ptr(), flags) };
-    if result == 0 {
-        let err = std::io::Error::last_os_error();
-        // Clean up source file on failure
-        let _ = fs::remove_file(src);
-        return Err(format!("MoveFileExW failed: {}", err));
+    save_account_index_in_dir(data_dir, index)?;
+    if let Err(e) = remove_account_files(account_ids) {
+        crate::modules::logger::log_warn(&format!("Failed to delete account data: {}", e));
     }
+    Ok(())
+}
 
+/// Remove account JSON files (index is handled by the caller)
+fn remove_account_files(account_ids: &[String]) -> Result<(), String> {
+    let accounts_dir = get_accounts_dir()?;
+    for account_id in account_ids {
+        let account_path = accounts_dir.join(format!("{}.json", account_id));
+        if account_path.exists() {
+            fs::remove_file(&account_path)
+                .map_err(|e| format!("failed_to_delete_account_file: {}", e))?;
+        }
+    }
     Ok(())
 }
 
-/// Non-Windows: use standard rename
-#[cfg(not(target_os = "windows"))]
-fn atomic_replace_file(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
-    fs::rename(src, dst).map_err(|e| format!("rename failed: {}", e))
+/// Get account storage backend status
+pub fn get_account_store_status() -> Result<account_store::AccountStoreStatus, String> {
+    let data_dir = get_data_dir()?;
+    Ok(account_store::status(&data_dir))
 }
 
-/// Load account data
-pub fn load_account(account_id: &str) -> Result<Account, String> {
-    let accounts_dir = get_accounts_dir()?;
-    let account_path = accounts_dir.join(format!("{}.json", account_id));
-    load_account_at_path(&account_path)
+/// One-shot migration of the JSON layout (accounts.json + accounts/*.json) into accounts.db.
+/// The JSON files are left in place as a backup; once accounts.db exists it takes precedence.
+pub fn migrate_to_account_store() -> Result<account_store::AccountStoreReport, String> {
+    let _lock = lock_account_index()?;
+    let data_dir = get_data_dir()?;
+    migrate_to_account_store_in_dir(&data_dir)
 }
 
-/// Save account data
-pub fn save_account(account: &Account) -> Result<(), String> {
+/// Migrate JSON layout in a specific directory into accounts.db (internal helper)
+fn migrate_to_account_store_in_dir(
+    data_dir: &PathBuf,
+) -> Result<account_store::AccountStoreReport, String> {
+    if account_store::is_enabled(data_dir) {
+        return Err("account_store_already_enabled".to_string());
+    }
+
+    let index = load_account_index_in_dir(data_dir)?;
+
+    let mut accounts = Vec::new();
+    let accounts_dir = data_dir.join(ACCOUNTS_DIR);
+    if accounts_dir.exists() {
+        let entries = fs::read_dir(&accounts_dir)
+            .map_err(|e| format!("failed_to_read_accounts_dir: {}", e))?;
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().is_some_and( |ext| ext == "json") {
+                let mut account = load_account_at_path(&path)?;
+                seal_account_tokens(&mut account)?;
+                accounts.push(account);
+            }
+        }
+    }
+
+    // Build the database under a temporary name so a failed migration never enables the store
+    let store_path = account_store::store_path(data_dir);
+    let temp_path = data_dir.join(format!("{}.tmp.{}", account_store::STORE_DB, Uuid::new_v4()));
+    let imported = account_store::import_layout(&temp_path, &index, &accounts)
+        .and_then(|_| {
+            atomic_file::replace_file(&temp_path, &store_path).map_err(|e| e.to_string())
+        });
+    if let Err(e) = imported {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("failed_to_migrate_account_store: {}", e));
+    }
+
+    crate::modules::logger::log_info(&format!(
+        "Migrated {} account(s) ({} indexed) into {:?}",
+        accounts.len(),
+        index.accounts.len(),
+        store_path
+    ));
+
+    Ok(account_store::AccountStoreReport {
+        accounts: accounts.len(),
+        indexed: index.accounts.len(),
+        path: store_path.to_string_lossy().to_string(),
+    })
+}
+
+/// Export accounts.db back to the JSON layout and disable the SQLite store.
+/// The database is kept as accounts.db.exported-<timestamp>.
+pub fn export_account_store_to_json() -> Result<account_store::AccountStoreReport, String> {
+    let _lock = lock_account_index()?;
+    let data_dir = get_data_dir()?;
+    export_account_store_in_dir(&data_dir)
+}
+
+/// Export accounts.db in a specific directory to the JSON layout (internal helper)
+fn export_account_store_in_dir(
+    data_dir: &Path,
+) -> Result<account_store::AccountStoreReport, String> {
+    if !account_store::is_enabled(data_dir) {
+        return Err("account_store_not_enabled".to_string());
+    }
+
+    let (index, accounts) = account_store::load_layout(data_dir)?;
+
+    let accounts_dir = data_dir.join(ACCOUNTS_DIR);
+    fs::create_dir_all(&accounts_dir)
+        .map_err(|e| format!("failed_to_create_accounts_dir: {}", e))?;
+    for account in &accounts {
+        // Tokens are already sealed in the store, write them as-is
+        let content = serde_json::to_string_pretty(account)
+            .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
+        atomic_file::write_atomic(&accounts_dir.join(format!("{}.json", account.id)), content)
+            .map_err(|e| format!("failed_to_save_account_data: {}", e))?;
+    }
+    save_account_index_in_dir(data_dir, &index)?;
+
+    let store_path = account_store::store_path(data_dir);
+    let archived = data_dir.join(format!(
+        "{}.exported-{}",
+        account_store::STORE_DB,
+        chrono::Utc::now().timestamp()
+    ));
+    fs::rename(&store_path, &archived)
+        .map_err(|e| format!("failed_to_disable_account_store: {}", e))?;
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = data_dir.join(format!("{}{}", account_store::STORE_DB, suffix));
+        if sidecar.exists() {
+            let _ = fs::remove_file(&sidecar);
+        }
+    }
+
+    crate::modules::logger::log_info(&format!(
+        "Exported {} account(s) from SQLite store back to JSON layout",
+        accounts.len()
+    ));
+
+    Ok(account_store::AccountStoreReport {
+        accounts: accounts.len(),
+        indexed: index.accounts.len(),
+        path: accounts_dir.to_string_lossy().to_string(),
+    })
+}
+
+/// Result of an encryption key rotation
+#[derive(Debug, Serialize)]
+pub struct KeyRotationResult {
+    pub key_id: String,
+    pub reencrypted: usize,
+    pub retired_keys: usize,
+}
+
+/// Rotate the at-rest encryption key and re-encrypt every stored account with it
+/// (SQLite store rows and JSON files, which remain as a backup after migration).
+/// Old keys are only retired once all accounts were re-encrypted successfully.
+pub fn rotate_encryption_key() -> Result<KeyRotationResult, String> {
+    let _lock = lock_account_index()?;
+
+    let key_id = crate::utils::crypto::rotate_encryption_key()?;
+
+    let mut reencrypted = 0;
+    let mut failures = Vec::new();
+
+    let data_dir = get_data_dir()?;
+    if account_store::is_enabled(&data_dir) {
+        let (_, accounts) = account_store::load_layout(&data_dir)?;
+        for account in accounts {
+            let id = account.id.clone();
+            match open_stored_account(account)
+                .and_then(|account| write_stored_account(&data_dir, &account))
+            {
+                Ok(()) => reencrypted += 1,
+                Err(e) => failures.push(format!("{}: {}", id, e)),
+            }
+        }
+    }
+
     let accounts_dir = get_accounts_dir()?;
-    let account_path = accounts_dir.join(format!("{}.json", account.id));
+    let entries =
+        fs::read_dir(&accounts_dir).map_err(|e| format!("failed_to_read_accounts_dir: {}", e))?;
 
-    let content = serde_json::to_string_pretty(account)
-        .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        match load_account_at_path(&path).and_then(|account| write_account_at_path(&path, &account))
+        {
+            Ok(()) => reencrypted += 1,
+            Err(e) => failures.push(format!("{:?}: {}", path.file_name().unwrap_or_default(), e)),
+        }
+    }
+
+    // Trashed accounts must stay restorable after old keys are retired
+    for mut entry in modules::trash::list_entries(&data_dir)? {
+        let result = open_account_tokens(&mut entry.account)
+            .and_then(|_| seal_account_tokens(&mut entry.account))
+            .and_then(|_| modules::trash::write_entry(&data_dir, &entry));
+        match result {
+            Ok(()) => reencrypted += 1,
+            Err(e) => failures.push(format!("trash {}: {}", entry.id, e)),
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(format!(
+            "key_rotation_incomplete: {} account(s) still use previous keys ({})",
+            failures.len(),
+            failures.join("; ")
+        ));
+    }
 
-    fs::write(&account_path, content).map_err(|e| format!("failed_to_save_account_data: {}", e))
+    let retired_keys = crate::utils::crypto::retire_inactive_keys()?;
+    crate::modules::logger::log_info(&format!(
+        "Re-encrypted {} account(s) with key {}, retired {} old key(s)",
+        reencrypted, key_id, retired_keys
+    ));
+
+    Ok(KeyRotationResult {
+        key_id,
+        reencrypted,
+        retired_keys,
+    })
 }
 
 /// List all accounts
 pub fn list_accounts() -> Result<Vec<Account>, String> {
     crate::modules::logger::log_info("Listing accounts...");
+    let data_dir = get_data_dir()?;
+    if account_store::is_enabled(&data_dir) {
+        return account_store::list_accounts(&data_dir)?
+            .into_iter()
+            .map(open_stored_account)
+            .collect();
+    }
+
     let index = load_account_index()?;
     let mut accounts = Vec::new();
 
@@ -685,19 +1218,26 @@ pub fn list_accounts() -> Result<Vec<Account>, String> {
     Ok(accounts)
 }
 
+/// Emails are compared case-insensitively when matching accounts
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn same_email(a: &str, b: &str) -> bool {
+    normalize_email(a) == normalize_email(b)
+}
+
 /// Add account
 pub fn add_account(
     email: String,
     name: Option<String>,
     token: TokenData,
 ) -> Result<Account, String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = lock_account_index()?;
     let mut index = load_account_index()?;
 
     // Check if account already exists
-    if index.accounts.iter().any(|s| s.email == email) {
+    if index.accounts.iter().any(|s| same_email(&s.email, &email)) {
         return Err(format!("Account already exists: {}", email));
     }
 
@@ -706,17 +1246,12 @@ pub fn add_account(
     let mut account = Account::new(account_id.clone(), email.clone(), token);
     account.name = name.clone();
 
-    // Save account data
-    save_account(&account)?;
-
     // Update index
     index.accounts.push(AccountSummary {
         id: account.id.clone(),
         email: account.email.clone(),
         name: account.name.clone(),
         disabled: account.disabled,
-        // deprecated: 反代功能已移除，始终为 false
-        proxy_disabled: false,
         protected_models: account.protected_models.clone(),
         created_at: account.created_at,
         last_used: account.last_used,
@@ -727,7 +1262,8 @@ pub fn add_account(
         index.current_account_id = Some(account_id);
     }
 
-    save_account_index(&index)?;
+    save_account_with_index(&account, &index)?;
+    modules::audit::record(&account.id, &account.email, modules::audit::AuditEvent::Added);
 
     Ok(account)
 }
@@ -738,16 +1274,14 @@ pub fn upsert_account(
     name: Option<String>,
     token: TokenData,
 ) -> Result<Account, String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = lock_account_index()?;
     let mut index = load_account_index()?;
 
     // Find account ID if exists
     let existing_account_id = index
         .accounts
         .iter()
-        .find(|s| s.email == email)
+        .find(|s| same_email(&s.email, &email))
         .map(|s| s.id.clone());
 
     if let Some(account_id) = existing_account_id {
@@ -769,13 +1303,12 @@ pub fn upsert_account(
                     account.disabled_at = None;
                 }
                 account.update_last_used();
-                save_account(&account)?;
 
                 // Sync name in index
                 if let Some(idx_summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
                     idx_summary.name = name;
-                    save_account_index(&index)?;
                 }
+                save_account_with_index(&account, &index)?;
 
                 return Ok(account);
             }
@@ -787,13 +1320,12 @@ pub fn upsert_account(
                 // Index exists but file is missing, recreating
                 let mut account = Account::new(account_id.clone(), email.clone(), token);
                 account.name = name.clone();
-                save_account(&account)?;
 
                 // Sync name in index
                 if let Some(idx_summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
                     idx_summary.name = name;
-                    save_account_index(&index)?;
                 }
+                save_account_with_index(&account, &index)?;
 
                 return Ok(account);
             }
@@ -811,34 +1343,36 @@ pub fn upsert_account(
 
 /// Delete account
 pub fn delete_account(account_id: &str) -> Result<(), String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = lock_account_index()?;
     let mut index = load_account_index()?;
 
+    let Some(email) = index
+        .accounts
+        .iter()
+        .find(|s| s.id == account_id)
+        .map(|s| s.email.clone())
+    else {
+        return Err(format!("Account ID not found: {}", account_id));
+    };
+
+    // Keep a recoverable copy before touching the index
+    let data_dir = get_data_dir()?;
+    let account_ids = [account_id.to_string()];
+    let trashed = move_accounts_to_trash(&data_dir, &index, &account_ids)?;
+
     // Remove from index
-    let original_len = index.accounts.len();
     index.accounts.retain(|s| s.id != account_id);
 
-    if index.accounts.len() == original_len {
-        return Err(format!("Account ID not found: {}", account_id));
-    }
-
     // Clear current account if it's being deleted
     if index.current_account_id.as_deref() == Some(account_id) {
         index.current_account_id = index.accounts.first().map(|s| s.id.clone());
     }
 
-    save_account_index(&index)?;
-
-    // Delete account file
-    let accounts_dir = get_accounts_dir()?;
-    let account_path = accounts_dir.join(format!("{}.json", account_id));
-
-    if account_path.exists() {
-        fs::remove_file(&account_path)
-            .map_err(|e| format!("failed_to_delete_account_file: {}", e))?;
+    if let Err(e) = remove_accounts_with_index(&data_dir, &index, &account_ids) {
+        let _ = modules::trash::remove_entries(&data_dir, &trashed);
+        return Err(e);
     }
+    modules::audit::record(account_id, &email, modules::audit::AuditEvent::Deleted);
 
     // deprecated: 反代功能已移除，TokenManager 缓存清理不再需要
 
@@ -847,12 +1381,18 @@ pub fn delete_account(account_id: &str) -> Result<(), String> {
 
 /// Batch delete accounts (atomic index operation)
 pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = lock_account_index()?;
     let mut index = load_account_index()?;
 
-    let accounts_dir = get_accounts_dir()?;
+    // Keep recoverable copies before touching the index
+    let data_dir = get_data_dir()?;
+    let trashed = move_accounts_to_trash(&data_dir, &index, account_ids)?;
+    let deleted: Vec<AccountSummary> = index
+        .accounts
+        .iter()
+        .filter(|s| account_ids.contains(&s.id))
+        .cloned()
+        .collect();
 
     for account_id in account_ids {
         // Remove from index
@@ -863,12 +1403,6 @@ pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
             index.current_account_id = None;
         }
 
-        // Delete account file
-        let account_path = accounts_dir.join(format!("{}.json", account_id));
-        if account_path.exists() {
-            let _ = fs::remove_file(&account_path);
-        }
-
         // deprecated: 反代功能已移除，TokenManager 缓存清理不再需要
     }
 
@@ -877,15 +1411,127 @@ pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
         index.current_account_id = index.accounts.first().map(|s| s.id.clone());
     }
 
-    save_account_index(&index)
+    if let Err(e) = remove_accounts_with_index(&data_dir, &index, account_ids) {
+        let _ = modules::trash::remove_entries(&data_dir, &trashed);
+        return Err(e);
+    }
+    for summary in &deleted {
+        modules::audit::record(&summary.id, &summary.email, modules::audit::AuditEvent::Deleted);
+    }
+
+    Ok(())
+}
+
+/// Whether an account still has data on disk (JSON file or store row)
+fn account_data_exists(data_dir: &Path, account_id: &str) -> Result<bool, String> {
+    if account_store::is_enabled(data_dir) {
+        return Ok(account_store::load_account(data_dir, account_id)?.is_some());
+    }
+    Ok(get_accounts_dir()?.join(format!("{}.json", account_id)).exists())
+}
+
+/// Write trash entries for accounts about to be deleted (caller holds the index lock).
+/// Fails without side effects if any existing account cannot be copied.
+fn move_accounts_to_trash(
+    data_dir: &Path,
+    index: &AccountIndex,
+    account_ids: &[String],
+) -> Result<Vec<String>, String> {
+    let deleted_at = chrono::Utc::now().timestamp();
+    let mut trashed = Vec::new();
+
+    for account_id in account_ids {
+        let Some(position) = index.accounts.iter().position(|s| &s.id == account_id) else {
+            continue;
+        };
+
+        let result = load_account(account_id).and_then(|mut account| {
+            seal_account_tokens(&mut account)?;
+            let entry = modules::trash::TrashEntry {
+                id: Uuid::new_v4().to_string(),
+                deleted_at,
+                position: Some(position),
+                was_current: index.current_account_id.as_deref() == Some(account_id.as_str()),
+                account,
+            };
+            modules::trash::write_entry(data_dir, &entry)?;
+            Ok(entry.id)
+        });
+
+        match result {
+            Ok(entry_id) => trashed.push(entry_id),
+            // Nothing left to recover: index entry without data
+            Err(e) if !account_data_exists(data_dir, account_id).unwrap_or(true) => {
+                crate::modules::logger::log_warn(&format!(
+                    "Account {} has no data to move to trash: {}",
+                    account_id, e
+                ));
+            }
+            Err(e) => {
+                let _ = modules::trash::remove_entries(data_dir, &trashed);
+                return Err(format!("failed_to_move_account_to_trash: {}: {}", account_id, e));
+            }
+        }
+    }
+
+    Ok(trashed)
+}
+
+/// Restore an account from trash to its original index position
+pub fn restore_account_from_trash(entry_id: &str) -> Result<Account, String> {
+    let _lock = lock_account_index()?;
+    let data_dir = get_data_dir()?;
+    let entry = modules::trash::read_entry(&data_dir, entry_id)?;
+    let mut index = load_account_index()?;
+
+    if index
+        .accounts
+        .iter()
+        .any(|s| s.id == entry.account.id || same_email(&s.email, &entry.account.email))
+    {
+        return Err(format!(
+            "account_already_exists: {} is already in the account list",
+            entry.account.email
+        ));
+    }
+
+    let mut account = entry.account;
+    open_account_tokens(&mut account)?;
+
+    let position = entry
+        .position
+        .unwrap_or(index.accounts.len())
+        .min(index.accounts.len());
+    index.accounts.insert(
+        position,
+        AccountSummary {
+            id: account.id.clone(),
+            email: account.email.clone(),
+            name: account.name.clone(),
+            disabled: account.disabled,
+            protected_models: account.protected_models.clone(),
+            created_at: account.created_at,
+            last_used: account.last_used,
+        },
+    );
+    if index.current_account_id.is_none() {
+        index.current_account_id = Some(account.id.clone());
+    }
+    save_account_with_index(&account, &index)?;
+
+    modules::trash::remove_entries(&data_dir, &[entry_id.to_string()])?;
+    modules::audit::record(&account.id, &account.email, modules::audit::AuditEvent::Restored);
+    crate::modules::logger::log_info(&format!(
+        "Restored account {} from trash at position {}",
+        account.email, position
+    ));
+    Ok(account)
 }
 
 /// Reorder account list
 /// Update account order in index file based on provided IDs
 pub fn reorder_accounts(account_ids: &[String]) -> Result<(), String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = lock_account_index()?;
     let mut index = load_account_index()?;
 
     // Create a map of account ID to summary
@@ -920,17 +1566,50 @@ pub fn reorder_accounts(account_ids: &[String]) -> Result<(), String> {
     save_account_index(&index)
 }
 
-/// Switch current account (Core Logic)
+/// Switch current account and notify the result
 pub async fn switch_account(
     account_id: &str,
     integration: &(impl modules::integration::SystemIntegration + ?Sized),
+) -> Result<(), String> {
+    let previous_account_id = get_current_account_id().ok().flatten();
+    let result = switch_account_core(account_id, integration).await;
+    let source = modules::audit::current_source();
+    modules::metrics::record_switch(source, result.is_ok());
+    let email = load_account(account_id).ok().map(|a| a.email);
+    if result.is_ok() && previous_account_id.as_deref() != Some(account_id) {
+        modules::webhooks::dispatch(WebhookEvent::AccountSwitched {
+            account_id: account_id.to_string(),
+            email: email.clone().unwrap_or_default(),
+            previous_account_id,
+            source,
+        });
+    }
+    let event = match &result {
+        Ok(()) => NotificationEvent::SwitchCompleted {
+            account_id: account_id.to_string(),
+            email: email.unwrap_or_default(),
+            source,
+        },
+        Err(e) => NotificationEvent::SwitchFailed {
+            account_id: account_id.to_string(),
+            email,
+            error: e.clone(),
+            source,
+        },
+    };
+    modules::notifications::notify(event);
+    result
+}
+
+/// Switch current account (Core Logic)
+async fn switch_account_core(
+    account_id: &str,
+    integration: &(impl modules::integration::SystemIntegration + ?Sized),
 ) -> Result<(), String> {
     use crate::modules::oauth;
 
     let index = {
-        let _lock = ACCOUNT_INDEX_LOCK
-            .lock()
-            .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+        let _lock = lock_account_index()?;
         load_account_index()?
     };
 
@@ -954,6 +1633,7 @@ pub async fn switch_account(
     if fresh_token.access_token != account.token.access_token {
         account.token = fresh_token.clone();
         save_account(&account)?;
+        modules::audit::record(&account.id, &account.email, modules::audit::AuditEvent::TokenRefreshed);
     }
 
     // [FIX] Ensure account has a device profile for isolation
@@ -976,9 +1656,7 @@ pub async fn switch_account(
 
     // 4. Update tool internal state
     {
-        let _lock = ACCOUNT_INDEX_LOCK
-            .lock()
-            .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+        let _lock = lock_account_index()?;
         let mut index = load_account_index()?;
         index.current_account_id = Some(account_id.to_string());
         save_account_index(&index)?;
@@ -986,6 +1664,7 @@ pub async fn switch_account(
 
     account.update_last_used();
     save_account(&account)?;
+    modules::audit::record(&account.id, &account.email, modules::audit::AuditEvent::Switched);
 
     crate::modules::logger::log_info(&format!(
         "Account switch core logic completed: {}",
@@ -1176,71 +1855,130 @@ pub fn get_current_account() -> Result<Option<Account>, String> {
 
 /// Set current active account ID
 pub fn set_current_account_id(account_id: &str) -> Result<(), String> {
-    let _lock = ACCOUNT_INDEX_LOCK
-        .lock()
-        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let _lock = lock_account_index()?;
     let mut index = load_account_index()?;
     index.current_account_id = Some(account_id.to_string());
     save_account_index(&index)
 }
 
+/// 根据最新配额同步 403 VALIDATION_REQUIRED 状态，返回是否新进入验证阻止
+fn sync_validation_state(account: &mut Account) -> bool {
+    let Some(quota) = account.quota.as_ref() else {
+        return false;
+    };
+    if quota.is_forbidden
+        && quota.forbidden_reason.as_deref() == Some(modules::quota::VALIDATION_REQUIRED)
+    {
+        let started = !account.validation_blocked;
+        account.validation_blocked = true;
+        account.validation_blocked_reason = quota.forbidden_reason.clone();
+        account.validation_url = quota.validation_url.clone().or(account.validation_url.take());
+        return started;
+    }
+    if !quota.is_forbidden && account.validation_blocked {
+        account.validation_blocked = false;
+        account.validation_blocked_until = None;
+        account.validation_blocked_reason = None;
+        account.validation_url = None;
+    }
+    false
+}
+
 /// Update account quota
 pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), String> {
+    let config = crate::modules::config::load_app_config().ok();
+    // Hold the data dir lock from load to save so another process cannot interleave its write
+    let data_lock = modules::data_lock::acquire()?;
     let mut account = load_account(account_id)?;
+    let was_forbidden = account.quota.as_ref().is_some_and(|q| q.is_forbidden);
     account.update_quota(quota);
-
-    // --- Quota protection logic start ---
-    if let Ok(config) = crate::modules::config::load_app_config() {
-        if config.quota_protection.enabled {
-            if let Some(ref q) = account.quota {
-                let threshold = config.quota_protection.threshold_percentage as i32;
-
-                let mut group_min_percentage: HashMap<String, i32> = HashMap::new();
-
-                for model in &q.models {
-                    // deprecated: 反代已移除，直接使用模型原始名称作为分组键
-                    let std_id = model.name.clone();
-                    let entry = group_min_percentage.entry(std_id).or_insert(100);
-                    if model.percentage < *entry {
-                        *entry = model.percentage;
-                    }
-                }
-
-                for std_id in &config.quota_protection.monitored_models {
-                    let min_pct = group_min_percentage.get(std_id).cloned().unwrap_or(100);
-
-                    if min_pct <= threshold {
-                        if !account.protected_models.contains(std_id) {
-                            crate::modules::logger::log_info(&format!(
-                                "[Quota] Triggering model protection: {} (Group: {} Min: {}% <= Thres: {}%)",
-                                account.email, std_id, min_pct, threshold
-                            ));
-                            account.protected_models.insert(std_id.clone());
-                        }
-                    } else {
-                        if account.protected_models.contains(std_id) {
-                            crate::modules::logger::log_info(&format!(
-                                "[Quota] Model protection recovered: {} (Group: {} Min: {}% > Thres: {}%)",
-                                account.email, std_id, min_pct, threshold
-                            ));
-                            account.protected_models.remove(std_id);
-                        }
-                    }
-                }
-
-            }
-        }
-    }
-    // --- Quota protection logic end ---
+    let forbidden_changed = account.quota.as_ref().is_some_and(|q| q.is_forbidden) != was_forbidden;
+    let validation_started = sync_validation_state(&mut account);
+    let protection_changes = match config {
+        Some(config) => modules::quota_protection::evaluate(
+            &mut account,
+            &config.quota_protection,
+            chrono::Utc::now().timestamp(),
+        ),
+        None => Vec::new(),
+    };
 
     // Save account first
     save_account(&account)?;
+    drop(data_lock);
+
+    if let Some(ref q) = account.quota {
+        modules::quota_history::record(&account.id, q);
+    }
+    // 定时刷新只在账号状态变化时写入审计日志，避免日志被例行刷新淹没
+    if modules::audit::current_source().is_user_initiated()
+        || forbidden_changed
+        || validation_started
+        || !protection_changes.is_empty()
+    {
+        modules::audit::record(&account.id, &account.email, modules::audit::AuditEvent::QuotaRefreshed);
+    }
+    let newly_protected: Vec<String> = protection_changes
+        .iter()
+        .filter(|c| c.protected)
+        .map(|c| c.model.clone())
+        .collect();
+    if validation_started {
+        modules::notifications::notify(NotificationEvent::ValidationRequired {
+            account_id: account.id.clone(),
+            email: account.email.clone(),
+            validation_url: account.validation_url.clone(),
+        });
+        modules::webhooks::dispatch(WebhookEvent::ValidationBlocked {
+            account_id: account.id.clone(),
+            email: account.email.clone(),
+            reason: account.validation_blocked_reason.clone(),
+            validation_url: account.validation_url.clone(),
+        });
+    }
+    for change in &protection_changes {
+        let (account_id, email) = (account.id.clone(), account.email.clone());
+        let (model, reason) = (change.model.clone(), change.reason.clone());
+        modules::webhooks::dispatch(if change.protected {
+            WebhookEvent::ProtectionEntered { account_id, email, model, reason }
+        } else {
+            WebhookEvent::ProtectionLeft { account_id, email, model, reason }
+        });
+    }
+    if !newly_protected.is_empty() {
+        modules::notifications::notify(NotificationEvent::ProtectionTriggered {
+            account_id: account.id.clone(),
+            email: account.email.clone(),
+            models: newly_protected.clone(),
+        });
+    }
+    if !newly_protected.is_empty()
+        && get_current_account_id().ok().flatten().as_deref() == Some(account_id)
+    {
+        modules::auto_switch::notify_protected(account_id, newly_protected);
+    }
+    if !protection_changes.is_empty() {
+        let select = |protected: bool| -> Vec<String> {
+            protection_changes
+                .iter()
+                .filter(|c| c.protected == protected)
+                .map(|c| c.model.clone())
+                .collect()
+        };
+        modules::audit::record(
+            &account.id,
+            &account.email,
+            modules::audit::AuditEvent::ProtectedModelsChanged {
+                added: select(true),
+                removed: select(false),
+                changes: protection_changes,
+            },
+        );
+    }
 
     // [FIX] 同时更新索引文件中的摘要信息，确保列表页图标即时刷新
     {
-        let _lock = ACCOUNT_INDEX_LOCK
-            .lock()
-            .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+        let _lock = lock_account_index()?;
         if let Ok(mut index) = load_account_index() {
             if let Some(summary) = index.accounts.iter_mut().find(|a| a.id == account_id) {
                 summary.protected_models = account.protected_models.clone();
@@ -1254,6 +1992,18 @@ pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), St
     Ok(())
 }
 
+/// [`update_account_quota`] on the blocking pool, so waiting for another process's
+/// data dir lock does not stall a tokio worker (the audit source is carried over)
+pub async fn update_account_quota_async(account_id: &str, quota: QuotaData) -> Result<(), String> {
+    let account_id = account_id.to_string();
+    let source = modules::audit::current_source();
+    tokio::task::spawn_blocking(move || {
+        modules::audit::sync_scope(source, || update_account_quota(&account_id, quota))
+    })
+    .await
+    .map_err(|e| format!("update_account_quota_task_failed: {}", e))?
+}
+
 /// Export accounts by IDs (for backup/migration)
 pub fn export_accounts_by_ids(account_ids: &[String]) -> Result<crate::models::AccountExportResponse, String> {
     use crate::models::{AccountExportItem, AccountExportResponse};
@@ -1263,9 +2013,18 @@ pub fn export_accounts_by_ids(account_ids: &[String]) -> Result<crate::models::A
     let export_items: Vec<AccountExportItem> = accounts
         .into_iter()
         .filter(|acc| account_ids.contains(&acc.id))
-        .map(|acc| AccountExportItem {
-            email: acc.email,
-            refresh_token: acc.token.refresh_token,
+        .map(|acc| {
+            modules::audit::record(
+                &acc.id,
+                &acc.email,
+                modules::audit::AuditEvent::Exported {
+                    format: "json".to_string(),
+                },
+            );
+            AccountExportItem {
+                email: acc.email,
+                refresh_token: acc.token.refresh_token,
+            }
         })
         .collect();
 
@@ -1287,6 +2046,36 @@ pub fn export_accounts() -> Result<Vec<(String, String)>, String> {
     Ok(exports)
 }
 
+/// refresh_token 已失效（invalid_grant）：禁用账号、记录审计并发出通知
+fn disable_for_invalid_grant(account: &mut Account, error: &str, context: &str) {
+    modules::logger::log_error(&format!(
+        "Disabling account {} due to invalid_grant during {} (quota check)",
+        account.email, context
+    ));
+    let reason = format!("invalid_grant: {}", error);
+    account.disabled = true;
+    account.disabled_at = Some(chrono::Utc::now().timestamp());
+    account.disabled_reason = Some(reason.clone());
+    let _ = save_account(account);
+    modules::audit::record(
+        &account.id,
+        &account.email,
+        modules::audit::AuditEvent::Disabled {
+            reason: reason.clone(),
+        },
+    );
+    modules::notifications::notify(NotificationEvent::AccountDisabled {
+        account_id: account.id.clone(),
+        email: account.email.clone(),
+        reason: reason.clone(),
+    });
+    modules::webhooks::dispatch(WebhookEvent::AccountDisabled {
+        account_id: account.id.clone(),
+        email: account.email.clone(),
+        reason,
+    });
+}
+
 /// Quota query with retry (moved from commands to modules for reuse)
 pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppResult<QuotaData> {
     use crate::error::AppError;
@@ -1298,15 +2087,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
         Ok(t) => t,
         Err(e) => {
             if e.contains("invalid_grant") {
-                modules::logger::log_error(&format!(
-                    "Disabling account {} due to invalid_grant during token refresh (quota check)",
-                    account.email
-                ));
-                account.disabled = true;
-                account.disabled_at = Some(chrono::Utc::now().timestamp());
-                account.disabled_reason = Some(format!("invalid_grant: {}", e));
-                let _ = save_account(account);
-                // deprecated: 反代功能已移除
+                disable_for_invalid_grant(account, &e, "token refresh");
             }
             return Err(AppError::OAuth(e));
         }
@@ -1330,6 +2111,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
 
         account.name = name.clone();
         upsert_account(account.email.clone(), name, token.clone()).map_err(AppError::Account)?;
+        modules::audit::record(&account.id, &account.email, modules::audit::AuditEvent::TokenRefreshed);
     }
 
     // 0. Supplement display name (if missing or upper step failed)
@@ -1398,15 +2180,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                     Ok(t) => t,
                     Err(e) => {
                         if e.contains("invalid_grant") {
-                            modules::logger::log_error(&format!(
-                                "Disabling account {} due to invalid_grant during forced refresh (quota check)",
-                                account.email
-                            ));
-                            account.disabled = true;
-                            account.disabled_at = Some(chrono::Utc::now().timestamp());
-                            account.disabled_reason = Some(format!("invalid_grant: {}", e));
-                            let _ = save_account(account);
-                            // deprecated: 反代功能已移除
+                            disable_for_invalid_grant(account, &e, "forced refresh");
                         }
                         return Err(AppError::OAuth(e));
                     }
@@ -1437,6 +2211,11 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                 account.name = name.clone();
                 upsert_account(account.email.clone(), name, new_token.clone())
                     .map_err(AppError::Account)?;
+                modules::audit::record(
+                    &account.id,
+                    &account.email,
+                    modules::audit::AuditEvent::TokenRefreshed,
+                );
 
                 // Retry query
                 let retry_result: crate::error::AppResult<(QuotaData, Option<String>)> =
@@ -1486,22 +2265,8 @@ pub struct RefreshStats {
 
 /// Core logic to batch refresh all account quotas (decoupled from Tauri status)
 pub async fn refresh_all_quotas_logic() -> Result<RefreshStats, String> {
-    use futures::future::join_all;
-    use std::sync::Arc;
-    use tokio::sync::Semaphore;
-
-    const MAX_CONCURRENT: usize = 5;
-    let start = std::time::Instant::now();
-
-    crate::modules::logger::log_info(&format!(
-        "Starting batch refresh of all account quotas (Concurrent mode, max: {})",
-        MAX_CONCURRENT
-    ));
-    let accounts = list_accounts()?;
-
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT));
-
-    let tasks: Vec<_> = accounts
+    crate::modules::logger::log_info("Starting batch refresh of all account quotas");
+    let accounts: Vec<Account> = list_accounts()?
         .into_iter()
         .filter(|account| {
             if account.disabled {
@@ -1522,16 +2287,68 @@ pub async fn refresh_all_quotas_logic() -> Result<RefreshStats, String> {
             }
             true
         })
+        .collect();
+
+    refresh_quotas_for(accounts).await
+}
+
+/// 批量刷新共享的自适应并发限制，跨批次保留调整后的并发
+static REFRESH_LIMITER: Lazy<std::sync::Arc<AdaptiveLimiter>> = Lazy::new(|| {
+    let policy = RefreshPolicyConfig::default();
+    AdaptiveLimiter::new(policy.min_concurrency, policy.max_concurrency)
+});
+
+/// Shared limiter for upstream account requests, with bounds synced to the refresh policy
+pub fn refresh_limiter(policy: &RefreshPolicyConfig) -> std::sync::Arc<AdaptiveLimiter> {
+    let limiter = REFRESH_LIMITER.clone();
+    limiter.set_bounds(policy.min_concurrency, policy.max_concurrency);
+    limiter
+}
+
+/// Refresh quotas for the given accounts concurrently (callers decide which accounts to include)
+pub async fn refresh_quotas_for(accounts: Vec<Account>) -> Result<RefreshStats, String> {
+    use futures::future::join_all;
+
+    let start = std::time::Instant::now();
+    let policy = crate::modules::config::load_app_config()
+        .map(|config| config.refresh_policy)
+        .unwrap_or_default();
+    let limiter = refresh_limiter(&policy);
+
+    crate::modules::logger::log_info(&format!(
+        "Refreshing quotas for {} account(s) (Adaptive concurrency: {}, range {}-{})",
+        accounts.len(),
+        limiter.limit(),
+        policy.min_concurrency,
+        policy.max_concurrency
+    ));
+
+    let tasks: Vec<_> = accounts
+        .into_iter()
         .map(|mut account| {
             let email = account.email.clone();
             let account_id = account.id.clone();
-            let permit = semaphore.clone();
+            let limiter = limiter.clone();
             async move {
-                let _guard = permit.acquire().await.unwrap();
+                let _permit = limiter.acquire().await;
                 crate::modules::logger::log_info(&format!("  - Processing {}", email));
-                match fetch_quota_with_retry(&mut account).await {
+                let (result, throttled) =
+                    retry::count_throttles(fetch_quota_with_retry(&mut account)).await;
+                // 本账号的请求（含重试）出现过 429/5xx 即视为上游限流
+                if throttled > 0 {
+                    if let Some(limit) = limiter.on_throttle() {
+                        crate::modules::logger::log_warn(&format!(
+                            "Upstream throttling detected, concurrency reduced to {}",
+                            limit
+                        ));
+                    }
+                } else if result.is_ok() {
+                    limiter.on_success();
+                }
+                modules::metrics::record_account_refresh(&account_id, result.is_ok());
+                match result {
                     Ok(quota) => {
-                        if let Err(e) = update_account_quota(&account_id, quota) {
+                        if let Err(e) = update_account_quota_async(&account_id, quota).await {
                             let msg = format!("Account {}: Save quota failed - {}", email, e);
                             crate::modules::logger::log_error(&msg);
                             Err(msg)
@@ -1569,16 +2386,27 @@ pub async fn refresh_all_quotas_logic() -> Result<RefreshStats, String> {
 
     let elapsed = start.elapsed();
     crate::modules::logger::log_info(&format!(
-        "Batch refresh completed: {} success, {} failed, took: {}ms",
+        "Batch refresh completed: {} success, {} failed, took: {}ms, concurrency now: {}",
         success,
         failed,
-        elapsed.as_millis()
+        elapsed.as_millis(),
+        limiter.limit()
     ));
+    if failed > 0 {
+        modules::webhooks::dispatch(WebhookEvent::RefreshFailed {
+            total,
+            success,
+            failed,
+            errors: details.clone(),
+        });
+    }
 
-    Ok(RefreshStats {
+    let stats = RefreshStats {
         total,
         success,
         failed,
         details,
-    })
+    };
+    modules::metrics::record_refresh_batch(&stats);
+    Ok(stats)
 }
@@ -0,0 +1,494 @@
+//! 加密账号导出包
+//!
+//! 用于在团队机器间迁移账号池：密码经 Argon2id 派生密钥，AES-256-GCM 加密账号数据，
+//! 明文头部（版本、创建时间、账号数量、KDF 参数）作为 AAD 参与认证，篡改即导入失败。
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{DeviceProfile, DeviceProfileVersion, QuotaData, TokenData};
+use crate::modules;
+
+const BUNDLE_FORMAT: &str = "antigravity-account-bundle";
+const BUNDLE_VERSION: u32 = 1;
+const CIPHER: &str = "aes-256-gcm";
+const KDF_ALGORITHM: &str = "argon2id";
+const SALT_LEN: usize = 16;
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// Argon2id 参数（OWASP 推荐的最低配置）
+const DEFAULT_KDF: KdfParams = KdfParams {
+    algorithm: String::new(),
+    salt: String::new(),
+    m_cost: 19 * 1024,
+    t_cost: 2,
+    p_cost: 1,
+};
+
+/// 导入时接受的 KDF 成本上限，防止恶意头部耗尽内存 / CPU（m_cost 单位为 KiB）
+const MAX_KDF_M_COST: u32 = 256 * 1024;
+const MAX_KDF_T_COST: u32 = 10;
+const MAX_KDF_P_COST: u32 = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KdfParams {
+    pub algorithm: String,
+    pub salt: String,
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+/// 导出包明文头部（整体作为 AAD）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundleHeader {
+    pub format: String,
+    pub version: u32,
+    pub created_at: i64,
+    pub account_count: usize,
+    pub app_version: String,
+    pub includes: BundleExportOptions,
+    pub kdf: KdfParams,
+    pub cipher: String,
+    pub nonce: String,
+}
+
+/// 导出包文件结构
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountBundle {
+    #[serde(flatten)]
+    pub header: BundleHeader,
+    pub ciphertext: String,
+}
+
+/// 导出选项：默认仅包含邮箱与 refresh_token
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct BundleExportOptions {
+    #[serde(default)]
+    pub labels: bool,
+    #[serde(default)]
+    pub device_profiles: bool,
+    #[serde(default)]
+    pub quota: bool,
+}
+
+/// 导出包中的单个账号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleAccount {
+    pub email: String,
+    pub refresh_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_profile: Option<DeviceProfile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub device_history: Vec<DeviceProfileVersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<QuotaData>,
+}
+
+/// 单个账号的导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleImportItem {
+    pub email: String,
+    /// created / updated / failed
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 导入报告
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleImportReport {
+    pub created_at: i64,
+    pub total: usize,
+    pub imported: usize,
+    pub failed: usize,
+    pub results: Vec<BundleImportItem>,
+}
+
+fn derive_key(password: &str, kdf: &KdfParams) -> Result<[u8; 32], String> {
+    if kdf.algorithm != KDF_ALGORITHM {
+        return Err(format!("unsupported_bundle_kdf: {}", kdf.algorithm));
+    }
+    if kdf.m_cost > MAX_KDF_M_COST
+        || kdf.t_cost > MAX_KDF_T_COST
+        || kdf.p_cost > MAX_KDF_P_COST
+    {
+        return Err(format!(
+            "bundle_kdf_params_too_expensive: m={} KiB, t={}, p={} (max m={} KiB, t={}, p={})",
+            kdf.m_cost, kdf.t_cost, kdf.p_cost, MAX_KDF_M_COST, MAX_KDF_T_COST, MAX_KDF_P_COST
+        ));
+    }
+    let salt = general_purpose::STANDARD
+        .decode(&kdf.salt)
+        .map_err(|e| format!("invalid_bundle_salt: {}", e))?;
+    let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32))
+        .map_err(|e| format!("invalid_bundle_kdf_params: {}", e))?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), &salt, &mut key)
+        .map_err(|e| format!("bundle_key_derivation_failed: {}", e))?;
+    Ok(key)
+}
+
+fn header_aad(header: &BundleHeader) -> Result<Vec<u8>, String> {
+    serde_json::to_vec(header).map_err(|e| format!("failed_to_serialize_bundle_header: {}", e))
+}
+
+/// 按给定的 KDF 成本参数把账号加密为导出包
+fn seal_bundle(
+    accounts: &[BundleAccount],
+    password: &str,
+    options: BundleExportOptions,
+    kdf_cost: &KdfParams,
+) -> Result<AccountBundle, String> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(format!(
+            "bundle_password_too_short: at least {} characters required",
+            MIN_PASSWORD_LEN
+        ));
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = KdfParams {
+        algorithm: KDF_ALGORITHM.to_string(),
+        salt: general_purpose::STANDARD.encode(salt),
+        ..kdf_cost.clone()
+    };
+    let key = derive_key(password, &kdf)?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let header = BundleHeader {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        created_at: chrono::Utc::now().timestamp(),
+        account_count: accounts.len(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        includes: options,
+        kdf,
+        cipher: CIPHER.to_string(),
+        nonce: general_purpose::STANDARD.encode(nonce),
+    };
+
+    let plaintext = serde_json::to_vec(accounts)
+        .map_err(|e| format!("failed_to_serialize_bundle_accounts: {}", e))?;
+    let aad = header_aad(&header)?;
+    let ciphertext = Aes256Gcm::new(&key.into())
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: &plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|e| format!("bundle_encryption_failed: {}", e))?;
+
+    Ok(AccountBundle {
+        header,
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    })
+}
+
+/// 校验头部并解密导出包
+fn open_bundle(bundle: &AccountBundle, password: &str) -> Result<Vec<BundleAccount>, String> {
+    let header = &bundle.header;
+    if header.format != BUNDLE_FORMAT {
+        return Err("invalid_bundle_format".to_string());
+    }
+    if header.version > BUNDLE_VERSION {
+        return Err(format!(
+            "unsupported_bundle_version: {} (this app supports up to {})",
+            header.version, BUNDLE_VERSION
+        ));
+    }
+    if header.cipher != CIPHER {
+        return Err(format!("unsupported_bundle_cipher: {}", header.cipher));
+    }
+
+    let key = derive_key(password, &header.kdf)?;
+    let nonce = general_purpose::STANDARD
+        .decode(&header.nonce)
+        .map_err(|e| format!("invalid_bundle_nonce: {}", e))?;
+    if nonce.len() != 12 {
+        return Err("invalid_bundle_nonce_length".to_string());
+    }
+    let ciphertext = general_purpose::STANDARD
+        .decode(&bundle.ciphertext)
+        .map_err(|e| format!("invalid_bundle_ciphertext: {}", e))?;
+
+    let aad = header_aad(header)?;
+    let plaintext = Aes256Gcm::new(&key.into())
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| "bundle_decryption_failed: wrong password or corrupted bundle".to_string())?;
+
+    let accounts: Vec<BundleAccount> = serde_json::from_slice(&plaintext)
+        .map_err(|e| format!("failed_to_parse_bundle_accounts: {}", e))?;
+    if accounts.len() != header.account_count {
+        return Err(format!(
+            "bundle_account_count_mismatch: header {} vs payload {}",
+            header.account_count,
+            accounts.len()
+        ));
+    }
+    Ok(accounts)
+}
+
+/// 导出选中账号为加密导出包（JSON 字符串，由前端保存为文件）
+pub fn export_bundle(
+    account_ids: &[String],
+    password: &str,
+    options: BundleExportOptions,
+) -> Result<String, String> {
+    let selected: Vec<_> = modules::account::list_accounts()?
+        .into_iter()
+        .filter(|acc| account_ids.contains(&acc.id))
+        .collect();
+    let exported: Vec<(String, String)> = selected
+        .iter()
+        .map(|acc| (acc.id.clone(), acc.email.clone()))
+        .collect();
+    let accounts: Vec<BundleAccount> = selected
+        .into_iter()
+        .map(|acc| BundleAccount {
+            email: acc.email,
+            refresh_token: acc.token.refresh_token,
+            name: acc.name,
+            project_id: acc.token.project_id,
+            custom_label: acc.custom_label.filter(|_| options.labels),
+            device_profile: acc.device_profile.filter(|_| options.device_profiles),
+            device_history: if options.device_profiles {
+                acc.device_history
+            } else {
+                Vec::new()
+            },
+            quota: acc.quota.filter(|_| options.quota),
+        })
+        .collect();
+
+    if accounts.is_empty() {
+        return Err("no_accounts_selected".to_string());
+    }
+
+    let bundle = seal_bundle(&accounts, password, options, &DEFAULT_KDF)?;
+    for (account_id, email) in &exported {
+        modules::audit::record(
+            account_id,
+            email,
+            modules::audit::AuditEvent::Exported {
+                format: "bundle".to_string(),
+            },
+        );
+    }
+    modules::logger::log_info(&format!(
+        "Exported encrypted bundle with {} account(s)",
+        accounts.len()
+    ));
+    serde_json::to_string_pretty(&bundle).map_err(|e| format!("failed_to_serialize_bundle: {}", e))
+}
+
+/// 读取导出包头部（无需密码，用于导入前预览）
+pub fn inspect_bundle(content: &str) -> Result<BundleHeader, String> {
+    let bundle: AccountBundle =
+        serde_json::from_str(content).map_err(|e| format!("invalid_bundle_file: {}", e))?;
+    if bundle.header.format != BUNDLE_FORMAT {
+        return Err("invalid_bundle_format".to_string());
+    }
+    Ok(bundle.header)
+}
+
+fn import_one(item: BundleAccount) -> Result<bool, String> {
+    if item.email.trim().is_empty() || item.refresh_token.trim().is_empty() {
+        return Err("missing_email_or_refresh_token".to_string());
+    }
+
+    let existed = modules::account::load_account_index()?
+        .accounts
+        .iter()
+        .any(|s| s.email == item.email);
+
+    // access_token 留空且立即过期，首次使用时通过 refresh_token 刷新
+    let token = TokenData::new(
+        String::new(),
+        item.refresh_token,
+        0,
+        Some(item.email.clone()),
+        item.project_id,
+        None,
+    );
+    let mut account = modules::account::upsert_account(item.email, item.name, token)?;
+
+    let mut dirty = false;
+    if item.custom_label.is_some() {
+        account.custom_label = item.custom_label;
+        dirty = true;
+    }
+    if item.device_profile.is_some() {
+        account.device_profile = item.device_profile;
+        account.device_history = item.device_history;
+        dirty = true;
+    }
+    if item.quota.is_some() {
+        account.quota = item.quota;
+        dirty = true;
+    }
+    if dirty {
+        modules::account::save_account(&account)?;
+    }
+
+    Ok(existed)
+}
+
+/// 校验并导入加密导出包，返回逐账号结果
+pub fn import_bundle(content: &str, password: &str) -> Result<BundleImportReport, String> {
+    let bundle: AccountBundle =
+        serde_json::from_str(content).map_err(|e| format!("invalid_bundle_file: {}", e))?;
+    let accounts = open_bundle(&bundle, password)?;
+
+    let mut results = Vec::with_capacity(accounts.len());
+    for item in accounts {
+        let email = item.email.clone();
+        let result = match import_one(item) {
+            Ok(existed) => BundleImportItem {
+                email,
+                status: if existed { "updated" } else { "created" }.to_string(),
+                error: None,
+            },
+            Err(e) => {
+                modules::logger::log_warn(&format!("Bundle import failed for {}: {}", email, e));
+                BundleImportItem {
+                    email,
+                    status: "failed".to_string(),
+                    error: Some(e),
+                }
+            }
+        };
+        results.push(result);
+    }
+
+    let failed = results.iter().filter(|r| r.status == "failed").count();
+    modules::logger::log_info(&format!(
+        "Imported bundle: {} account(s), {} failed",
+        results.len(),
+        failed
+    ));
+
+    Ok(BundleImportReport {
+        created_at: bundle.header.created_at,
+        total: results.len(),
+        imported: results.len() - failed,
+        failed,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试使用低成本 KDF 参数
+    fn test_kdf() -> KdfParams {
+        KdfParams {
+            m_cost: 256,
+            t_cost: 1,
+            ..DEFAULT_KDF
+        }
+    }
+
+    fn sample_accounts() -> Vec<BundleAccount> {
+        vec![BundleAccount {
+            email: "team@example.com".to_string(),
+            refresh_token: "1//refresh".to_string(),
+            name: Some("Team".to_string()),
+            project_id: None,
+            custom_label: Some("pool-a".to_string()),
+            device_profile: None,
+            device_history: Vec::new(),
+            quota: Some(QuotaData::new()),
+        }]
+    }
+
+    fn options() -> BundleExportOptions {
+        BundleExportOptions {
+            labels: true,
+            device_profiles: false,
+            quota: true,
+        }
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let bundle = seal_bundle(&sample_accounts(), "correct horse", options(), &test_kdf()).unwrap();
+        assert_eq!(bundle.header.account_count, 1);
+        assert_eq!(bundle.header.kdf.algorithm, KDF_ALGORITHM);
+
+        // 序列化后不含明文凭据
+        let serialized = serde_json::to_string(&bundle).unwrap();
+        assert!(!serialized.contains("1//refresh"));
+        assert!(!serialized.contains("team@example.com"));
+
+        let parsed: AccountBundle = serde_json::from_str(&serialized).unwrap();
+        let accounts = open_bundle(&parsed, "correct horse").unwrap();
+        assert_eq!(accounts[0].refresh_token, "1//refresh");
+        assert_eq!(accounts[0].custom_label.as_deref(), Some("pool-a"));
+        assert!(accounts[0].quota.is_some());
+    }
+
+    #[test]
+    fn test_bundle_rejects_wrong_password_and_tampering() {
+        let bundle = seal_bundle(&sample_accounts(), "correct horse", options(), &test_kdf()).unwrap();
+        assert!(open_bundle(&bundle, "wrong password").is_err());
+
+        // 修改头部（账号数量）会导致认证失败
+        let mut tampered = bundle.clone();
+        tampered.header.account_count = 2;
+        let err = open_bundle(&tampered, "correct horse").unwrap_err();
+        assert!(err.starts_with("bundle_decryption_failed"), "{}", err);
+
+        let mut newer = bundle;
+        newer.header.version = BUNDLE_VERSION + 1;
+        assert!(open_bundle(&newer, "correct horse")
+            .unwrap_err()
+            .starts_with("unsupported_bundle_version"));
+    }
+
+    #[test]
+    fn test_bundle_rejects_oversized_kdf_params() {
+        let bundle = seal_bundle(&sample_accounts(), "correct horse", options(), &test_kdf()).unwrap();
+
+        let mut oversized = bundle.clone();
+        oversized.header.kdf.m_cost = MAX_KDF_M_COST + 1;
+        assert!(open_bundle(&oversized, "correct horse")
+            .unwrap_err()
+            .starts_with("bundle_kdf_params_too_expensive"));
+
+        let mut oversized = bundle;
+        oversized.header.kdf.t_cost = u32::MAX;
+        assert!(open_bundle(&oversized, "correct horse")
+            .unwrap_err()
+            .starts_with("bundle_kdf_params_too_expensive"));
+    }
+
+    #[test]
+    fn test_bundle_requires_reasonable_password() {
+        assert!(seal_bundle(&sample_accounts(), "short", options(), &test_kdf()).is_err());
+    }
+}
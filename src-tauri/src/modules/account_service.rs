@@ -11,6 +11,14 @@ impl AccountService {
         Self { integration }
     }
 
+    /// 审计日志中的事件来源
+    fn event_source(&self) -> modules::audit::EventSource {
+        match &self.integration {
+            modules::integration::SystemManager::Desktop(_) => modules::audit::current_source(),
+            modules::integration::SystemManager::Headless => modules::audit::EventSource::Headless,
+        }
+    }
+
     /// 添加账号逻辑
     pub async fn add_account(&self, refresh_token: &str) -> Result<Account, String> {
         // [FIX #1583] 生成临时 UUID 作为账号上下文，避免传递 None 导致代理选择异常
@@ -38,8 +46,9 @@ impl AccountService {
         );
 
         // 5. 持久化
-        let mut account =
-            modules::upsert_account(user_info.email.clone(), user_info.get_display_name(), token)?;
+        let mut account = modules::audit::sync_scope(self.event_source(), || {
+            modules::upsert_account(user_info.email.clone(), user_info.get_display_name(), token)
+        })?;
 
         // 6. [NEW] 自动获取配额信息（用于刷新时间排序）
         let email_for_log = account.email.clone();
@@ -80,14 +89,18 @@ impl AccountService {
 
     /// 删除账号逻辑
     pub fn delete_account(&self, account_id: &str) -> Result<(), String> {
-        modules::delete_account(account_id)?;
+        modules::audit::sync_scope(self.event_source(), || modules::delete_account(account_id))?;
         self.integration.update_tray();
         Ok(())
     }
 
     /// 切换账号逻辑
     pub async fn switch_account(&self, account_id: &str) -> Result<(), String> {
-        modules::account::switch_account(account_id, &self.integration).await
+        modules::audit::with_source(
+            self.event_source(),
+            modules::account::switch_account(account_id, &self.integration),
+        )
+        .await
     }
 
     /// 列表获取
@@ -165,11 +178,13 @@ impl AccountService {
             None,
         );
 
-        let account = modules::upsert_account(
-            user_info.email.clone(),
-            user_info.get_display_name(),
-            token_data,
-        )?;
+        let account = modules::audit::sync_scope(self.event_source(), || {
+            modules::upsert_account(
+                user_info.email.clone(),
+                user_info.get_display_name(),
+                token_data,
+            )
+        })?;
 
         // 发送 UI 更新通知 (通过 integration)
         self.integration.update_tray();
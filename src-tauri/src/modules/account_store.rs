@@ -0,0 +1,501 @@
+//! SQLite 账号存储（accounts.db）
+//!
+//! 数据目录中存在 accounts.db 时启用，取代 accounts.json + accounts/*.json 布局。
+//! 账号顺序、当前账号、配额与设备指纹历史均在事务中写入，无需索引恢复逻辑。
+//! 本模块只负责持久化，令牌加解密仍由 `modules::account` 处理。
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::models::account::ACCOUNT_INDEX_VERSION;
+use crate::models::{Account, AccountIndex, AccountSummary, DeviceProfileVersion, QuotaData};
+
+pub const STORE_DB: &str = "accounts.db";
+const SCHEMA_VERSION: i64 = 1;
+const META_CURRENT_ACCOUNT: &str = "current_account_id";
+const META_SCHEMA_VERSION: &str = "schema_version";
+
+/// 账号存储迁移/导出结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountStoreReport {
+    pub accounts: usize,
+    pub indexed: usize,
+    pub path: String,
+}
+
+/// 账号存储状态
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountStoreStatus {
+    pub enabled: bool,
+    pub path: String,
+}
+
+pub fn store_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(STORE_DB)
+}
+
+/// 数据目录中存在 accounts.db 即视为启用 SQLite 存储
+pub fn is_enabled(data_dir: &Path) -> bool {
+    store_path(data_dir).exists()
+}
+
+pub fn status(data_dir: &Path) -> AccountStoreStatus {
+    AccountStoreStatus {
+        enabled: is_enabled(data_dir),
+        path: store_path(data_dir).to_string_lossy().to_string(),
+    }
+}
+
+fn open_db(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path)
+        .map_err(|e| format!("failed_to_open_account_store: {}", e))?;
+    conn.busy_timeout(Duration::from_secs(5))
+        .map_err(|e| format!("failed_to_configure_account_store: {}", e))?;
+    conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+        .map_err(|e| format!("failed_to_configure_account_store: {}", e))?;
+    init_schema(&conn)?;
+    Ok(conn)
+}
+
+fn open(data_dir: &Path) -> Result<Connection, String> {
+    open_db(&store_path(data_dir))
+}
+
+fn init_schema(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS meta (
+            key   TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        -- position 为 NULL 表示账号不在索引中（与 JSON 布局中孤立的账号文件一致）
+        CREATE TABLE IF NOT EXISTS accounts (
+            id         TEXT PRIMARY KEY,
+            email      TEXT NOT NULL,
+            position   INTEGER,
+            data       TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            last_used  INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_accounts_email ON accounts(email);
+        CREATE INDEX IF NOT EXISTS idx_accounts_position ON accounts(position);
+        CREATE TABLE IF NOT EXISTS account_quota (
+            account_id   TEXT PRIMARY KEY REFERENCES accounts(id) ON DELETE CASCADE,
+            data         TEXT NOT NULL,
+            last_updated INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS device_history (
+            id         TEXT NOT NULL,
+            account_id TEXT NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+            seq        INTEGER NOT NULL,
+            data       TEXT NOT NULL,
+            PRIMARY KEY (account_id, id)
+        );",
+    )
+    .map_err(|e| format!("failed_to_init_account_store: {}", e))?;
+
+    conn.execute(
+        "INSERT OR IGNORE INTO meta (key, value) VALUES (?1, ?2)",
+        params![META_SCHEMA_VERSION, SCHEMA_VERSION.to_string()],
+    )
+    .map_err(|e| format!("failed_to_init_account_store: {}", e))?;
+
+    Ok(())
+}
+
+fn db_err(context: &str) -> impl Fn(rusqlite::Error) -> String + '_ {
+    move |e| format!("{}: {}", context, e)
+}
+
+fn to_summary(account: &Account) -> AccountSummary {
+    AccountSummary {
+        id: account.id.clone(),
+        email: account.email.clone(),
+        name: account.name.clone(),
+        disabled: account.disabled,
+        protected_models: account.protected_models.clone(),
+        created_at: account.created_at,
+        last_used: account.last_used,
+    }
+}
+
+/// Write one account with its quota and device history (caller owns the transaction)
+fn write_account_tx(tx: &Transaction, account: &Account) -> Result<(), String> {
+    let mut base = account.clone();
+    let quota = base.quota.take();
+    let history = std::mem::take(&mut base.device_history);
+
+    let data = serde_json::to_string(&base)
+        .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
+    tx.execute(
+        "INSERT INTO accounts (id, email, data, created_at, last_used)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(id) DO UPDATE SET
+            email = excluded.email,
+            data = excluded.data,
+            last_used = excluded.last_used",
+        params![base.id, base.email, data, base.created_at, base.last_used],
+    )
+    .map_err(db_err("failed_to_save_account_data"))?;
+
+    match quota {
+        Some(quota) => {
+            let data = serde_json::to_string(&quota)
+                .map_err(|e| format!("failed_to_serialize_quota: {}", e))?;
+            tx.execute(
+                "INSERT OR REPLACE INTO account_quota (account_id, data, last_updated)
+                 VALUES (?1, ?2, ?3)",
+                params![base.id, data, quota.last_updated],
+            )
+            .map_err(db_err("failed_to_save_quota"))?;
+        }
+        None => {
+            tx.execute("DELETE FROM account_quota WHERE account_id = ?1", params![base.id])
+                .map_err(db_err("failed_to_save_quota"))?;
+        }
+    }
+
+    tx.execute("DELETE FROM device_history WHERE account_id = ?1", params![base.id])
+        .map_err(db_err("failed_to_save_device_history"))?;
+    for (seq, version) in history.iter().enumerate() {
+        let data = serde_json::to_string(version)
+            .map_err(|e| format!("failed_to_serialize_device_history: {}", e))?;
+        tx.execute(
+            "INSERT OR REPLACE INTO device_history (id, account_id, seq, data) VALUES (?1, ?2, ?3, ?4)",
+            params![version.id, base.id, seq as i64, data],
+        )
+        .map_err(db_err("failed_to_save_device_history"))?;
+    }
+
+    Ok(())
+}
+
+/// Apply index order and current account (caller owns the transaction)
+fn write_index_tx(tx: &Transaction, index: &AccountIndex) -> Result<(), String> {
+    tx.execute("UPDATE accounts SET position = NULL", [])
+        .map_err(db_err("failed_to_save_account_index"))?;
+    for (position, summary) in index.accounts.iter().enumerate() {
+        tx.execute(
+            "UPDATE accounts SET position = ?1 WHERE id = ?2",
+            params![position as i64, summary.id],
+        )
+        .map_err(db_err("failed_to_save_account_index"))?;
+    }
+
+    match &index.current_account_id {
+        Some(id) => tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+            params![META_CURRENT_ACCOUNT, id],
+        ),
+        None => tx.execute("DELETE FROM meta WHERE key = ?1", params![META_CURRENT_ACCOUNT]),
+    }
+    .map_err(db_err("failed_to_save_account_index"))?;
+
+    Ok(())
+}
+
+fn read_accounts(conn: &Connection, only_indexed: bool) -> Result<Vec<Account>, String> {
+    let sql = if only_indexed {
+        "SELECT a.data, q.data FROM accounts a
+         LEFT JOIN account_quota q ON q.account_id = a.id
+         WHERE a.position IS NOT NULL ORDER BY a.position"
+    } else {
+        "SELECT a.data, q.data FROM accounts a
+         LEFT JOIN account_quota q ON q.account_id = a.id
+         ORDER BY a.position IS NULL, a.position, a.created_at"
+    };
+
+    let mut stmt = conn.prepare(sql).map_err(db_err("failed_to_read_account_store"))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))
+        .map_err(db_err("failed_to_read_account_store"))?;
+
+    let mut history = read_device_history(conn, None)?;
+    let mut accounts = Vec::new();
+    for row in rows {
+        let (data, quota) = row.map_err(db_err("failed_to_read_account_store"))?;
+        let mut account = parse_account(&data, quota.as_deref())?;
+        account.device_history = history.remove(&account.id).unwrap_or_default();
+        accounts.push(account);
+    }
+    Ok(accounts)
+}
+
+fn read_device_history(
+    conn: &Connection,
+    account_id: Option<&str>,
+) -> Result<HashMap<String, Vec<DeviceProfileVersion>>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT account_id, data FROM device_history
+             WHERE ?1 IS NULL OR account_id = ?1 ORDER BY account_id, seq",
+        )
+        .map_err(db_err("failed_to_read_device_history"))?;
+    let rows = stmt
+        .query_map(params![account_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(db_err("failed_to_read_device_history"))?;
+
+    let mut grouped: HashMap<String, Vec<DeviceProfileVersion>> = HashMap::new();
+    for row in rows {
+        let (id, data) = row.map_err(db_err("failed_to_read_device_history"))?;
+        let version: DeviceProfileVersion = serde_json::from_str(&data)
+            .map_err(|e| format!("failed_to_parse_device_history: {}", e))?;
+        grouped.entry(id).or_default().push(version);
+    }
+    Ok(grouped)
+}
+
+fn parse_account(data: &str, quota: Option<&str>) -> Result<Account, String> {
+    let mut value: serde_json::Value = serde_json::from_str(data)
+        .map_err(|e| format!("failed_to_parse_account_data: {}", e))?;
+    // 行数据按当前 schema 读取，下次保存时写回新格式
+    crate::modules::schema::migrate(crate::modules::schema::SchemaKind::Account, &mut value)?;
+    let mut account: Account = serde_json::from_value(value)
+        .map_err(|e| format!("failed_to_parse_account_data: {}", e))?;
+    account.quota = match quota {
+        Some(q) => Some(
+            serde_json::from_str::<QuotaData>(q)
+                .map_err(|e| format!("failed_to_parse_quota: {}", e))?,
+        ),
+        None => None,
+    };
+    Ok(account)
+}
+
+/// Load index (order + current account) from the store
+pub fn load_index(data_dir: &Path) -> Result<AccountIndex, String> {
+    let conn = open(data_dir)?;
+    let mut stmt = conn
+        .prepare("SELECT data FROM accounts WHERE position IS NOT NULL ORDER BY position")
+        .map_err(db_err("failed_to_read_account_index"))?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(db_err("failed_to_read_account_index"))?;
+
+    let mut summaries = Vec::new();
+    for row in rows {
+        let data = row.map_err(db_err("failed_to_read_account_index"))?;
+        summaries.push(to_summary(&parse_account(&data, None)?));
+    }
+
+    let current_account_id = conn
+        .query_row(
+            "SELECT value FROM meta WHERE key = ?1",
+            params![META_CURRENT_ACCOUNT],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(db_err("failed_to_read_account_index"))?;
+
+    Ok(AccountIndex {
+        version: format!("{}.0", ACCOUNT_INDEX_VERSION),
+        accounts: summaries,
+        current_account_id,
+    })
+}
+
+/// Save index order and current account. Summary fields other than the id are derived
+/// from the stored accounts, so only order and membership are taken from `index`.
+pub fn save_index(data_dir: &Path, index: &AccountIndex) -> Result<(), String> {
+    let mut conn = open(data_dir)?;
+    let tx = conn.transaction().map_err(db_err("failed_to_save_account_index"))?;
+    write_index_tx(&tx, index)?;
+    tx.commit().map_err(db_err("failed_to_save_account_index"))
+}
+
+/// Load one account (tokens as stored on disk)
+pub fn load_account(data_dir: &Path, account_id: &str) -> Result<Option<Account>, String> {
+    let conn = open(data_dir)?;
+    let row = conn
+        .query_row(
+            "SELECT a.data, q.data FROM accounts a
+             LEFT JOIN account_quota q ON q.account_id = a.id
+             WHERE a.id = ?1",
+            params![account_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+        )
+        .optional()
+        .map_err(db_err("failed_to_read_account_data"))?;
+
+    let Some((data, quota)) = row else {
+        return Ok(None);
+    };
+    let mut account = parse_account(&data, quota.as_deref())?;
+    account.device_history = read_device_history(&conn, Some(account_id))?
+        .remove(account_id)
+        .unwrap_or_default();
+    Ok(Some(account))
+}
+
+/// Save one account with its quota and device history in a single transaction
+pub fn save_account(data_dir: &Path, account: &Account) -> Result<(), String> {
+    let mut conn = open(data_dir)?;
+    let tx = conn.transaction().map_err(db_err("failed_to_save_account_data"))?;
+    write_account_tx(&tx, account)?;
+    tx.commit().map_err(db_err("failed_to_save_account_data"))
+}
+
+/// Save one account and the index in a single transaction
+pub fn save_account_with_index(
+    data_dir: &Path,
+    account: &Account,
+    index: &AccountIndex,
+) -> Result<(), String> {
+    let mut conn = open(data_dir)?;
+    let tx = conn.transaction().map_err(db_err("failed_to_save_account_data"))?;
+    write_account_tx(&tx, account)?;
+    write_index_tx(&tx, index)?;
+    tx.commit().map_err(db_err("failed_to_save_account_data"))
+}
+
+/// List accounts in index order with a single pass over the store
+pub fn list_accounts(data_dir: &Path) -> Result<Vec<Account>, String> {
+    let conn = open(data_dir)?;
+    read_accounts(&conn, true)
+}
+
+/// Load every stored account (including ones not in the index) together with the index
+pub fn load_layout(data_dir: &Path) -> Result<(AccountIndex, Vec<Account>), String> {
+    let index = load_index(data_dir)?;
+    let conn = open(data_dir)?;
+    Ok((index, read_accounts(&conn, false)?))
+}
+
+fn delete_accounts_tx(tx: &Transaction, account_ids: &[String]) -> Result<(), String> {
+    for id in account_ids {
+        tx.execute("DELETE FROM accounts WHERE id = ?1", params![id])
+            .map_err(db_err("failed_to_delete_account"))?;
+    }
+    Ok(())
+}
+
+/// Delete accounts (with their quota and device history) and save the updated index in a single transaction
+pub fn delete_accounts_with_index(
+    data_dir: &Path,
+    account_ids: &[String],
+    index: &AccountIndex,
+) -> Result<(), String> {
+    let mut conn = open(data_dir)?;
+    let tx = conn.transaction().map_err(db_err("failed_to_delete_account"))?;
+    delete_accounts_tx(&tx, account_ids)?;
+    write_index_tx(&tx, index)?;
+    tx.commit().map_err(db_err("failed_to_delete_account"))
+}
+
+/// Write a complete layout into a (new) database file in one transaction
+pub fn import_layout(
+    db_path: &Path,
+    index: &AccountIndex,
+    accounts: &[Account],
+) -> Result<(), String> {
+    let mut conn = open_db(db_path)?;
+    let tx = conn.transaction().map_err(db_err("failed_to_import_accounts"))?;
+    for account in accounts {
+        write_account_tx(&tx, account)?;
+    }
+    write_index_tx(&tx, index)?;
+    tx.commit().map_err(db_err("failed_to_import_accounts"))?;
+
+    // 合并 WAL，确保关闭后单个数据库文件即包含全部数据（便于重命名）
+    conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+        .map_err(db_err("failed_to_import_accounts"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::{account, TestDataDir};
+    use crate::models::DeviceProfile;
+
+    fn index_of(ids: &[&Account], current: Option<&str>) -> AccountIndex {
+        AccountIndex {
+            version: format!("{}.0", ACCOUNT_INDEX_VERSION),
+            accounts: ids.iter().map(|a| to_summary(a)).collect(),
+            current_account_id: current.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_store_roundtrip_with_quota_and_history() {
+        let dir = TestDataDir::new();
+        let mut a = account("a");
+        let b = account("b");
+
+        let mut quota = QuotaData::new();
+        quota.add_model("gemini-3-pro".to_string(), 42, "".to_string());
+        a.quota = Some(quota);
+        a.device_history.push(DeviceProfileVersion {
+            id: "v1".to_string(),
+            created_at: 1,
+            label: "generated".to_string(),
+            profile: DeviceProfile {
+                machine_id: "m".to_string(),
+                mac_machine_id: "mm".to_string(),
+                dev_device_id: "d".to_string(),
+                sqm_id: "s".to_string(),
+            },
+            is_current: true,
+        });
+
+        import_layout(&store_path(dir.path()), &index_of(&[&b, &a], Some("a")), &[a.clone(), b])
+            .unwrap();
+        assert!(is_enabled(dir.path()));
+
+        let index = load_index(dir.path()).unwrap();
+        assert_eq!(index.version, format!("{}.0", ACCOUNT_INDEX_VERSION));
+        let order: Vec<_> = index.accounts.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(order, vec!["b", "a"]);
+        assert_eq!(index.current_account_id.as_deref(), Some("a"));
+
+        let loaded = load_account(dir.path(), "a").unwrap().unwrap();
+        assert_eq!(loaded.quota.unwrap().models[0].percentage, 42);
+        assert_eq!(loaded.device_history.len(), 1);
+
+        let listed = list_accounts(dir.path()).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[1].device_history[0].id, "v1");
+
+        // 重排 + 删除：配额与历史随账号级联删除
+        save_index(dir.path(), &index_of(&[&a], None)).unwrap();
+        delete_accounts_with_index(dir.path(), &["a".to_string()], &index_of(&[], None)).unwrap();
+        assert!(load_account(dir.path(), "a").unwrap().is_none());
+        let conn = open(dir.path()).unwrap();
+        let quota_rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM account_quota", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(quota_rows, 0);
+
+        // b 不在索引中，但仍保留在存储里
+        let (index, all) = load_layout(dir.path()).unwrap();
+        assert!(index.accounts.is_empty());
+        assert!(index.current_account_id.is_none());
+        assert_eq!(all.len(), 1);
+    }
+
+    #[test]
+    fn test_account_and_index_written_together() {
+        let dir = TestDataDir::new();
+        let a = account("a");
+        let b = account("b");
+        let index = index_of(&[&a], Some("a"));
+        import_layout(&store_path(dir.path()), &index, std::slice::from_ref(&a)).unwrap();
+
+        save_account_with_index(dir.path(), &b, &index_of(&[&a, &b], Some("b"))).unwrap();
+        let index = load_index(dir.path()).unwrap();
+        let order: Vec<_> = index.accounts.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(order, vec!["a", "b"]);
+        assert_eq!(index.current_account_id.as_deref(), Some("b"));
+
+        delete_accounts_with_index(dir.path(), &["b".to_string()], &index_of(&[&a], Some("a")))
+            .unwrap();
+        let (index, all) = load_layout(dir.path()).unwrap();
+        assert_eq!(index.accounts.len(), 1);
+        assert_eq!(index.current_account_id.as_deref(), Some("a"));
+        assert_eq!(all.len(), 1);
+    }
+}
@@ -0,0 +1,409 @@
+//! 账号活动审计日志（audit/）
+//!
+//! 每个账号一个只追加的 JSON Lines 文件 `audit/<account_id>.jsonl`，记录切换、刷新、禁用、
+//! 模型保护变化、导出等事件及其来源（托盘 / 调度器 / 命令 / headless）。
+//! 文件超过上限后只保留最近的部分；账号删除后日志仍保留，便于事后追查。
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::modules::quota_protection::ProtectionChange;
+use crate::utils::atomic_file;
+
+const AUDIT_DIR: &str = "audit";
+/// 单个日志文件超过该大小时触发压缩
+const MAX_JOURNAL_BYTES: u64 = 512 * 1024;
+/// 压缩后保留的最近记录大小
+const COMPACT_TARGET_BYTES: u64 = 256 * 1024;
+const DEFAULT_QUERY_LIMIT: usize = 1000;
+
+/// 进程内串行化追加与压缩
+static JOURNAL_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// 触发事件的子系统
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSource {
+    Tray,
+    Scheduler,
+    Command,
+    Headless,
+    /// 配额保护触发的自动切换
+    AutoSwitch,
+}
+
+impl EventSource {
+    /// 用户主动触发（托盘、前端命令、headless 接口），而非调度器或自动切换
+    pub fn is_user_initiated(self) -> bool {
+        matches!(self, Self::Tray | Self::Command | Self::Headless)
+    }
+}
+
+tokio::task_local! {
+    static EVENT_SOURCE: EventSource;
+}
+
+/// 在指定来源下执行异步任务，其间记录的事件都归属该来源
+pub async fn with_source<F: Future>(source: EventSource, fut: F) -> F::Output {
+    EVENT_SOURCE.scope(source, fut).await
+}
+
+/// 同步版本的 [`with_source`]
+pub fn sync_scope<R>(source: EventSource, f: impl FnOnce() -> R) -> R {
+    EVENT_SOURCE.sync_scope(source, f)
+}
+
+/// 当前任务的事件来源，未设置时视为前端命令
+pub fn current_source() -> EventSource {
+    EVENT_SOURCE
+        .try_with(|s| *s)
+        .unwrap_or(EventSource::Command)
+}
+
+/// 审计事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    Added,
+    Switched,
+    TokenRefreshed,
+    QuotaRefreshed,
+    Disabled {
+        reason: String,
+    },
+    ProtectedModelsChanged {
+        added: Vec<String>,
+        removed: Vec<String>,
+        /// 每个模型的变化原因
+        #[serde(default)]
+        changes: Vec<ProtectionChange>,
+    },
+    Exported {
+        format: String,
+    },
+    /// 订阅等级变化（paid_tier 优先）
+    TierChanged {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    Deleted,
+    Restored,
+}
+
+impl AuditEvent {
+    /// 事件类型名（与序列化后的 `type` 字段一致）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuditEvent::Added => "added",
+            AuditEvent::Switched => "switched",
+            AuditEvent::TokenRefreshed => "token_refreshed",
+            AuditEvent::QuotaRefreshed => "quota_refreshed",
+            AuditEvent::Disabled { .. } => "disabled",
+            AuditEvent::ProtectedModelsChanged { .. } => "protected_models_changed",
+            AuditEvent::Exported { .. } => "exported",
+            AuditEvent::TierChanged { .. } => "tier_changed",
+            AuditEvent::Deleted => "deleted",
+            AuditEvent::Restored => "restored",
+        }
+    }
+}
+
+/// 日志中的一条记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Unix 时间戳（秒）
+    pub ts: i64,
+    pub account_id: String,
+    pub email: String,
+    pub source: EventSource,
+    pub event: AuditEvent,
+}
+
+/// 查询条件，均为可选；时间范围为闭区间
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditQuery {
+    pub account_id: Option<String>,
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, record: &AuditRecord) -> bool {
+        (self.event_types.is_empty() || self.event_types.iter().any(|t| t == record.event.kind()))
+            && self.since.is_none_or(|since| record.ts >= since)
+            && self.until.is_none_or(|until| record.ts <= until)
+    }
+}
+
+pub fn audit_dir_in(data_dir: &Path) -> PathBuf {
+    data_dir.join(AUDIT_DIR)
+}
+
+fn journal_path(data_dir: &Path, account_id: &str) -> Result<PathBuf, String> {
+    if account_id.is_empty()
+        || !account_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(format!("invalid_audit_account_id: {}", account_id));
+    }
+    Ok(audit_dir_in(data_dir).join(format!("{}.jsonl", account_id)))
+}
+
+/// 追加记录到账号日志，超过 `max_bytes` 时压缩
+fn append_in_dir(
+    data_dir: &Path,
+    record: &AuditRecord,
+    max_bytes: u64,
+    target_bytes: u64,
+) -> Result<(), String> {
+    let path = journal_path(data_dir, &record.account_id)?;
+    let mut line = serde_json::to_string(record)
+        .map_err(|e| format!("failed_to_serialize_audit_record: {}", e))?;
+    line.push('\n');
+
+    let _guard = JOURNAL_LOCK.lock();
+    fs::create_dir_all(audit_dir_in(data_dir))
+        .map_err(|e| format!("failed_to_create_audit_dir: {}", e))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("failed_to_open_audit_log: {}", e))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("failed_to_write_audit_log: {}", e))?;
+
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    drop(file);
+    if len > max_bytes {
+        compact_file(&path, target_bytes)?;
+    }
+    Ok(())
+}
+
+/// 只保留 `target_bytes` 以内最新的完整行
+fn compact_file(path: &Path, target_bytes: u64) -> Result<(), String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("failed_to_read_audit_log: {}", e))?;
+
+    let mut kept = Vec::new();
+    let mut size = 0u64;
+    for line in content.lines().rev().filter(|l| !l.trim().is_empty()) {
+        size += line.len() as u64 + 1;
+        if size > target_bytes {
+            break;
+        }
+        kept.push(line);
+    }
+    kept.reverse();
+
+    let mut compacted = kept.join("\n");
+    if !compacted.is_empty() {
+        compacted.push('\n');
+    }
+    atomic_file::write_atomic(path, compacted)
+        .map_err(|e| format!("failed_to_compact_audit_log: {}", e))
+}
+
+fn read_journal(path: &Path, query: &AuditQuery, out: &mut Vec<AuditRecord>) -> Result<(), String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("failed_to_read_audit_log: {}", e))?;
+    // 跳过无法解析的行（如写入中断留下的半行）
+    out.extend(
+        content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<AuditRecord>(line).ok())
+            .filter(|record| query.matches(record)),
+    );
+    Ok(())
+}
+
+/// 查询指定数据目录中的记录，最新的在前
+fn query_in_dir(data_dir: &Path, query: &AuditQuery) -> Result<Vec<AuditRecord>, String> {
+    let mut records = Vec::new();
+    let _guard = JOURNAL_LOCK.lock();
+
+    match query.account_id.as_deref() {
+        Some(account_id) => {
+            let path = journal_path(data_dir, account_id)?;
+            if path.exists() {
+                read_journal(&path, query, &mut records)?;
+            }
+        }
+        None => {
+            let dir = audit_dir_in(data_dir);
+            if dir.exists() {
+                for file in fs::read_dir(&dir)
+                    .map_err(|e| format!("failed_to_read_audit_dir: {}", e))?
+                    .flatten()
+                {
+                    let path = file.path();
+                    if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+                        read_journal(&path, query, &mut records)?;
+                    }
+                }
+            }
+        }
+    }
+
+    records.sort_by_key(|r| std::cmp::Reverse(r.ts));
+    records.truncate(query.limit.unwrap_or(DEFAULT_QUERY_LIMIT));
+    Ok(records)
+}
+
+/// 记录一条审计事件；写入失败只记警告，不影响调用方
+pub fn record(account_id: &str, email: &str, event: AuditEvent) {
+    let record = AuditRecord {
+        ts: chrono::Utc::now().timestamp(),
+        account_id: account_id.to_string(),
+        email: email.to_string(),
+        source: current_source(),
+        event,
+    };
+    let result = crate::modules::account::get_data_dir().and_then(|data_dir| {
+        append_in_dir(&data_dir, &record, MAX_JOURNAL_BYTES, COMPACT_TARGET_BYTES)
+    });
+    if let Err(e) = result {
+        crate::modules::logger::log_warn(&format!(
+            "Failed to record audit event {} for {}: {}",
+            record.event.kind(),
+            email,
+            e
+        ));
+    }
+}
+
+/// 查询审计日志（按时间倒序）
+pub fn query(query: &AuditQuery) -> Result<Vec<AuditRecord>, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    query_in_dir(&data_dir, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDataDir;
+
+    fn rec(account_id: &str, ts: i64, event: AuditEvent) -> AuditRecord {
+        AuditRecord {
+            ts,
+            account_id: account_id.to_string(),
+            email: format!("{}@example.com", account_id),
+            source: current_source(),
+            event,
+        }
+    }
+
+    fn append(dir: &Path, record: AuditRecord) {
+        append_in_dir(dir, &record, MAX_JOURNAL_BYTES, COMPACT_TARGET_BYTES).unwrap();
+    }
+
+    #[test]
+    fn test_query_filters_by_account_type_and_time() {
+        let dir = TestDataDir::new();
+        append(dir.path(), rec("a", 100, AuditEvent::Switched));
+        append(
+            dir.path(),
+            rec(
+                "a",
+                200,
+                AuditEvent::Exported {
+                    format: "json".into(),
+                },
+            ),
+        );
+        append(
+            dir.path(),
+            rec(
+                "b",
+                300,
+                AuditEvent::Exported {
+                    format: "bundle".into(),
+                },
+            ),
+        );
+        // 半行记录应被忽略
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(journal_path(dir.path(), "b").unwrap())
+            .unwrap();
+        file.write_all(b"{\"ts\":4").unwrap();
+
+        let all = query_in_dir(dir.path(), &AuditQuery::default()).unwrap();
+        let ts: Vec<_> = all.iter().map(|r| r.ts).collect();
+        assert_eq!(ts, vec![300, 200, 100]);
+
+        let exports = query_in_dir(
+            dir.path(),
+            &AuditQuery {
+                event_types: vec!["exported".into()],
+                since: Some(150),
+                until: Some(250),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].account_id, "a");
+
+        let only_b = query_in_dir(
+            dir.path(),
+            &AuditQuery {
+                account_id: Some("b".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(only_b.len(), 1);
+        assert!(query_in_dir(
+            dir.path(),
+            &AuditQuery {
+                account_id: Some("../x".into()),
+                ..Default::default()
+            },
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_compaction_keeps_newest_records() {
+        let dir = TestDataDir::new();
+        for ts in 0..50 {
+            let record = rec("a", ts, AuditEvent::QuotaRefreshed);
+            append_in_dir(dir.path(), &record, 2048, 1024).unwrap();
+        }
+
+        let path = journal_path(dir.path(), "a").unwrap();
+        assert!(fs::metadata(&path).unwrap().len() <= 2048);
+        let records = query_in_dir(dir.path(), &AuditQuery::default()).unwrap();
+        assert!(!records.is_empty() && records.len() < 50);
+        assert_eq!(records[0].ts, 49);
+        // 保留的记录是连续的最近记录
+        assert_eq!(records.last().unwrap().ts, 50 - records.len() as i64);
+    }
+
+    #[test]
+    fn test_source_follows_scope() {
+        assert_eq!(current_source(), EventSource::Command);
+        let nested = sync_scope(EventSource::Scheduler, || {
+            (
+                current_source(),
+                sync_scope(EventSource::Tray, current_source),
+            )
+        });
+        assert_eq!(nested, (EventSource::Scheduler, EventSource::Tray));
+        assert_eq!(current_source(), EventSource::Command);
+
+        assert!(current_source().is_user_initiated());
+        assert!(!EventSource::Scheduler.is_user_initiated());
+        assert!(!EventSource::AutoSwitch.is_user_initiated());
+    }
+}
@@ -0,0 +1,303 @@
+//! 配额保护触发的自动切换
+//!
+//! `update_account_quota` 发现当前账号有模型新进入保护时调用 [`notify_protected`] 登记一次待切换，
+//! 随后由调度器、托盘或命令在异步上下文中通过 [`run_pending`] 执行。切换受冷却时间、
+//! Antigravity 活动检测与允许列表约束，暂时无法切换时保留待办并稍后重试。
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::fs;
+use std::time::Duration;
+
+use crate::models::config::AutoSwitchConfig;
+use crate::models::Account;
+use crate::modules::audit::{self, EventSource};
+use crate::modules::integration::SystemManager;
+use crate::modules::{account, db, logger, process};
+
+/// Antigravity 忙碌时的重试间隔
+const BUSY_RETRY_SECS: i64 = 60;
+/// 没有可用账号或切换失败时的重试间隔
+const FAILURE_RETRY_SECS: i64 = 600;
+
+#[derive(Debug, Clone)]
+struct PendingSwitch {
+    account_id: String,
+    /// 触发切换的受保护模型
+    models: Vec<String>,
+    retry_at: i64,
+}
+
+#[derive(Default)]
+struct State {
+    pending: Option<PendingSwitch>,
+    last_switch_at: Option<i64>,
+}
+
+static STATE: Lazy<Mutex<State>> = Lazy::new(|| Mutex::new(State::default()));
+
+enum Outcome {
+    Switched {
+        id: String,
+        email: String,
+    },
+    /// 不再需要切换（已关闭、已手动切换或保护已解除）
+    Dropped,
+    Retry(i64),
+}
+
+/// 登记当前账号新进入保护的模型
+pub fn notify_protected(account_id: &str, models: Vec<String>) {
+    let mut state = STATE.lock();
+    match state.pending.as_mut() {
+        Some(pending) if pending.account_id == account_id => {
+            for model in models {
+                if !pending.models.contains(&model) {
+                    pending.models.push(model);
+                }
+            }
+            pending.retry_at = 0;
+        }
+        _ => {
+            state.pending = Some(PendingSwitch {
+                account_id: account_id.to_string(),
+                models,
+                retry_at: 0,
+            });
+        }
+    }
+}
+
+/// 待切换的下一次尝试时间
+pub fn pending_retry_at() -> Option<i64> {
+    STATE.lock().pending.as_ref().map(|p| p.retry_at)
+}
+
+/// 各触发模型中剩余最少的百分比；账号缺少任一模型数据时返回 None
+fn candidate_score(account: &Account, models: &[String]) -> Option<i32> {
+    let quota = account.quota.as_ref()?;
+    models
+        .iter()
+        .map(|key| {
+            quota
+                .metered_models()
+                .filter(|m| m.name.starts_with(key.as_str()))
+                .map(|m| m.percentage)
+                .min()
+        })
+        .try_fold(100, |score, min| min.map(|min| score.min(min)))
+}
+
+/// 选出触发模型剩余额度最多的可用账号
+fn pick_candidate<'a>(
+    accounts: &'a [Account],
+    current_id: &str,
+    models: &[String],
+    config: &AutoSwitchConfig,
+) -> Option<&'a Account> {
+    accounts
+        .iter()
+        .filter(|a| a.id != current_id && !a.disabled && !a.validation_blocked)
+        .filter(|a| a.quota.as_ref().is_some_and(|q| !q.is_forbidden))
+        .filter(|a| config.allows(&a.id, &a.email))
+        .filter(|a| !models.iter().any(|m| a.protected_models.contains(m)))
+        .filter_map(|a| candidate_score(a, models).map(|score| (a, score)))
+        .filter(|(_, score)| *score > 0)
+        .max_by_key(|(a, score)| (*score, std::cmp::Reverse(a.protected_models.len())))
+        .map(|(a, _)| a)
+}
+
+/// Antigravity 正在运行且状态数据库近期有写入
+fn antigravity_busy(idle_secs: u64) -> bool {
+    if !process::is_antigravity_running() {
+        return false;
+    }
+    let Ok(db_path) = db::get_db_path() else {
+        return false;
+    };
+    [db_path.clone(), db_path.with_extension("vscdb-wal")]
+        .iter()
+        .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .any(|modified| modified.elapsed().unwrap_or_default() < Duration::from_secs(idle_secs))
+}
+
+async fn attempt(
+    pending: &PendingSwitch,
+    config: &AutoSwitchConfig,
+    last_switch_at: Option<i64>,
+    integration: &SystemManager,
+    now: i64,
+) -> Outcome {
+    if !config.enabled {
+        return Outcome::Dropped;
+    }
+    if account::get_current_account_id().ok().flatten().as_deref()
+        != Some(pending.account_id.as_str())
+    {
+        return Outcome::Dropped;
+    }
+    let Ok(current) = account::load_account(&pending.account_id) else {
+        return Outcome::Dropped;
+    };
+    if !pending
+        .models
+        .iter()
+        .any(|m| current.protected_models.contains(m))
+    {
+        return Outcome::Dropped;
+    }
+
+    if let Some(until) = last_switch_at.map(|at| at + config.cooldown_minutes as i64 * 60) {
+        if now < until {
+            logger::log_info(&format!("[AutoSwitch] 冷却中，{} 秒后重试", until - now));
+            return Outcome::Retry(until);
+        }
+    }
+    let desktop = matches!(integration, SystemManager::Desktop(_));
+    if desktop && config.defer_while_busy && antigravity_busy(config.busy_idle_secs) {
+        logger::log_info("[AutoSwitch] Antigravity 正在使用中，推迟切换");
+        return Outcome::Retry(now + BUSY_RETRY_SECS);
+    }
+
+    let accounts = match account::list_accounts() {
+        Ok(accounts) => accounts,
+        Err(e) => {
+            logger::log_warn(&format!("[AutoSwitch] 读取账号列表失败: {}", e));
+            return Outcome::Retry(now + FAILURE_RETRY_SECS);
+        }
+    };
+    let Some(target) = pick_candidate(&accounts, &current.id, &pending.models, config) else {
+        logger::log_warn(&format!(
+            "[AutoSwitch] {} 的 {:?} 已进入保护，但没有可切换的账号",
+            current.email, pending.models
+        ));
+        return Outcome::Retry(now + FAILURE_RETRY_SECS);
+    };
+
+    logger::log_info(&format!(
+        "[AutoSwitch] {} 的 {:?} 已进入保护，切换到 {}",
+        current.email, pending.models, target.email
+    ));
+    let service = crate::modules::account_service::AccountService::new(integration.clone());
+    match service.switch_account(&target.id).await {
+        Ok(()) => Outcome::Switched {
+            id: target.id.clone(),
+            email: target.email.clone(),
+        },
+        Err(e) => {
+            logger::log_error(&format!("[AutoSwitch] 切换到 {} 失败: {}", target.email, e));
+            Outcome::Retry(now + FAILURE_RETRY_SECS)
+        }
+    }
+}
+
+/// 执行到期的待切换，返回切换到的账号 ID
+pub async fn run_pending(integration: &SystemManager) -> Option<String> {
+    let now = chrono::Utc::now().timestamp();
+    // 取出待办，避免并发调用重复切换
+    let (pending, last_switch_at) = {
+        let mut state = STATE.lock();
+        if state.pending.as_ref().is_none_or(|p| p.retry_at > now) {
+            return None;
+        }
+        (state.pending.take()?, state.last_switch_at)
+    };
+    let config = match crate::modules::config::load_app_config() {
+        Ok(config) => config.auto_switch,
+        Err(e) => {
+            logger::log_warn(&format!("[AutoSwitch] 读取配置失败: {}", e));
+            AutoSwitchConfig::default()
+        }
+    };
+
+    let outcome = audit::with_source(
+        EventSource::AutoSwitch,
+        attempt(&pending, &config, last_switch_at, integration, now),
+    )
+    .await;
+    match outcome {
+        Outcome::Switched { id, email } => {
+            STATE.lock().last_switch_at = Some(now);
+            logger::log_info(&format!("[AutoSwitch] 已切换到 {}", email));
+            if let SystemManager::Desktop(handle) = integration {
+                use tauri::Emitter;
+                let _ = handle.emit("tray://account-switched", id.clone());
+            }
+            Some(id)
+        }
+        Outcome::Dropped => None,
+        Outcome::Retry(retry_at) => {
+            let mut state = STATE.lock();
+            // 期间登记了新的待办时以新的为准
+            if state.pending.is_none() {
+                state.pending = Some(PendingSwitch {
+                    retry_at,
+                    ..pending
+                });
+            }
+            None
+        }
+    }
+}
+
+/// 在后台执行到期的待切换
+pub fn spawn_pending(integration: SystemManager) {
+    if pending_retry_at().is_some() {
+        tauri::async_runtime::spawn(async move {
+            run_pending(&integration).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::account_with_quota;
+
+    fn account(id: &str, claude: i32, flash: i32) -> Account {
+        account_with_quota(id, &[("claude-sonnet-4-6", claude, ""), ("gemini-3-flash", flash, "")])
+    }
+
+    #[test]
+    fn test_pick_candidate() {
+        let models = vec!["claude".to_string()];
+        let mut protected = account("protected", 90, 100);
+        protected.protected_models.insert("claude".into());
+        let mut disabled = account("disabled", 100, 100);
+        disabled.disabled = true;
+        let accounts = vec![
+            account("current", 5, 100),
+            account("low", 40, 100),
+            account("best", 80, 10),
+            protected,
+            disabled,
+            account("empty", 0, 100),
+        ];
+        let config = AutoSwitchConfig::default();
+
+        let picked = pick_candidate(&accounts, "current", &models, &config);
+        assert_eq!(picked.map(|a| a.id.as_str()), Some("best"));
+
+        // 允许列表按 ID 或邮箱匹配
+        let config = AutoSwitchConfig {
+            allowed_accounts: vec!["LOW@example.com".into()],
+            ..Default::default()
+        };
+        let picked = pick_candidate(&accounts, "current", &models, &config);
+        assert_eq!(picked.map(|a| a.id.as_str()), Some("low"));
+
+        let config = AutoSwitchConfig {
+            allowed_accounts: vec!["empty".into()],
+            ..Default::default()
+        };
+        assert!(pick_candidate(&accounts, "current", &models, &config).is_none());
+    }
+
+    #[test]
+    fn test_candidate_score_uses_weakest_trigger_model() {
+        let account = account("a", 70, 20);
+        let models = vec!["claude".to_string(), "gemini-3-flash".to_string()];
+        assert_eq!(candidate_score(&account, &models), Some(20));
+        assert_eq!(candidate_score(&account, &["gpt".to_string()]), None);
+    }
+}
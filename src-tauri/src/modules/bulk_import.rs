@@ -0,0 +1,504 @@
+//! 批量导入 refresh_token
+//!
+//! 支持三种文件格式（自动识别）：
+//! - JSON：`AccountExportResponse`（`{"accounts":[{email, refresh_token}]}`）或其 accounts 数组
+//! - CSV：`email,refresh_token,label`（表头可选，label 可选）
+//! - 纯文本：每行一个 refresh_token，`#` 开头为注释
+//!
+//! 先按邮箱与 refresh_token 去重，再以有限并发校验令牌，最后顺序写入。
+//! dry_run 模式只做解析与校验，不写入任何文件。
+
+use futures::future::join_all;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::models::{Account, AccountExportItem, AccountExportResponse, TokenData};
+use crate::modules;
+
+/// 导入文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    Json,
+    Csv,
+    Lines,
+}
+
+/// 单条导入结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Added,
+    Updated,
+    Skipped,
+    InvalidGrant,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReportItem {
+    /// 来源行号（JSON 为数组下标 + 1）
+    pub line: usize,
+    pub email: Option<String>,
+    /// 脱敏后的 refresh_token，便于定位
+    pub token_hint: String,
+    pub status: ImportStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkImportReport {
+    pub format: ImportFormat,
+    pub dry_run: bool,
+    pub total: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub invalid_grant: usize,
+    pub failed: usize,
+    pub items: Vec<ImportReportItem>,
+}
+
+/// 解析出的待导入条目
+#[derive(Debug, Clone)]
+struct ImportEntry {
+    line: usize,
+    email: Option<String>,
+    refresh_token: String,
+    label: Option<String>,
+}
+
+/// 通过校验的条目
+struct ValidatedEntry {
+    entry: ImportEntry,
+    email: String,
+    name: Option<String>,
+    access_token: String,
+    expires_in: i64,
+}
+
+fn token_hint(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    if chars.len() <= 12 {
+        return "***".to_string();
+    }
+    let head: String = chars[..6].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", head, tail)
+}
+
+fn report_item(
+    entry: &ImportEntry,
+    status: ImportStatus,
+    reason: Option<String>,
+) -> ImportReportItem {
+    ImportReportItem {
+        line: entry.line,
+        email: entry.email.clone(),
+        token_hint: token_hint(&entry.refresh_token),
+        status,
+        reason,
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(|v| v.trim().trim_matches('"').trim())
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// Detect the file format from its content
+fn detect_format(content: &str) -> ImportFormat {
+    let trimmed = content.trim_start_matches('\u{feff}').trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        return ImportFormat::Json;
+    }
+    let first_line = trimmed
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .unwrap_or_default();
+    if first_line.contains(',') {
+        ImportFormat::Csv
+    } else {
+        ImportFormat::Lines
+    }
+}
+
+/// Parse import file content into entries
+fn parse_entries(content: &str) -> Result<(ImportFormat, Vec<ImportEntry>), String> {
+    let content = content.trim_start_matches('\u{feff}');
+    let format = detect_format(content);
+    let mut entries = Vec::new();
+
+    match format {
+        ImportFormat::Json => {
+            let items: Vec<AccountExportItem> =
+                match serde_json::from_str::<AccountExportResponse>(content) {
+                    Ok(resp) => resp.accounts,
+                    Err(_) => serde_json::from_str(content)
+                        .map_err(|e| format!("invalid_import_json: {}", e))?,
+                };
+            for (i, item) in items.into_iter().enumerate() {
+                entries.push(ImportEntry {
+                    line: i + 1,
+                    email: non_empty(Some(&item.email)),
+                    refresh_token: item.refresh_token.trim().to_string(),
+                    label: None,
+                });
+            }
+        }
+        ImportFormat::Csv => {
+            for (i, line) in content.lines().enumerate() {
+                let line_trimmed = line.trim();
+                if line_trimmed.is_empty() || line_trimmed.starts_with('#') {
+                    continue;
+                }
+                let fields: Vec<&str> = line_trimmed.split(',').collect();
+                // 可选表头
+                if entries.is_empty()
+                    && fields[0]
+                        .trim()
+                        .trim_matches('"')
+                        .eq_ignore_ascii_case("email")
+                {
+                    continue;
+                }
+                entries.push(ImportEntry {
+                    line: i + 1,
+                    email: non_empty(fields.first().copied()),
+                    refresh_token: non_empty(fields.get(1).copied()).unwrap_or_default(),
+                    label: non_empty(fields.get(2).copied()),
+                });
+            }
+        }
+        ImportFormat::Lines => {
+            for (i, line) in content.lines().enumerate() {
+                let token = line.trim();
+                if token.is_empty() || token.starts_with('#') {
+                    continue;
+                }
+                entries.push(ImportEntry {
+                    line: i + 1,
+                    email: None,
+                    refresh_token: token.to_string(),
+                    label: None,
+                });
+            }
+        }
+    }
+
+    Ok((format, entries))
+}
+
+/// Dedupe entries against each other and against existing accounts.
+/// Returns entries that still need validation plus the items already decided.
+fn plan_entries(
+    entries: Vec<ImportEntry>,
+    existing: &[Account],
+) -> (Vec<ImportEntry>, Vec<ImportReportItem>) {
+    let existing_tokens: HashMap<&str, &str> = existing
+        .iter()
+        .map(|a| (a.token.refresh_token.as_str(), a.email.as_str()))
+        .collect();
+
+    let mut seen_tokens = HashSet::new();
+    let mut seen_emails = HashSet::new();
+    let mut pending = Vec::new();
+    let mut decided = Vec::new();
+
+    for entry in entries {
+        if entry.refresh_token.is_empty() || entry.refresh_token.contains(char::is_whitespace) {
+            decided.push(report_item(
+                &entry,
+                ImportStatus::Failed,
+                Some("malformed_refresh_token".to_string()),
+            ));
+            continue;
+        }
+        if let Some(email) = existing_tokens.get(entry.refresh_token.as_str()) {
+            decided.push(report_item(
+                &entry,
+                ImportStatus::Skipped,
+                Some(format!("already_imported: {}", email)),
+            ));
+            continue;
+        }
+        if !seen_tokens.insert(entry.refresh_token.clone()) {
+            decided.push(report_item(
+                &entry,
+                ImportStatus::Skipped,
+                Some("duplicate_token_in_file".to_string()),
+            ));
+            continue;
+        }
+        if let Some(email) = &entry.email {
+            if !seen_emails.insert(modules::account::normalize_email(email)) {
+                decided.push(report_item(
+                    &entry,
+                    ImportStatus::Skipped,
+                    Some("duplicate_email_in_file".to_string()),
+                ));
+                continue;
+            }
+        }
+        pending.push(entry);
+    }
+
+    (pending, decided)
+}
+
+async fn validate_entry(entry: ImportEntry) -> Result<ValidatedEntry, ImportReportItem> {
+    let temp_account_id = uuid::Uuid::new_v4().to_string();
+
+    let token_res =
+        match modules::oauth::refresh_access_token(&entry.refresh_token, Some(&temp_account_id))
+            .await
+        {
+            Ok(t) => t,
+            Err(e) => {
+                let status = if e.contains("invalid_grant") {
+                    ImportStatus::InvalidGrant
+                } else {
+                    ImportStatus::Failed
+                };
+                return Err(report_item(&entry, status, Some(e)));
+            }
+        };
+
+    let user_info = modules::oauth::get_user_info(&token_res.access_token, Some(&temp_account_id))
+        .await
+        .map_err(|e| report_item(&entry, ImportStatus::Failed, Some(e)))?;
+
+    if let Some(expected) = &entry.email {
+        if !expected.eq_ignore_ascii_case(&user_info.email) {
+            return Err(report_item(
+                &entry,
+                ImportStatus::Failed,
+                Some(format!(
+                    "email_mismatch: token belongs to {}",
+                    user_info.email
+                )),
+            ));
+        }
+    }
+
+    Ok(ValidatedEntry {
+        email: user_info.email.clone(),
+        name: user_info.get_display_name(),
+        access_token: token_res.access_token,
+        expires_in: token_res.expires_in,
+        entry,
+    })
+}
+
+fn persist_entry(validated: &ValidatedEntry) -> Result<(), String> {
+    let token = TokenData::new(
+        validated.access_token.clone(),
+        validated.entry.refresh_token.clone(),
+        validated.expires_in,
+        Some(validated.email.clone()),
+        None,
+        None,
+    );
+    let mut account =
+        modules::account::upsert_account(validated.email.clone(), validated.name.clone(), token)?;
+    if let Some(label) = &validated.entry.label {
+        account.custom_label = Some(label.chars().take(15).collect());
+        modules::account::save_account(&account)?;
+    }
+    Ok(())
+}
+
+/// 批量导入 refresh_token；dry_run 时仅校验并返回预期结果
+pub async fn bulk_import(content: &str, dry_run: bool) -> Result<BulkImportReport, String> {
+    let (format, entries) = parse_entries(content)?;
+    if entries.is_empty() {
+        return Err("no_import_entries_found".to_string());
+    }
+    let total = entries.len();
+
+    let existing = modules::account::list_accounts()?;
+    let (pending, mut items) = plan_entries(entries, &existing);
+    let mut known_emails: HashSet<String> =
+        existing.iter().map(|a| modules::account::normalize_email(&a.email)).collect();
+
+    // 与配额刷新共用并发限制，遵循 refresh_policy 的上下限
+    let policy = modules::config::load_app_config()
+        .map(|config| config.refresh_policy)
+        .unwrap_or_default();
+    let limiter = modules::account::refresh_limiter(&policy);
+
+    modules::logger::log_info(&format!(
+        "Bulk import ({:?}, dry_run={}): {} entries, validating {} (concurrency {})",
+        format,
+        dry_run,
+        total,
+        pending.len(),
+        limiter.limit()
+    ));
+
+    let tasks: Vec<_> = pending
+        .into_iter()
+        .map(|entry| {
+            let limiter = limiter.clone();
+            async move {
+                let _permit = limiter.acquire().await;
+                validate_entry(entry).await
+            }
+        })
+        .collect();
+
+    let mut imported_emails = HashSet::new();
+    for result in join_all(tasks).await {
+        let validated = match result {
+            Ok(v) => v,
+            Err(item) => {
+                items.push(item);
+                continue;
+            }
+        };
+
+        let key = modules::account::normalize_email(&validated.email);
+        let mut item = report_item(&validated.entry, ImportStatus::Added, None);
+        item.email = Some(validated.email.clone());
+
+        // 纯文本格式只有在校验后才知道邮箱，这里再做一次去重
+        if !imported_emails.insert(key.clone()) {
+            item.status = ImportStatus::Skipped;
+            item.reason = Some("duplicate_email_in_file".to_string());
+            items.push(item);
+            continue;
+        }
+        if known_emails.contains(&key) {
+            item.status = ImportStatus::Updated;
+        }
+
+        if !dry_run {
+            if let Err(e) = persist_entry(&validated) {
+                item.status = ImportStatus::Failed;
+                item.reason = Some(e);
+            } else {
+                known_emails.insert(key);
+            }
+        }
+        items.push(item);
+    }
+
+    items.sort_by_key(|item| item.line);
+    let count = |status: ImportStatus| items.iter().filter(|i| i.status == status).count();
+    let report = BulkImportReport {
+        format,
+        dry_run,
+        total,
+        added: count(ImportStatus::Added),
+        updated: count(ImportStatus::Updated),
+        skipped: count(ImportStatus::Skipped),
+        invalid_grant: count(ImportStatus::InvalidGrant),
+        failed: count(ImportStatus::Failed),
+        items,
+    };
+
+    modules::logger::log_info(&format!(
+        "Bulk import finished: added {}, updated {}, skipped {}, invalid_grant {}, failed {}",
+        report.added, report.updated, report.skipped, report.invalid_grant, report.failed
+    ));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn existing_account(email: &str, refresh_token: &str) -> Account {
+        Account::new(
+            uuid::Uuid::new_v4().to_string(),
+            email.to_string(),
+            TokenData::new(
+                String::new(),
+                refresh_token.to_string(),
+                0,
+                None,
+                None,
+                None,
+            ),
+        )
+    }
+
+    #[test]
+    fn test_parse_export_response_json() {
+        let content = r#"{"accounts":[{"email":"a@example.com","refresh_token":"1//aaa"},{"email":"b@example.com","refresh_token":"1//bbb"}]}"#;
+        let (format, entries) = parse_entries(content).unwrap();
+        assert_eq!(format, ImportFormat::Json);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].email.as_deref(), Some("b@example.com"));
+
+        // 直接给出 accounts 数组也可以
+        let (_, entries) =
+            parse_entries(r#"[{"email":"a@example.com","refresh_token":"1//aaa"}]"#).unwrap();
+        assert_eq!(entries[0].refresh_token, "1//aaa");
+    }
+
+    #[test]
+    fn test_parse_csv_with_header_and_label() {
+        let content = "\u{feff}email,refresh_token,label\n\na@example.com, 1//aaa ,team-a\nb@example.com,1//bbb\n";
+        let (format, entries) = parse_entries(content).unwrap();
+        assert_eq!(format, ImportFormat::Csv);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].line, 3);
+        assert_eq!(entries[0].refresh_token, "1//aaa");
+        assert_eq!(entries[0].label.as_deref(), Some("team-a"));
+        assert!(entries[1].label.is_none());
+    }
+
+    #[test]
+    fn test_parse_lines_skips_comments() {
+        let content = "# exported tokens\n1//aaa\n\n  1//bbb  \n";
+        let (format, entries) = parse_entries(content).unwrap();
+        assert_eq!(format, ImportFormat::Lines);
+        let tokens: Vec<_> = entries.iter().map(|e| e.refresh_token.as_str()).collect();
+        assert_eq!(tokens, vec!["1//aaa", "1//bbb"]);
+        assert_eq!(entries[1].line, 4);
+    }
+
+    #[test]
+    fn test_plan_dedupes_against_file_and_existing_accounts() {
+        let content = "1//known-token-0001\n1//fresh-token-0001\n1//fresh-token-0001\nbad token\n";
+        let (_, entries) = parse_entries(content).unwrap();
+        let existing = vec![existing_account("known@example.com", "1//known-token-0001")];
+
+        let (pending, decided) = plan_entries(entries, &existing);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].refresh_token, "1//fresh-token-0001");
+
+        let statuses: Vec<_> = decided.iter().map(|i| (i.line, i.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (1, ImportStatus::Skipped),
+                (3, ImportStatus::Skipped),
+                (4, ImportStatus::Failed)
+            ]
+        );
+        assert!(decided[0]
+            .reason
+            .as_deref()
+            .unwrap()
+            .contains("known@example.com"));
+        assert!(!decided[0].token_hint.contains("known-token"));
+    }
+
+    #[test]
+    fn test_plan_dedupes_csv_emails_case_insensitively() {
+        let content = "A@example.com,1//aaa-token-0001\na@example.com,1//aaa-token-0002\n";
+        let (_, entries) = parse_entries(content).unwrap();
+        let (pending, decided) = plan_entries(entries, &[]);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(
+            decided[0].reason.as_deref(),
+            Some("duplicate_email_in_file")
+        );
+    }
+}
@@ -1,98 +1,146 @@
 use std::fs;
+use std::path::Path;
 use serde_json;
 
 use crate::models::AppConfig;
+use crate::utils::crypto;
 use super::account::get_data_dir;
-
-const CONFIG_FILE: &str = "gui_config.json";
+use super::schema::SchemaKind;
 
 /// Load application configuration
 pub fn load_app_config() -> Result<AppConfig, String> {
     let data_dir = get_data_dir()?;
-    let config_path = data_dir.join(CONFIG_FILE);
+    let config_path = super::paths::config_file()?;
     
+    if config_path.exists() {
+        let (config, migrated) = read_app_config(&data_dir, &config_path)?;
+        if !migrated {
+            return Ok(config);
+        }
+    }
+
+    // Creating or migrating writes the file back: hold the data dir lock and re-read,
+    // so a config saved by another process in between is not overwritten
+    let _data_lock = super::data_lock::acquire()?;
     if !config_path.exists() {
         let config = AppConfig::new();
         // [FIX #1460] Persist initial config to prevent new API Key on every refresh
         let _ = save_app_config(&config);
         return Ok(config);
     }
-    
-    let content = fs::read_to_string(&config_path)
+
+    let (config, migrated) = read_app_config(&data_dir, &config_path)?;
+    // If migration occurred, auto-save once to clean up the file
+    if migrated {
+        let _ = save_app_config(&config);
+    }
+
+    Ok(config)
+}
+
+/// Read and migrate the config file; returns true if it should be written back
+fn read_app_config(data_dir: &Path, config_path: &Path) -> Result<(AppConfig, bool), String> {
+    let content = fs::read_to_string(config_path)
         .map_err(|e| format!("failed_to_read_config_file: {}", e))?;
     
     let mut v: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| format!("failed_to_parse_config_file: {}", e))?;
-    
-    let mut modified = false;
-
-    // Migration logic
-    if let Some(proxy) = v.get_mut("proxy") {
-        // [FIX #1738] Enhanced type checking for custom_mapping
-        // Ensures the field is always parsed as an object, preventing type mismatch errors
-        let mut custom_mapping = match proxy.get("custom_mapping") {
-            Some(m) if m.is_object() => m.as_object().unwrap().clone(),
-            Some(m) => {
-                // If custom_mapping is not an object type (e.g., string), log warning and reset to empty
-                tracing::warn!("Invalid custom_mapping type (expected object, got {:?}), resetting to empty", m);
-                serde_json::Map::new()
-            }
-            None => serde_json::Map::new(),
-        };
 
-        // Migrate Anthropic mapping
-        if let Some(anthropic) = proxy.get_mut("anthropic_mapping").and_then(|m| m.as_object_mut()) {
-            for (k, v) in anthropic.iter() {
-                // Only move non-series fields, as series fields are now handled by Preset logic or builtin tables
-                if !k.ends_with("-series") {
-                    if !custom_mapping.contains_key(k) {
-                        custom_mapping.insert(k.clone(), v.clone());
-                    }
-                }
-            }
-            // Remove old field
-            proxy.as_object_mut().unwrap().remove("anthropic_mapping");
-            modified = true;
-        }
+    // Ordered schema migrations (original file is backed up before the first one runs)
+    let migrated = super::schema::migrate_loaded(
+        SchemaKind::AppConfig,
+        data_dir,
+        config_path,
+        content.as_bytes(),
+        &mut v,
+    )?;
 
-        // Migrate OpenAI mapping
-        if let Some(openai) = proxy.get_mut("openai_mapping").and_then(|m| m.as_object_mut()) {
-            for (k, v) in openai.iter() {
-                if !k.ends_with("-series") {
-                    if !custom_mapping.contains_key(k) {
-                        custom_mapping.insert(k.clone(), v.clone());
-                    }
-                }
-            }
-            // Remove old field
-            proxy.as_object_mut().unwrap().remove("openai_mapping");
-            modified = true;
-        }
+    let mut config: AppConfig = serde_json::from_value(v)
+        .map_err(|e| format!("failed_to_convert_config_after_migration: {}", e))?;
+    let needs_reseal = open_secrets(&mut config);
+    Ok((config, migrated || needs_reseal))
+}
 
-        if modified {
-            proxy.as_object_mut().unwrap().insert("custom_mapping".to_string(), serde_json::Value::Object(custom_mapping));
+/// Encrypt webhook signing secrets before the config is written to disk
+fn seal_secrets(config: &mut AppConfig) -> Result<(), String> {
+    for secret in config.webhooks.endpoints.iter_mut().filter_map(|e| e.secret.as_mut()) {
+        if !secret.is_empty() && !crypto::is_encrypted_secret(secret) {
+            *secret = crypto::encrypt_secret(secret)
+                .map_err(|e| format!("failed_to_encrypt_webhook_secret: {}", e))?;
         }
     }
+    Ok(())
+}
 
-    let config: AppConfig = serde_json::from_value(v)
-        .map_err(|e| format!("failed_to_convert_config_after_migration: {}", e))?;
-    
-    // If migration occurred, auto-save once to clean up the file
-    if modified {
-        let _ = save_app_config(&config);
+/// Decrypt secrets loaded from disk. Returns true if any secret was stored in plaintext
+/// or under a retired key and should be written back.
+/// A secret that cannot be decrypted is kept as is (e.g. the key changed) so it is not lost.
+fn open_secrets(config: &mut AppConfig) -> bool {
+    let mut needs_reseal = false;
+    for endpoint in config.webhooks.endpoints.iter_mut() {
+        let Some(secret) = endpoint.secret.as_mut() else {
+            continue;
+        };
+        needs_reseal |= crypto::needs_reencryption(secret);
+        if crypto::is_encrypted_secret(secret) {
+            match crypto::decrypt_secret(secret) {
+                Ok(plaintext) => *secret = plaintext,
+                Err(e) => super::logger::log_warn(&format!(
+                    "Failed to decrypt webhook secret for {}: {}",
+                    endpoint.url, e
+                )),
+            }
+        }
     }
-
-    Ok(config)
+    needs_reseal
 }
 
 /// Save application configuration
 pub fn save_app_config(config: &AppConfig) -> Result<(), String> {
-    let data_dir = get_data_dir()?;
-    let config_path = data_dir.join(CONFIG_FILE);
+    let _data_lock = super::data_lock::acquire()?;
+    let config_path = super::paths::config_file()?;
+    // Never overwrite a config written by a newer version
+    super::schema::ensure_supported_file(SchemaKind::AppConfig, &config_path)?;
     
-    let content = serde_json::to_string_pretty(config)
+    let mut sealed = config.clone();
+    seal_secrets(&mut sealed)?;
+    let content = serde_json::to_string_pretty(&sealed)
         .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
     
-    fs::write(&config_path, content)
-        .map_err(|e| format!("failed_to_save_config: {}", e))
+    // Keep the previous version as gui_config.json.bak
+    crate::utils::atomic_file::write_atomic_with_backup(&config_path, content)
+        .map_err(|e| format!("failed_to_save_config: {}", e))?;
+    super::endpoints::invalidate();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::WebhookEndpoint;
+    use crate::modules::test_support::{EnvOverride, TestDataDir};
+
+    #[test]
+    fn test_webhook_secrets_are_sealed_on_disk() {
+        let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
+        let mut config = AppConfig::new();
+        config.webhooks.endpoints.push(WebhookEndpoint {
+            url: "http://chat.local/hook".into(),
+            secret: Some("s3cret".into()),
+            events: Vec::new(),
+            enabled: true,
+        });
+
+        let mut sealed = config.clone();
+        seal_secrets(&mut sealed).unwrap();
+        let stored = sealed.webhooks.endpoints[0].secret.clone().unwrap();
+        assert!(crypto::is_encrypted_secret(&stored));
+        assert!(!open_secrets(&mut sealed));
+        assert_eq!(sealed.webhooks.endpoints[0].secret.as_deref(), Some("s3cret"));
+
+        // 旧版明文密钥可读，并标记需要写回
+        assert!(open_secrets(&mut config));
+        assert_eq!(config.webhooks.endpoints[0].secret.as_deref(), Some("s3cret"));
+    }
 }
@@ -0,0 +1,333 @@
+//! 数据目录跨进程咨询锁（data.lock）
+//!
+//! GUI、Docker headless 实例与脚本可能共享同一个 ABV_DATA_DIR。
+//! 写入索引、账号与配置前需持有该锁；读取无需等待，写入都是原子替换，读到的总是完整的文件。
+//! 锁文件记录 PID / 主机名 / 心跳，
+//! 持有者崩溃后心跳过期即视为失效锁，可被其他进程接管。
+//! 同一进程内可重入（引用计数），进程内的互斥仍由各模块自己的 Mutex 负责。
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::time::{Duration, Instant};
+
+const LOCK_FILE: &str = "data.lock";
+/// 写入方等待锁释放的最长时间
+const WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// 心跳超过该时长未更新即视为失效锁
+const STALE_AFTER_SECS: i64 = 30;
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 锁文件内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub host: String,
+    pub acquired_at: i64,
+    pub heartbeat: i64,
+}
+
+struct HeldLock {
+    count: usize,
+    info: LockInfo,
+}
+
+/// 本进程当前持有的锁（按锁文件路径）
+static HELD: Lazy<Mutex<HashMap<PathBuf, HeldLock>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+/// 进程内唯一的心跳线程，首次加锁时启动
+static HEARTBEAT: Once = Once::new();
+
+/// 持有期间保持数据目录锁，Drop 时释放（引用计数归零才删除锁文件）
+pub struct DataDirLock {
+    path: PathBuf,
+}
+
+impl Drop for DataDirLock {
+    fn drop(&mut self) {
+        let mut held = HELD.lock();
+        let Some(entry) = held.get_mut(&self.path) else {
+            return;
+        };
+        entry.count -= 1;
+        if entry.count > 0 {
+            return;
+        }
+        let entry = held.remove(&self.path).expect("lock entry exists");
+
+        // 仅删除仍属于本进程的锁文件（若已被判定失效并被他人接管则不动）
+        if read_info(&self.path).is_some_and(|info| is_same_owner(&info, &entry.info)) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn host_name() -> String {
+    sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string())
+}
+
+fn lock_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(LOCK_FILE)
+}
+
+fn is_same_owner(a: &LockInfo, b: &LockInfo) -> bool {
+    a.pid == b.pid && a.host == b.host && a.acquired_at == b.acquired_at
+}
+
+fn read_info(path: &Path) -> Option<LockInfo> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_info(path: &Path, info: &LockInfo) -> std::io::Result<()> {
+    let content = serde_json::to_string(info).map_err(std::io::Error::other)?;
+    // 原子替换，避免其他进程读到写了一半的心跳
+    crate::utils::atomic_file::write_atomic(path, content)
+}
+
+fn process_alive(pid: u32) -> bool {
+    let pid = sysinfo::Pid::from_u32(pid);
+    let mut system = sysinfo::System::new();
+    system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]));
+    system.process(pid).is_some()
+}
+
+/// 判断锁文件是否已失效：心跳过期，或同主机上持有进程已不存在
+fn is_stale(path: &Path, info: Option<&LockInfo>) -> bool {
+    let now = chrono::Utc::now().timestamp();
+    match info {
+        Some(info) => {
+            if now - info.heartbeat > STALE_AFTER_SECS {
+                return true;
+            }
+            if info.host == host_name() {
+                // 本进程未登记却留有同 PID 的锁文件（如容器重启后 PID 复用），同样视为失效
+                return info.pid == std::process::id() || !process_alive(info.pid);
+            }
+            false
+        }
+        // 锁文件刚创建尚未写入内容，或内容损坏：按修改时间判断
+        None => {
+            let age = fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.elapsed().ok());
+            match age {
+                Some(age) => age.as_secs() as i64 > STALE_AFTER_SECS,
+                None => true,
+            }
+        }
+    }
+}
+
+fn describe_holder(path: &Path, info: Option<&LockInfo>) -> String {
+    match info {
+        Some(info) => format!(
+            "data_dir_locked: another process (pid {} on {}) is writing to {:?}, last heartbeat {}s ago. Please retry later.",
+            info.pid,
+            info.host,
+            path.parent().unwrap_or(path),
+            chrono::Utc::now().timestamp() - info.heartbeat
+        ),
+        None => format!(
+            "data_dir_locked: {:?} is held by another process. Please retry later.",
+            path
+        ),
+    }
+}
+
+/// 刷新本进程持有的所有锁的心跳
+fn refresh_heartbeats() {
+    let now = chrono::Utc::now().timestamp();
+    let mut held = HELD.lock();
+    for (path, entry) in held.iter_mut() {
+        entry.info.heartbeat = now;
+        if let Err(e) = write_info(path, &entry.info) {
+            crate::modules::logger::log_warn(&format!(
+                "Failed to refresh data dir lock heartbeat: {}",
+                e
+            ));
+        }
+    }
+}
+
+fn start_heartbeat() {
+    HEARTBEAT.call_once(|| {
+        std::thread::spawn(|| loop {
+            std::thread::sleep(HEARTBEAT_INTERVAL);
+            refresh_heartbeats();
+        });
+    });
+}
+
+/// 接管失效锁：先将锁文件原子重命名为唯一名称，再对移走的文件重新判断持有者。
+/// 并发接管时只有一个进程能移走同一个文件；若移走的已是他人刚创建的新锁则原样放回。
+fn take_over_stale(path: &Path) -> bool {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let taken = path.with_file_name(format!("{}.stale.{}", file_name, uuid::Uuid::new_v4()));
+    match fs::rename(path, &taken) {
+        Ok(()) => {}
+        // 已被其他进程移走或删除，重新尝试创建
+        Err(e) if e.kind() == ErrorKind::NotFound => return true,
+        Err(_) => return false,
+    }
+
+    let info = read_info(&taken);
+    if is_stale(&taken, info.as_ref()) {
+        crate::modules::logger::log_warn(&format!(
+            "Removed stale data dir lock {:?} (holder: {:?})",
+            path, info
+        ));
+        let _ = fs::remove_file(&taken);
+        return true;
+    }
+
+    // hard_link 不会覆盖已存在的文件：放回失败说明又有新锁，此时保留新锁
+    if let Err(e) = fs::hard_link(&taken, path) {
+        crate::modules::logger::log_warn(&format!(
+            "Failed to restore live data dir lock {:?} (holder: {:?}): {}",
+            path, info, e
+        ));
+    }
+    let _ = fs::remove_file(&taken);
+    false
+}
+
+/// 获取指定数据目录的锁，最多等待 `timeout`
+pub fn acquire_in_dir_with_timeout(data_dir: &Path, timeout: Duration) -> Result<DataDirLock, String> {
+    let path = lock_path(data_dir);
+    let started = Instant::now();
+
+    loop {
+        {
+            let mut held = HELD.lock();
+            if let Some(entry) = held.get_mut(&path) {
+                entry.count += 1;
+                return Ok(DataDirLock { path });
+            }
+
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let now = chrono::Utc::now().timestamp();
+                    let info = LockInfo {
+                        pid: std::process::id(),
+                        host: host_name(),
+                        acquired_at: now,
+                        heartbeat: now,
+                    };
+                    let content = serde_json::to_string(&info)
+                        .map_err(|e| format!("failed_to_write_data_lock: {}", e))?;
+                    if let Err(e) = file.write_all(content.as_bytes()) {
+                        let _ = fs::remove_file(&path);
+                        return Err(format!("failed_to_write_data_lock: {}", e));
+                    }
+
+                    held.insert(path.clone(), HeldLock { count: 1, info });
+                    start_heartbeat();
+                    return Ok(DataDirLock { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(format!("failed_to_create_data_lock: {}", e)),
+            }
+        }
+
+        let info = read_info(&path);
+        if is_stale(&path, info.as_ref()) && take_over_stale(&path) {
+            continue;
+        }
+
+        if started.elapsed() >= timeout {
+            return Err(describe_holder(&path, info.as_ref()));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// 获取指定数据目录的锁（同一进程内可重入）
+pub fn acquire_in_dir(data_dir: &Path) -> Result<DataDirLock, String> {
+    acquire_in_dir_with_timeout(data_dir, WAIT_TIMEOUT)
+}
+
+/// 获取当前数据目录的锁（同一进程内可重入）
+pub fn acquire() -> Result<DataDirLock, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    acquire_in_dir(&data_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDataDir;
+
+    fn write_foreign_lock(dir: &Path, heartbeat: i64) {
+        let info = LockInfo {
+            pid: 4242,
+            host: format!("{}-other", host_name()),
+            acquired_at: heartbeat,
+            heartbeat,
+        };
+        write_info(&lock_path(dir), &info).unwrap();
+    }
+
+    #[test]
+    fn test_lock_is_reentrant_and_released() {
+        let dir = TestDataDir::new();
+        let outer = acquire_in_dir_with_timeout(dir.path(), Duration::ZERO).unwrap();
+        let inner = acquire_in_dir_with_timeout(dir.path(), Duration::ZERO).unwrap();
+        assert!(lock_path(dir.path()).exists());
+
+        // 共享的心跳线程刷新所有持有中的锁
+        let mut info = read_info(&lock_path(dir.path())).unwrap();
+        info.heartbeat = 0;
+        write_info(&lock_path(dir.path()), &info).unwrap();
+        refresh_heartbeats();
+        assert!(read_info(&lock_path(dir.path())).unwrap().heartbeat > 0);
+
+        drop(inner);
+        assert!(lock_path(dir.path()).exists(), "outer guard still holds the lock");
+        drop(outer);
+        assert!(!lock_path(dir.path()).exists());
+    }
+
+    #[test]
+    fn test_live_foreign_lock_blocks_writers() {
+        let dir = TestDataDir::new();
+        write_foreign_lock(dir.path(), chrono::Utc::now().timestamp());
+
+        let err = acquire_in_dir_with_timeout(dir.path(), Duration::from_millis(200))
+            .err()
+            .expect("foreign lock must block");
+        assert!(err.starts_with("data_dir_locked"), "{}", err);
+        assert!(err.contains("pid 4242"));
+    }
+
+    #[test]
+    fn test_stale_foreign_lock_is_taken_over() {
+        let dir = TestDataDir::new();
+        write_foreign_lock(dir.path(), chrono::Utc::now().timestamp() - STALE_AFTER_SECS - 5);
+
+        let guard = acquire_in_dir_with_timeout(dir.path(), Duration::ZERO).unwrap();
+        let info = read_info(&lock_path(dir.path())).unwrap();
+        assert_eq!(info.pid, std::process::id());
+        drop(guard);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0, "no renamed lock left behind");
+    }
+
+    #[test]
+    fn test_takeover_restores_a_live_lock() {
+        let dir = TestDataDir::new();
+        let heartbeat = chrono::Utc::now().timestamp();
+        write_foreign_lock(dir.path(), heartbeat);
+
+        // 另一个进程抢先接管并写入了新锁：移走后复查发现未失效，须原样放回
+        assert!(!take_over_stale(&lock_path(dir.path())));
+        let info = read_info(&lock_path(dir.path())).unwrap();
+        assert_eq!((info.pid, info.heartbeat), (4242, heartbeat));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
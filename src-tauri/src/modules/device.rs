@@ -1,5 +1,6 @@
 use crate::models::DeviceProfile;
-use crate::modules::{logger, process};
+use crate::modules::{logger, paths, process};
+use crate::utils::atomic_file;
 use chrono::Local;
 use rand::{distributions::Alphanumeric, Rng};
 use rusqlite::Connection;
@@ -8,18 +9,6 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
-const DATA_DIR: &str = ".antigravity_tools";
-const GLOBAL_BASELINE: &str = "device_original.json";
-
-fn get_data_dir() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("failed_to_get_home_dir")?;
-    let data_dir = home.join(DATA_DIR);
-    if !data_dir.exists() {
-        fs::create_dir_all(&data_dir).map_err(|e| format!("failed_to_create_data_dir: {}", e))?;
-    }
-    Ok(data_dir)
-}
-
 /// Find storage.json path (prefer custom/portable paths)
 pub fn get_storage_path() -> Result<PathBuf, String> {
     // 1) --user-data-dir flag
@@ -212,7 +201,8 @@ pub fn write_profile(storage_path: &Path, profile: &DeviceProfile) -> Result<(),
 
     let updated = serde_json::to_string_pretty(&json)
         .map_err(|e| format!("serialize_failed: {}", e))?;
-    fs::write(storage_path, updated).map_err(|e| format!("write_failed ({:?}): {}", storage_path, e))?;
+    atomic_file::write_atomic(storage_path, updated)
+        .map_err(|e| format!("write_failed ({:?}): {}", storage_path, e))?;
     logger::log_info(&format!("device_profile_written to {:?}", storage_path));
 
     // Sync ItemTable.storage.serviceMachineId in state.vscdb
@@ -237,7 +227,7 @@ pub fn sync_service_machine_id(storage_path: &Path, service_id: &str) -> Result<
 
     let updated = serde_json::to_string_pretty(&json)
         .map_err(|e| format!("serialize_failed: {}", e))?;
-    fs::write(storage_path, updated).map_err(|e| format!("write_failed: {}", e))?;
+    atomic_file::write_atomic(storage_path, updated).map_err(|e| format!("write_failed: {}", e))?;
     logger::log_info("service_machine_id_synced");
 
     let _ = sync_state_service_machine_id_value(service_id);
@@ -284,7 +274,8 @@ pub fn sync_service_machine_id_from_storage(storage_path: &Path) -> Result<(), S
 
     if dirty {
         let updated = serde_json::to_string_pretty(&json).map_err(|e| format!("serialize_failed: {}", e))?;
-        fs::write(storage_path, updated).map_err(|e| format!("write_failed: {}", e))?;
+        atomic_file::write_atomic(storage_path, updated)
+            .map_err(|e| format!("write_failed: {}", e))?;
         logger::log_info("service_machine_id_added");
     }
 
@@ -318,8 +309,7 @@ fn sync_state_service_machine_id_value(service_id: &str) -> Result<(), String> {
 
 /// Load/Save global original profile (shared across all accounts)
 pub fn load_global_original() -> Option<DeviceProfile> {
-    if let Ok(dir) = get_data_dir() {
-        let path = dir.join(GLOBAL_BASELINE);
+    if let Ok(path) = paths::device_baseline_file() {
         if path.exists() {
             if let Ok(content) = fs::read_to_string(&path) {
                 if let Ok(profile) = serde_json::from_str::<DeviceProfile>(&content) {
@@ -332,14 +322,13 @@ pub fn load_global_original() -> Option<DeviceProfile> {
 }
 
 pub fn save_global_original(profile: &DeviceProfile) -> Result<(), String> {
-    let dir = get_data_dir()?;
-    let path = dir.join(GLOBAL_BASELINE);
+    let path = paths::device_baseline_file()?;
     if path.exists() {
         return Ok(()); // already exists, don't overwrite
     }
     let content =
         serde_json::to_string_pretty(profile).map_err(|e| format!("serialize_failed: {}", e))?;
-    fs::write(&path, content).map_err(|e| format!("write_failed: {}", e))
+    atomic_file::write_atomic(&path, content).map_err(|e| format!("write_failed: {}", e))
 }
 
 /// List storage.json backups in current directory (descending by time)
@@ -382,7 +371,9 @@ pub fn restore_backup(storage_path: &Path, use_oldest: bool) -> Result<PathBuf,
     };
     // backup current first
     let _ = backup_storage(storage_path)?;
-    fs::copy(&target, storage_path).map_err(|e| format!("restore_failed: {}", e))?;
+    let content = fs::read(&target).map_err(|e| format!("restore_failed: {}", e))?;
+    atomic_file::write_atomic(storage_path, content)
+        .map_err(|e| format!("restore_failed: {}", e))?;
     logger::log_info(&format!("storage_json_restored: {:?}", target));
     Ok(target)
 }
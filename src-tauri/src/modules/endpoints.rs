@@ -0,0 +1,162 @@
+//! 上游服务地址
+//!
+//! 每个地址按 环境变量 > `AppConfig.endpoints` > 内置默认值 解析，
+//! 便于经由自建网关访问，或在测试中指向本地 mock 服务。
+//! 配置中的地址按配置文件缓存，文件修改时间变化或保存配置时重新读取。
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::models::config::EndpointsConfig;
+
+/// 配置文件路径、修改时间与其中的地址配置
+type CachedEndpoints = (PathBuf, Option<SystemTime>, EndpointsConfig);
+
+static CONFIGURED: Lazy<RwLock<Option<CachedEndpoints>>> = Lazy::new(|| RwLock::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    /// fetchAvailableModels
+    QuotaApi,
+    /// loadCodeAssist 等 v1internal 接口的基础地址
+    CloudCodeBase,
+    Token,
+    UserInfo,
+    Auth,
+}
+
+impl Endpoint {
+    pub fn env_var(self) -> &'static str {
+        match self {
+            Endpoint::QuotaApi => "ABV_QUOTA_API_URL",
+            Endpoint::CloudCodeBase => "ABV_CLOUD_CODE_BASE_URL",
+            Endpoint::Token => "ABV_TOKEN_URL",
+            Endpoint::UserInfo => "ABV_USERINFO_URL",
+            Endpoint::Auth => "ABV_AUTH_URL",
+        }
+    }
+
+    pub fn default_url(self) -> &'static str {
+        match self {
+            Endpoint::QuotaApi => {
+                "https://cloudcode-pa.googleapis.com/v1internal:fetchAvailableModels"
+            }
+            Endpoint::CloudCodeBase => "https://daily-cloudcode-pa.sandbox.googleapis.com",
+            Endpoint::Token => "https://oauth2.googleapis.com/token",
+            Endpoint::UserInfo => "https://www.googleapis.com/oauth2/v2/userinfo",
+            Endpoint::Auth => "https://accounts.google.com/o/oauth2/v2/auth",
+        }
+    }
+
+    fn configured(self, config: &EndpointsConfig) -> Option<&str> {
+        match self {
+            Endpoint::QuotaApi => config.quota_api_url.as_deref(),
+            Endpoint::CloudCodeBase => config.cloud_code_base_url.as_deref(),
+            Endpoint::Token => config.token_url.as_deref(),
+            Endpoint::UserInfo => config.userinfo_url.as_deref(),
+            Endpoint::Auth => config.auth_url.as_deref(),
+        }
+    }
+}
+
+fn resolve_with(
+    endpoint: Endpoint,
+    config: &EndpointsConfig,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> String {
+    let non_empty = |v: &str| {
+        let v = v.trim().trim_end_matches('/');
+        (!v.is_empty()).then(|| v.to_string())
+    };
+    env(endpoint.env_var())
+        .and_then(|v| non_empty(&v))
+        .or_else(|| endpoint.configured(config).and_then(non_empty))
+        .unwrap_or_else(|| endpoint.default_url().to_string())
+}
+
+/// 配置中的地址，配置文件未变化时使用缓存
+fn configured_endpoints() -> EndpointsConfig {
+    let Ok(path) = crate::modules::paths::config_file() else {
+        return EndpointsConfig::default();
+    };
+    let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+    if let Some((cached_path, cached_modified, config)) = CONFIGURED.read().as_ref() {
+        if *cached_path == path && *cached_modified == modified {
+            return config.clone();
+        }
+    }
+
+    match crate::modules::config::load_app_config() {
+        Ok(config) => {
+            *CONFIGURED.write() = Some((path, modified, config.endpoints.clone()));
+            config.endpoints
+        }
+        Err(_) => EndpointsConfig::default(),
+    }
+}
+
+/// 清空缓存，保存配置后调用
+pub fn invalidate() {
+    *CONFIGURED.write() = None;
+}
+
+/// 当前生效的地址
+pub fn url(endpoint: Endpoint) -> String {
+    resolve_with(endpoint, &configured_endpoints(), &|name| {
+        std::env::var(name).ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::{EnvOverride, TestDataDir};
+
+    #[test]
+    fn test_resolution_order() {
+        let config = EndpointsConfig {
+            token_url: Some("http://gateway.local/token/".into()),
+            userinfo_url: Some("  ".into()),
+            ..Default::default()
+        };
+        let no_env = |_: &str| None;
+        assert_eq!(
+            resolve_with(Endpoint::Token, &config, &no_env),
+            "http://gateway.local/token"
+        );
+        // 空白配置视为未设置
+        assert_eq!(
+            resolve_with(Endpoint::UserInfo, &config, &no_env),
+            Endpoint::UserInfo.default_url()
+        );
+
+        let env = |name: &str| {
+            (name == "ABV_TOKEN_URL").then(|| "http://127.0.0.1:9000/token".to_string())
+        };
+        assert_eq!(
+            resolve_with(Endpoint::Token, &config, &env),
+            "http://127.0.0.1:9000/token"
+        );
+        assert_eq!(
+            resolve_with(Endpoint::QuotaApi, &config, &env),
+            Endpoint::QuotaApi.default_url()
+        );
+    }
+
+    #[test]
+    fn test_configured_endpoints_follow_saved_config() {
+        let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
+        let mut config = crate::modules::config::load_app_config().unwrap();
+        assert_eq!(configured_endpoints().token_url, None);
+
+        config.endpoints.token_url = Some("http://gateway.local/token".into());
+        crate::modules::config::save_app_config(&config).unwrap();
+        assert_eq!(
+            configured_endpoints().token_url.as_deref(),
+            Some("http://gateway.local/token")
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! 配额耗尽预测
+//!
+//! 根据配额历史中最近一段（上次重置之后）的下降速度，估计每个账号每个监控模型何时降到
+//! `QuotaProtectionConfig.threshold_percentage`，并汇总整个号池在下次重置前还能用多少"模型·小时"。
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::models::config::QuotaProtectionConfig;
+use crate::models::Account;
+use crate::modules::quota_history::{self, QuotaPoint};
+
+/// 计算消耗速度时回看的时间窗口
+const RATE_WINDOW_SECS: i64 = 6 * 3600;
+/// 数据跨度不足该时长时不估计速度
+const MIN_RATE_SPAN_SECS: i64 = 600;
+const SECS_PER_HOUR: f64 = 3600.0;
+
+type History = BTreeMap<(String, String), Vec<QuotaPoint>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForecastStatus {
+    /// 已低于保护阈值
+    BelowThreshold,
+    /// 将在重置前降到阈值
+    BeforeReset,
+    /// 重置先于降到阈值
+    ResetFirst,
+    /// 近期没有消耗，无法估计
+    Idle,
+}
+
+/// 单个账号单个模型的预测
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelForecast {
+    pub account_id: String,
+    pub email: String,
+    pub model: String,
+    pub remaining_percentage: i32,
+    pub threshold_percentage: u32,
+    /// 近期消耗速度（百分点/小时）
+    pub rate_per_hour: Option<f64>,
+    pub reset_at: Option<i64>,
+    /// 预计降到阈值的时间
+    pub threshold_at: Option<i64>,
+    pub status: ForecastStatus,
+}
+
+/// 号池内单个模型的汇总预测
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolForecast {
+    pub model: String,
+    pub accounts: usize,
+    /// 各账号阈值以上剩余额度之和（百分点）
+    pub headroom: f64,
+    /// 各账号消耗速度之和（百分点/小时）
+    pub rate_per_hour: Option<f64>,
+    pub next_reset_at: Option<i64>,
+    /// 各账号在自身重置前按近期速度还能使用的小时数之和
+    pub model_hours_left: Option<f64>,
+    /// 按当前总消耗速度，号池整体降到阈值的时间
+    pub exhausted_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaForecast {
+    pub generated_at: i64,
+    pub accounts: Vec<ModelForecast>,
+    pub pool: Vec<PoolForecast>,
+}
+
+/// Consumption rate in percentage points per hour, using only points after the latest reset
+fn consumption_rate(points: &[QuotaPoint]) -> Option<f64> {
+    // 剩余比例回升即视为重置，只使用最后一次回升之后的数据
+    let start = points
+        .windows(2)
+        .rposition(|pair| pair[1].remaining > pair[0].remaining)
+        .map_or(0, |i| i + 1);
+    let segment = &points[start..];
+    let (first, last) = (segment.first()?, segment.last()?);
+
+    let span = last.ts - first.ts;
+    let dropped = (first.remaining - last.remaining) * 100.0;
+    if span < MIN_RATE_SPAN_SECS || dropped <= 0.0 {
+        return None;
+    }
+    Some(dropped / span as f64 * SECS_PER_HOUR)
+}
+
+fn forecast_model(
+    account: &Account,
+    model: &crate::models::quota::ModelQuota,
+    last_updated: i64,
+    rate_per_hour: Option<f64>,
+    config: &QuotaProtectionConfig,
+) -> ModelForecast {
+    let remaining = model.percentage as f64;
+    let threshold_percentage = config.threshold_for(&model.name);
+    let threshold = threshold_percentage as f64;
+    let reset_at = model.reset_at();
+
+    let threshold_at = rate_per_hour
+        .filter(|_| remaining > threshold)
+        .map(|rate| last_updated + ((remaining - threshold) / rate * SECS_PER_HOUR).round() as i64);
+    let status = if remaining <= threshold {
+        ForecastStatus::BelowThreshold
+    } else {
+        match threshold_at {
+            None => ForecastStatus::Idle,
+            Some(at) if reset_at.is_some_and(|reset| reset <= at) => ForecastStatus::ResetFirst,
+            Some(_) => ForecastStatus::BeforeReset,
+        }
+    };
+
+    ModelForecast {
+        account_id: account.id.clone(),
+        email: account.email.clone(),
+        model: model.name.clone(),
+        remaining_percentage: model.percentage,
+        threshold_percentage,
+        rate_per_hour,
+        reset_at,
+        threshold_at,
+        status,
+    }
+}
+
+fn pool_forecast(model: &str, forecasts: &[&ModelForecast], now: i64) -> PoolForecast {
+    let headroom_of = |f: &ModelForecast| {
+        (f.remaining_percentage as f64 - f.threshold_percentage as f64).max(0.0)
+    };
+    let headroom: f64 = forecasts.iter().map(|f| headroom_of(f)).sum();
+
+    let rates: Vec<f64> = forecasts.iter().filter_map(|f| f.rate_per_hour).collect();
+    let rate_per_hour = (!rates.is_empty()).then(|| rates.iter().sum::<f64>());
+
+    // 没有自身速度的账号按号池平均速度估算
+    let model_hours_left = rate_per_hour.map(|total| {
+        let average = total / rates.len() as f64;
+        forecasts
+            .iter()
+            .map(|f| {
+                let hours = headroom_of(f) / f.rate_per_hour.unwrap_or(average);
+                match f.reset_at.filter(|&reset| reset > now) {
+                    Some(reset) => hours.min((reset - now) as f64 / SECS_PER_HOUR),
+                    None => hours,
+                }
+            })
+            .sum()
+    });
+
+    PoolForecast {
+        model: model.to_string(),
+        accounts: forecasts.len(),
+        headroom,
+        rate_per_hour,
+        next_reset_at: forecasts
+            .iter()
+            .filter_map(|f| f.reset_at)
+            .filter(|&reset| reset > now)
+            .min(),
+        model_hours_left,
+        exhausted_at: rate_per_hour
+            .map(|rate| now + (headroom / rate * SECS_PER_HOUR).round() as i64),
+    }
+}
+
+fn build_forecast(
+    accounts: &[Account],
+    history: &History,
+    config: &QuotaProtectionConfig,
+    now: i64,
+) -> QuotaForecast {
+    let mut forecasts = Vec::new();
+    for account in accounts.iter().filter(|a| !a.disabled) {
+        let Some(quota) = account.quota.as_ref().filter(|q| !q.is_forbidden) else {
+            continue;
+        };
+        for model in quota.metered_models().filter(|m| config.monitors(&m.name)) {
+            let rate = history
+                .get(&(account.id.clone(), model.name.clone()))
+                .and_then(|points| consumption_rate(points));
+            forecasts.push(forecast_model(
+                account,
+                model,
+                quota.last_updated,
+                rate,
+                config,
+            ));
+        }
+    }
+
+    let mut by_model: BTreeMap<&str, Vec<&ModelForecast>> = BTreeMap::new();
+    for forecast in &forecasts {
+        by_model.entry(&forecast.model).or_default().push(forecast);
+    }
+    let pool = by_model
+        .iter()
+        .map(|(model, group)| pool_forecast(model, group, now))
+        .collect();
+
+    forecasts.sort_by_key(|f| (f.threshold_at.is_none(), f.threshold_at));
+    QuotaForecast {
+        generated_at: now,
+        accounts: forecasts,
+        pool,
+    }
+}
+
+/// 预测各账号及号池的配额耗尽时间
+pub fn quota_forecast() -> Result<QuotaForecast, String> {
+    let now = chrono::Utc::now().timestamp();
+    let config = crate::modules::config::load_app_config()?;
+    let accounts = crate::modules::account::list_accounts()?;
+    let history = quota_history::recent_series(now - RATE_WINDOW_SECS).unwrap_or_else(|e| {
+        crate::modules::logger::log_warn(&format!("Quota forecast without history: {}", e));
+        History::new()
+    });
+    Ok(build_forecast(
+        &accounts,
+        &history,
+        &config.quota_protection,
+        now,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::{account_with_quota, NOW};
+    use std::collections::HashMap;
+
+    fn point(ts: i64, remaining: f64) -> QuotaPoint {
+        QuotaPoint {
+            ts,
+            remaining,
+            min_remaining: remaining,
+            reset_time: None,
+            tier: None,
+            resolution: 0,
+        }
+    }
+
+    fn account(id: &str, percentage: i32, reset_time: &str) -> Account {
+        account_with_quota(
+            id,
+            &[("claude-sonnet-4-6", percentage, reset_time), ("gemini-3-pro-image", 50, "")],
+        )
+    }
+
+    #[test]
+    fn test_rate_uses_points_after_latest_reset() {
+        // 重置前的下降不计入
+        let points = [
+            point(NOW - 7200, 0.9),
+            point(NOW - 5400, 0.1),
+            point(NOW - 3600, 1.0),
+            point(NOW - 1800, 0.9),
+            point(NOW, 0.8),
+        ];
+        let rate = consumption_rate(&points).unwrap();
+        assert!((rate - 20.0).abs() < 1e-9);
+
+        assert_eq!(
+            consumption_rate(&[point(NOW - 60, 0.9), point(NOW, 0.5)]),
+            None
+        );
+        assert_eq!(
+            consumption_rate(&[point(NOW - 3600, 0.5), point(NOW, 0.5)]),
+            None
+        );
+        assert_eq!(consumption_rate(&[]), None);
+    }
+
+    #[test]
+    fn test_account_and_pool_forecast() {
+        let config = QuotaProtectionConfig {
+            enabled: true,
+            threshold_percentage: 10,
+            monitored_models: vec!["claude".into()],
+            ..Default::default()
+        };
+        let accounts = vec![
+            // 50 点余量，20 点/小时 → 2.5 小时后到阈值，重置在 5 小时后
+            account("busy", 60, "2026-01-01T05:00:00Z"),
+            // 无消耗记录，按号池平均速度估算，重置在 1 小时后
+            account("idle", 90, "2026-01-01T01:00:00Z"),
+            account("low", 5, ""),
+        ];
+        let mut history = History::new();
+        history.insert(
+            ("busy".into(), "claude-sonnet-4-6".into()),
+            vec![point(NOW - 3600, 0.8), point(NOW, 0.6)],
+        );
+
+        let forecast = build_forecast(&accounts, &history, &config, NOW);
+        assert_eq!(forecast.accounts.len(), 3, "only monitored models");
+        let busy = &forecast.accounts[0];
+        assert_eq!(busy.account_id, "busy");
+        assert_eq!(busy.status, ForecastStatus::BeforeReset);
+        assert_eq!(busy.threshold_at, Some(NOW + 9000));
+        let status: HashMap<_, _> = forecast
+            .accounts
+            .iter()
+            .map(|f| (f.account_id.as_str(), f.status))
+            .collect();
+        assert_eq!(status["idle"], ForecastStatus::Idle);
+        assert_eq!(status["low"], ForecastStatus::BelowThreshold);
+
+        let pool = &forecast.pool[0];
+        assert_eq!(pool.model, "claude-sonnet-4-6");
+        assert_eq!(pool.headroom, 130.0);
+        assert!((pool.rate_per_hour.unwrap() - 20.0).abs() < 1e-9);
+        assert_eq!(pool.next_reset_at, Some(NOW + 3600));
+        // busy 2.5h + idle min(4h, 1h 后重置) + low 0
+        assert!((pool.model_hours_left.unwrap() - 3.5).abs() < 1e-9);
+        assert_eq!(pool.exhausted_at, Some(NOW + 23_400));
+    }
+}
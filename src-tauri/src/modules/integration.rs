@@ -64,8 +64,20 @@ impl SystemIntegration for DesktopIntegration {
     }
 
     fn show_notification(&self, title: &str, body: &str) {
-        // 使用 tauri-plugin-dialog 或原生通知（此处简化）
-        crate::modules::logger::log_info(&format!("[Notification] {}: {}", title, body));
+        use tauri_plugin_notification::NotificationExt;
+        if let Err(e) = self
+            .app_handle
+            .notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .show()
+        {
+            crate::modules::logger::log_warn(&format!(
+                "[Notification] Failed to show desktop notification ({}): {}: {}",
+                e, title, body
+            ));
+        }
     }
 }
 
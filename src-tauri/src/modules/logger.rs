@@ -2,7 +2,6 @@ use tracing::{info, warn, error};
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 use std::fs;
 use std::path::PathBuf;
-use crate::modules::account::get_data_dir;
 
 // Custom local timezone time formatter
 struct LocalTimer;
@@ -15,14 +14,7 @@ impl tracing_subscriber::fmt::time::FormatTime for LocalTimer {
 }
 
 pub fn get_log_dir() -> Result<PathBuf, String> {
-    let data_dir = get_data_dir()?;
-    let log_dir = data_dir.join("logs");
-    
-    if !log_dir.exists() {
-        fs::create_dir_all(&log_dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
-    }
-    
-    Ok(log_dir)
+    crate::modules::paths::log_dir()
 }
 
 /// Initialize the log system
@@ -0,0 +1,564 @@
+//! Prometheus / OpenMetrics 指标导出
+//!
+//! 可选的本地 HTTP 端点 `GET /metrics`，由 `AppConfig.metrics` 开启，或设置 `ABV_METRICS_ADDR`
+//! （同时覆盖监听地址），在进程启动时（早于 Tauri setup）开始监听。账号与配额相关的 gauge
+//! 在每次抓取时由账号文件计算（在阻塞线程池中读取），只以账号 ID 为标签，不输出邮箱；
+//! 切换次数、上游错误等计数器以及最近一次刷新结果保存在进程内存中，重启后归零。
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::models::config::MetricsConfig;
+use crate::models::Account;
+use crate::modules::account::RefreshStats;
+use crate::modules::audit::EventSource;
+use crate::modules::logger;
+
+const ENV_METRICS_ADDR: &str = "ABV_METRICS_ADDR";
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+static STATE: Lazy<Mutex<MetricsState>> = Lazy::new(|| Mutex::new(MetricsState::default()));
+
+/// 最近一次刷新结果
+#[derive(Debug, Clone, Copy)]
+struct RefreshOutcome {
+    ts: i64,
+    success: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BatchOutcome {
+    ts: i64,
+    total: usize,
+    success: usize,
+    failed: usize,
+}
+
+/// 进程内累计的指标
+#[derive(Debug, Clone, Default)]
+struct MetricsState {
+    /// (source, result) -> 次数
+    switches: BTreeMap<(&'static str, &'static str), u64>,
+    /// (host, status) -> 次数；网络错误的 status 为 "network"
+    api_errors: BTreeMap<(String, String), u64>,
+    last_refresh: HashMap<String, RefreshOutcome>,
+    last_batch: Option<BatchOutcome>,
+}
+
+fn source_label(source: EventSource) -> &'static str {
+    match source {
+        EventSource::Tray => "tray",
+        EventSource::Scheduler => "scheduler",
+        EventSource::Command => "command",
+        EventSource::Headless => "headless",
+        EventSource::AutoSwitch => "auto_switch",
+    }
+}
+
+/// 记录一次账号切换
+pub fn record_switch(source: EventSource, success: bool) {
+    let result = if success { "success" } else { "failure" };
+    *STATE
+        .lock()
+        .switches
+        .entry((source_label(source), result))
+        .or_default() += 1;
+}
+
+/// 记录一次上游错误响应（`status` 为空表示网络错误）
+pub fn record_api_error(host: &str, status: Option<u16>) {
+    let status = status.map_or_else(|| "network".to_string(), |s| s.to_string());
+    *STATE
+        .lock()
+        .api_errors
+        .entry((host.to_string(), status))
+        .or_default() += 1;
+}
+
+/// 记录单个账号的配额刷新结果
+pub fn record_account_refresh(account_id: &str, success: bool) {
+    STATE.lock().last_refresh.insert(
+        account_id.to_string(),
+        RefreshOutcome {
+            ts: chrono::Utc::now().timestamp(),
+            success,
+        },
+    );
+}
+
+/// 记录一次批量刷新的结果
+pub fn record_refresh_batch(stats: &RefreshStats) {
+    STATE.lock().last_batch = Some(BatchOutcome {
+        ts: chrono::Utc::now().timestamp(),
+        total: stats.total,
+        success: stats.success,
+        failed: stats.failed,
+    });
+}
+
+/// 文本格式输出
+struct Writer {
+    out: String,
+    openmetrics: bool,
+}
+
+impl Writer {
+    fn new(openmetrics: bool) -> Self {
+        Self {
+            out: String::new(),
+            openmetrics,
+        }
+    }
+
+    /// 指标族的 HELP/TYPE；计数器在 Prometheus 格式中以带 `_total` 的名字声明
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let name = if kind == "counter" && !self.openmetrics {
+            format!("{}_total", name)
+        } else {
+            name.to_string()
+        };
+        self.out.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} {}\n",
+            name, help, name, kind
+        ));
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+                .collect();
+            self.out.push_str(&format!("{{{}}}", labels.join(",")));
+        }
+        self.out.push_str(&format!(" {}\n", value));
+    }
+
+    fn finish(mut self) -> String {
+        if self.openmetrics {
+            self.out.push_str("# EOF\n");
+        }
+        self.out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn render(accounts: &[Account], state: &MetricsState, now: i64, openmetrics: bool) -> String {
+    let mut w = Writer::new(openmetrics);
+
+    w.family(
+        "antigravity_accounts",
+        "gauge",
+        "Number of accounts by state.",
+    );
+    let count = |f: fn(&Account) -> bool| accounts.iter().filter(|a| f(a)).count();
+    for (state, value) in [
+        ("total", accounts.len()),
+        ("disabled", count(|a| a.disabled)),
+        (
+            "forbidden",
+            count(|a| a.quota.as_ref().is_some_and(|q| q.is_forbidden)),
+        ),
+        ("validation_blocked", count(|a| a.validation_blocked)),
+    ] {
+        w.sample("antigravity_accounts", &[("state", state)], value as f64);
+    }
+
+    w.family(
+        "antigravity_model_remaining_ratio",
+        "gauge",
+        "Remaining quota fraction per account and model (0-1).",
+    );
+    for account in accounts {
+        for model in account.quota.iter().flat_map(|q| q.metered_models()) {
+            w.sample(
+                "antigravity_model_remaining_ratio",
+                &[("account_id", &account.id), ("model", &model.name)],
+                model.remaining(),
+            );
+        }
+    }
+
+    w.family(
+        "antigravity_model_reset_seconds",
+        "gauge",
+        "Seconds until the model quota resets.",
+    );
+    for account in accounts {
+        for model in account.quota.iter().flat_map(|q| q.models.iter()) {
+            if let Some(reset_at) = model.reset_at() {
+                w.sample(
+                    "antigravity_model_reset_seconds",
+                    &[("account_id", &account.id), ("model", &model.name)],
+                    (reset_at - now).max(0) as f64,
+                );
+            }
+        }
+    }
+
+    w.family(
+        "antigravity_token_expiry_seconds",
+        "gauge",
+        "Seconds until the access token expires (negative when expired).",
+    );
+    for account in accounts {
+        w.sample(
+            "antigravity_token_expiry_seconds",
+            &[("account_id", &account.id)],
+            (account.token.expiry_timestamp - now) as f64,
+        );
+    }
+
+    w.family(
+        "antigravity_quota_last_updated_timestamp_seconds",
+        "gauge",
+        "Unix time of the last stored quota snapshot.",
+    );
+    for account in accounts {
+        if let Some(quota) = account.quota.as_ref() {
+            w.sample(
+                "antigravity_quota_last_updated_timestamp_seconds",
+                &[("account_id", &account.id)],
+                quota.last_updated as f64,
+            );
+        }
+    }
+
+    w.family(
+        "antigravity_account_last_refresh_success",
+        "gauge",
+        "Whether the last quota refresh of the account succeeded (1) or failed (0).",
+    );
+    let refreshed: Vec<(&Account, RefreshOutcome)> = accounts
+        .iter()
+        .filter_map(|a| state.last_refresh.get(&a.id).map(|o| (a, *o)))
+        .collect();
+    for (account, outcome) in &refreshed {
+        w.sample(
+            "antigravity_account_last_refresh_success",
+            &[("account_id", &account.id)],
+            if outcome.success { 1.0 } else { 0.0 },
+        );
+    }
+    w.family(
+        "antigravity_account_last_refresh_timestamp_seconds",
+        "gauge",
+        "Unix time of the last quota refresh attempt of the account.",
+    );
+    for (account, outcome) in &refreshed {
+        w.sample(
+            "antigravity_account_last_refresh_timestamp_seconds",
+            &[("account_id", &account.id)],
+            outcome.ts as f64,
+        );
+    }
+
+    if let Some(batch) = state.last_batch {
+        w.family(
+            "antigravity_refresh_last_batch_accounts",
+            "gauge",
+            "Accounts in the last batch refresh by result.",
+        );
+        for (result, value) in [
+            ("total", batch.total),
+            ("success", batch.success),
+            ("failed", batch.failed),
+        ] {
+            w.sample(
+                "antigravity_refresh_last_batch_accounts",
+                &[("result", result)],
+                value as f64,
+            );
+        }
+        w.family(
+            "antigravity_refresh_last_batch_timestamp_seconds",
+            "gauge",
+            "Unix time the last batch refresh finished.",
+        );
+        w.sample(
+            "antigravity_refresh_last_batch_timestamp_seconds",
+            &[],
+            batch.ts as f64,
+        );
+    }
+
+    w.family(
+        "antigravity_account_switches",
+        "counter",
+        "Account switches by source and result.",
+    );
+    for ((source, result), value) in &state.switches {
+        w.sample(
+            "antigravity_account_switches_total",
+            &[("source", source), ("result", result)],
+            *value as f64,
+        );
+    }
+
+    w.family(
+        "antigravity_api_errors",
+        "counter",
+        "Upstream API error responses by host and status.",
+    );
+    for ((host, status), value) in &state.api_errors {
+        w.sample(
+            "antigravity_api_errors_total",
+            &[("host", host), ("status", status)],
+            *value as f64,
+        );
+    }
+
+    w.finish()
+}
+
+/// 当前指标（Prometheus 文本格式或 OpenMetrics）
+pub fn gather(openmetrics: bool) -> String {
+    let accounts = match crate::modules::account::list_accounts() {
+        Ok(accounts) => accounts,
+        Err(e) => {
+            logger::log_warn(&format!("[Metrics] Failed to list accounts: {}", e));
+            Vec::new()
+        }
+    };
+    let state = STATE.lock().clone();
+    render(
+        &accounts,
+        &state,
+        chrono::Utc::now().timestamp(),
+        openmetrics,
+    )
+}
+
+/// 监听地址：环境变量优先，其次是开启时的配置
+fn listen_addr(config: &MetricsConfig, env: Option<String>) -> Option<String> {
+    match env.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()) {
+        Some(addr) => Some(addr),
+        None => config.enabled.then(|| config.listen_addr.clone()),
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    gather: fn(bool) -> String,
+) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 || buf.len() + n > MAX_REQUEST_BYTES {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&buf);
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line
+        .next()
+        .unwrap_or("/")
+        .split('?')
+        .next()
+        .unwrap_or("/");
+    let openmetrics = lines.any(|line| {
+        line.split_once(':').is_some_and(|(k, v)| {
+            k.trim().eq_ignore_ascii_case("accept") && v.contains("application/openmetrics-text")
+        })
+    });
+
+    let (status, content_type, body) = match (method, path) {
+        // 读取账号文件是阻塞操作，放到阻塞线程池里
+        ("GET", "/metrics") => match tokio::task::spawn_blocking(move || gather(openmetrics)).await
+        {
+            Ok(body) => (
+                "200 OK",
+                if openmetrics {
+                    OPENMETRICS_CONTENT_TYPE
+                } else {
+                    PROMETHEUS_CONTENT_TYPE
+                },
+                body,
+            ),
+            Err(_) => (
+                "500 Internal Server Error",
+                "text/plain; charset=utf-8",
+                "Internal Server Error\n".to_string(),
+            ),
+        },
+        _ => (
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            "Not Found\n".to_string(),
+        ),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn serve(listener: TcpListener, gather: fn(bool) -> String) {
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(async move {
+            let _ = handle_connection(stream, gather).await;
+        });
+    }
+}
+
+/// 按配置启动指标端点；未开启时什么都不做
+pub fn start_server() {
+    let config = crate::modules::config::load_app_config()
+        .map(|config| config.metrics)
+        .unwrap_or_default();
+    let Some(addr) = listen_addr(&config, std::env::var(ENV_METRICS_ADDR).ok()) else {
+        return;
+    };
+    tauri::async_runtime::spawn(async move {
+        match TcpListener::bind(&addr).await {
+            Ok(listener) => {
+                logger::log_info(&format!("[Metrics] Serving on http://{}/metrics", addr));
+                serve(listener, gather).await;
+            }
+            Err(e) => logger::log_error(&format!("[Metrics] Failed to bind {}: {}", addr, e)),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::{account_with_quota, NOW};
+
+    fn account(id: &str, models: &[(&str, i32, &str)]) -> Account {
+        let mut account = account_with_quota(id, models);
+        account.token.expiry_timestamp = NOW + 600;
+        account.quota.as_mut().unwrap().last_updated = NOW - 60;
+        account
+    }
+
+    #[test]
+    fn test_render_account_gauges_and_counters() {
+        let a1 = account("a1", &[("claude-sonnet-4-6", 25, "2026-01-01T01:00:00Z")]);
+        let mut a2 = account("a2", &[]);
+        a2.disabled = true;
+        a2.validation_blocked = true;
+        a2.quota.as_mut().unwrap().is_forbidden = true;
+
+        let mut state = MetricsState::default();
+        state.last_refresh.insert(
+            "a2".into(),
+            RefreshOutcome {
+                ts: NOW,
+                success: false,
+            },
+        );
+        state.switches.insert(("auto_switch", "success"), 2);
+        state
+            .api_errors
+            .insert(("cloudcode-pa.googleapis.com".into(), "429".into()), 3);
+
+        let text = render(&[a1, a2], &state, NOW, false);
+        for line in [
+            "antigravity_accounts{state=\"total\"} 2",
+            "antigravity_accounts{state=\"disabled\"} 1",
+            "antigravity_accounts{state=\"forbidden\"} 1",
+            "antigravity_accounts{state=\"validation_blocked\"} 1",
+            "antigravity_model_remaining_ratio{account_id=\"a1\",model=\"claude-sonnet-4-6\"} 0.25",
+            "antigravity_model_reset_seconds{account_id=\"a1\",model=\"claude-sonnet-4-6\"} 3600",
+            "antigravity_token_expiry_seconds{account_id=\"a1\"} 600",
+            "antigravity_account_last_refresh_success{account_id=\"a2\"} 0",
+            "# TYPE antigravity_account_switches_total counter",
+            "antigravity_account_switches_total{source=\"auto_switch\",result=\"success\"} 2",
+            "antigravity_api_errors_total{host=\"cloudcode-pa.googleapis.com\",status=\"429\"} 3",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing: {}\n{}",
+                line,
+                text
+            );
+        }
+        // 未刷新过的账号没有最近刷新结果，没有批量刷新时不输出批量指标
+        assert!(!text.contains("account_last_refresh_success{account_id=\"a1\""));
+        assert!(!text.contains("antigravity_refresh_last_batch"));
+        assert!(!text.contains("# EOF"));
+        assert!(!text.contains("@example.com"));
+
+        // OpenMetrics：计数器族名不带 _total，以 # EOF 结尾
+        let text = render(&[], &state, NOW, true);
+        assert!(text.contains("# TYPE antigravity_account_switches counter\n"));
+        assert!(text.ends_with("# EOF\n"));
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    #[test]
+    fn test_listen_addr_and_http_endpoint() {
+        let config = MetricsConfig::default();
+        assert_eq!(listen_addr(&config, None), None);
+        assert_eq!(listen_addr(&config, Some(" ".into())), None);
+        assert_eq!(
+            listen_addr(&config, Some("0.0.0.0:9464".into())).as_deref(),
+            Some("0.0.0.0:9464")
+        );
+        let enabled = MetricsConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            listen_addr(&enabled, None).as_deref(),
+            Some("127.0.0.1:9464")
+        );
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(serve(listener, |openmetrics| {
+                render(&[], &MetricsState::default(), NOW, openmetrics)
+            }));
+
+            let fetch = |request: &'static str| async move {
+                let mut stream = TcpStream::connect(addr).await.unwrap();
+                stream.write_all(request.as_bytes()).await.unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                response
+            };
+            let response = fetch("GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n").await;
+            assert!(response.starts_with("HTTP/1.1 200 OK"));
+            assert!(response.contains(PROMETHEUS_CONTENT_TYPE));
+            assert!(response.contains("antigravity_accounts{state=\"total\"} 0"));
+
+            let response = fetch(
+                "GET /metrics HTTP/1.1\r\nAccept: application/openmetrics-text; version=1.0.0\r\n\r\n",
+            )
+            .await;
+            assert!(response.contains(OPENMETRICS_CONTENT_TYPE));
+            assert!(response.ends_with("# EOF\n"));
+
+            let response = fetch("GET / HTTP/1.1\r\n\r\n").await;
+            assert!(response.starts_with("HTTP/1.1 404"));
+        });
+    }
+}
@@ -0,0 +1,310 @@
+//! 本地 mock 上游服务（仅测试）
+//!
+//! 在 127.0.0.1 的随机端口上实现 loadCodeAssist、fetchAvailableModels、token 与 userinfo 接口。
+//! 每个接口有默认响应，也可以用 [`MockServer::enqueue`] 按顺序排入一次性响应（401、
+//! 403 VALIDATION_REQUIRED、429、invalid_grant 等）；收到的请求会被记录以便断言。
+//! 通过 [`MockServer::endpoint_env`] 给出的 `ABV_*_URL` 环境变量把应用指向它。
+
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::modules::endpoints::Endpoint;
+
+pub const MOCK_PROJECT_ID: &str = "mock-project";
+pub const MOCK_ACCESS_TOKEN: &str = "mock-access-token";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Route {
+    LoadCodeAssist,
+    FetchAvailableModels,
+    Token,
+    UserInfo,
+}
+
+impl Route {
+    fn from_path(path: &str) -> Option<Self> {
+        match path {
+            "/v1internal:loadCodeAssist" => Some(Route::LoadCodeAssist),
+            "/v1internal:fetchAvailableModels" => Some(Route::FetchAvailableModels),
+            "/token" => Some(Route::Token),
+            "/userinfo" => Some(Route::UserInfo),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn unauthorized() -> Self {
+        Self::json(
+            401,
+            json!({"error": {"code": 401, "message": "Request had invalid authentication credentials.", "status": "UNAUTHENTICATED"}}),
+        )
+    }
+
+    pub fn validation_required(validation_url: &str) -> Self {
+        Self::json(
+            403,
+            json!({"error": {
+                "code": 403,
+                "message": "Verify your account to continue.",
+                "status": "PERMISSION_DENIED",
+                "details": [{
+                    "@type": "type.googleapis.com/google.rpc.ErrorInfo",
+                    "reason": "VALIDATION_REQUIRED",
+                    "metadata": {"validation_url": validation_url}
+                }]
+            }}),
+        )
+    }
+
+    pub fn rate_limited(retry_after_secs: u64) -> Self {
+        Self::json(
+            429,
+            json!({"error": {"code": 429, "message": "Resource has been exhausted.", "status": "RESOURCE_EXHAUSTED"}}),
+        )
+        .with_header("Retry-After", &retry_after_secs.to_string())
+    }
+
+    pub fn invalid_grant() -> Self {
+        Self::json(
+            400,
+            json!({"error": "invalid_grant", "error_description": "Token has been expired or revoked."}),
+        )
+    }
+
+    /// fetchAvailableModels 响应：(模型, 剩余比例, 重置时间)
+    pub fn models(models: &[(&str, f64, &str)]) -> Self {
+        let models: serde_json::Map<String, Value> = models
+            .iter()
+            .map(|(name, fraction, reset)| {
+                (
+                    name.to_string(),
+                    json!({"quotaInfo": {"remainingFraction": fraction, "resetTime": reset}}),
+                )
+            })
+            .collect();
+        Self::json(200, json!({ "models": models }))
+    }
+
+    fn default_for(route: Route) -> Self {
+        match route {
+            Route::LoadCodeAssist => Self::json(
+                200,
+                json!({
+                    "cloudaicompanionProject": MOCK_PROJECT_ID,
+                    "currentTier": {"id": "free-tier"},
+                    "paidTier": {"id": "g1-pro-tier"}
+                }),
+            ),
+            Route::FetchAvailableModels => Self::models(&[
+                ("claude-sonnet-4-6", 0.8, "2030-01-01T00:00:00Z"),
+                ("gemini-3-flash", 1.0, "2030-01-01T00:00:00Z"),
+            ]),
+            Route::Token => Self::json(
+                200,
+                json!({"access_token": MOCK_ACCESS_TOKEN, "expires_in": 3599, "token_type": "Bearer"}),
+            ),
+            Route::UserInfo => Self::json(
+                200,
+                json!({"email": "mock@example.com", "name": "Mock User"}),
+            ),
+        }
+    }
+}
+
+/// 收到的请求
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub route: Route,
+    pub authorization: Option<String>,
+    pub body: String,
+}
+
+#[derive(Default)]
+struct MockState {
+    queued: HashMap<Route, VecDeque<MockResponse>>,
+    defaults: HashMap<Route, MockResponse>,
+    requests: Vec<RecordedRequest>,
+}
+
+pub struct MockServer {
+    base_url: String,
+    state: Arc<Mutex<MockState>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MockServer {
+    /// 需要在 tokio 运行时中调用
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock server");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let shared = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = shared.clone();
+                tokio::spawn(async move {
+                    let _ = handle_connection(stream, state).await;
+                });
+            }
+        });
+        Self {
+            base_url,
+            state,
+            task,
+        }
+    }
+
+    /// 把各接口指向本服务的环境变量
+    pub fn endpoint_env(&self) -> Vec<(&'static str, String)> {
+        [
+            (Endpoint::QuotaApi, "/v1internal:fetchAvailableModels"),
+            (Endpoint::CloudCodeBase, ""),
+            (Endpoint::Token, "/token"),
+            (Endpoint::UserInfo, "/userinfo"),
+            (Endpoint::Auth, "/auth"),
+        ]
+        .into_iter()
+        .map(|(endpoint, path)| (endpoint.env_var(), format!("{}{}", self.base_url, path)))
+        .collect()
+    }
+
+    /// 排入一次性响应，按顺序消费，用完后回到默认响应
+    pub fn enqueue(&self, route: Route, response: MockResponse) {
+        self.state
+            .lock()
+            .queued
+            .entry(route)
+            .or_default()
+            .push_back(response);
+    }
+
+    pub fn set_default(&self, route: Route, response: MockResponse) {
+        self.state.lock().defaults.insert(route, response);
+    }
+
+    pub fn requests(&self, route: Route) -> Vec<RecordedRequest> {
+        self.state
+            .lock()
+            .requests
+            .iter()
+            .filter(|r| r.route == route)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn find_header_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n")
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    state: Arc<Mutex<MockState>>,
+) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(end) = find_header_end(&buf) {
+            break end;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.lines();
+    let path = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or("/")
+        .split('?')
+        .next()
+        .unwrap_or("/")
+        .to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = buf[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    let response = match Route::from_path(&path) {
+        Some(route) => {
+            let mut state = state.lock();
+            state.requests.push(RecordedRequest {
+                route,
+                authorization: headers.get("authorization").cloned(),
+                body: String::from_utf8_lossy(&body).to_string(),
+            });
+            match state.queued.get_mut(&route).and_then(|q| q.pop_front()) {
+                Some(response) => response,
+                None => state
+                    .defaults
+                    .get(&route)
+                    .cloned()
+                    .unwrap_or_else(|| MockResponse::default_for(route)),
+            }
+        }
+        None => MockResponse::json(404, json!({"error": "not_found"})),
+    };
+
+    let mut out = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str("\r\n");
+    out.push_str(&response.body);
+    stream.write_all(out.as_bytes()).await?;
+    stream.shutdown().await
+}
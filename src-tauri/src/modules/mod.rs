@@ -1,6 +1,29 @@
 pub mod account;
+pub mod account_store;
+pub mod account_bundle;
+pub mod bulk_import;
 pub mod quota;
+pub mod quota_history;
+pub mod quota_protection;
+pub mod auto_switch;
+pub mod selection;
+pub mod tier;
+pub mod forecast;
+pub mod quota_pool;
+pub mod endpoints;
+pub mod notifications;
+pub mod webhooks;
+pub mod metrics;
+#[cfg(test)]
+pub mod mock_server;
+#[cfg(test)]
+pub mod test_support;
 pub mod config;
+pub mod data_lock;
+pub mod schema;
+pub mod paths;
+pub mod trash;
+pub mod audit;
 pub mod logger;
 pub mod db;
 pub mod process;
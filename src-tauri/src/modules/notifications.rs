@@ -0,0 +1,275 @@
+//! 通知
+//!
+//! 账号相关的重要事件经由三个渠道送达：系统原生通知（[`SystemManager::show_notification`]）、
+//! 前端事件 `notifications://new` 与应用日志。每类事件可在 `AppConfig.notifications` 中单独关闭，
+//! 免打扰时段内只跳过系统通知，前端与日志照常记录。
+
+use serde::Serialize;
+
+use crate::models::config::{NotificationConfig, NotificationKind};
+use crate::modules::audit::EventSource;
+use crate::modules::integration::SystemManager;
+use crate::modules::logger;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// 模型新进入配额保护
+    ProtectionTriggered {
+        account_id: String,
+        email: String,
+        models: Vec<String>,
+    },
+    /// refresh_token 失效（invalid_grant）导致账号被禁用
+    AccountDisabled {
+        account_id: String,
+        email: String,
+        reason: String,
+    },
+    /// 403 VALIDATION_REQUIRED，需要用户打开验证地址
+    ValidationRequired {
+        account_id: String,
+        email: String,
+        validation_url: Option<String>,
+    },
+    SwitchCompleted {
+        account_id: String,
+        email: String,
+        source: EventSource,
+    },
+    SwitchFailed {
+        account_id: String,
+        email: Option<String>,
+        error: String,
+        source: EventSource,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+/// 发送给前端的通知
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub ts: i64,
+    pub level: Level,
+    pub title: String,
+    pub body: String,
+    #[serde(flatten)]
+    pub event: NotificationEvent,
+}
+
+impl NotificationEvent {
+    pub fn kind(&self) -> NotificationKind {
+        match self {
+            NotificationEvent::ProtectionTriggered { .. } => NotificationKind::ProtectionTriggered,
+            NotificationEvent::AccountDisabled { .. } => NotificationKind::AccountDisabled,
+            NotificationEvent::ValidationRequired { .. } => NotificationKind::ValidationRequired,
+            NotificationEvent::SwitchCompleted { .. } => NotificationKind::SwitchCompleted,
+            NotificationEvent::SwitchFailed { .. } => NotificationKind::SwitchFailed,
+        }
+    }
+
+    fn level(&self) -> Level {
+        match self {
+            NotificationEvent::ProtectionTriggered { .. }
+            | NotificationEvent::ValidationRequired { .. } => Level::Warning,
+            NotificationEvent::AccountDisabled { .. } | NotificationEvent::SwitchFailed { .. } => {
+                Level::Error
+            }
+            NotificationEvent::SwitchCompleted { .. } => Level::Info,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            NotificationEvent::ProtectionTriggered { .. } => "Quota protection triggered",
+            NotificationEvent::AccountDisabled { .. } => "Account disabled",
+            NotificationEvent::ValidationRequired { .. } => "Account verification required",
+            NotificationEvent::SwitchCompleted { .. } => "Account switched",
+            NotificationEvent::SwitchFailed { .. } => "Account switch failed",
+        }
+    }
+
+    fn body(&self) -> String {
+        match self {
+            NotificationEvent::ProtectionTriggered { email, models, .. } => {
+                format!(
+                    "{}: {} reached the protection threshold",
+                    email,
+                    models.join(", ")
+                )
+            }
+            NotificationEvent::AccountDisabled { email, reason, .. } => {
+                format!("{} was disabled ({})", email, reason)
+            }
+            NotificationEvent::ValidationRequired {
+                email,
+                validation_url,
+                ..
+            } => match validation_url {
+                Some(url) => format!("{} needs verification: {}", email, url),
+                None => format!("{} needs verification in the browser", email),
+            },
+            NotificationEvent::SwitchCompleted { email, source, .. } => match source {
+                EventSource::AutoSwitch => {
+                    format!("Quota protection triggered, switched to {}", email)
+                }
+                _ => format!("Switched to {}", email),
+            },
+            NotificationEvent::SwitchFailed {
+                account_id,
+                email,
+                error,
+                ..
+            } => format!(
+                "Could not switch to {}: {}",
+                email.as_deref().unwrap_or(account_id),
+                error
+            ),
+        }
+    }
+}
+
+/// 各渠道是否投递：(desktop, in_app, log)
+fn channels(
+    config: &NotificationConfig,
+    kind: NotificationKind,
+    now: chrono::NaiveTime,
+) -> (bool, bool, bool) {
+    if !config.event_enabled(kind) {
+        return (false, false, false);
+    }
+    let quiet = config.quiet_hours.as_ref().is_some_and(|q| q.contains(now));
+    (config.desktop && !quiet, config.in_app, config.log)
+}
+
+/// 按配置投递通知
+pub fn notify(event: NotificationEvent) {
+    let config = crate::modules::config::load_app_config()
+        .map(|config| config.notifications)
+        .unwrap_or_default();
+    let (desktop, in_app, log) = channels(&config, event.kind(), chrono::Local::now().time());
+    if !(desktop || in_app || log) {
+        return;
+    }
+
+    let notification = Notification {
+        ts: chrono::Utc::now().timestamp(),
+        level: event.level(),
+        title: event.title().to_string(),
+        body: event.body(),
+        event,
+    };
+    let handle = crate::modules::log_bridge::app_handle();
+
+    if log {
+        let line = format!(
+            "[Notification] {}: {}",
+            notification.title, notification.body
+        );
+        match notification.level {
+            Level::Info => logger::log_info(&line),
+            Level::Warning => logger::log_warn(&line),
+            Level::Error => logger::log_error(&line),
+        }
+    }
+    if in_app {
+        if let Some(handle) = handle {
+            use tauri::Emitter;
+            let _ = handle.emit("notifications://new", &notification);
+        }
+    }
+    if desktop {
+        let integration = match handle {
+            Some(handle) => SystemManager::Desktop(handle.clone()),
+            None => SystemManager::Headless,
+        };
+        integration.show_notification(&notification.title, &notification.body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::QuietHours;
+    use chrono::NaiveTime;
+
+    fn at(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_quiet_hours_and_event_toggles() {
+        let overnight = QuietHours {
+            start: "22:30".into(),
+            end: "07:00".into(),
+        };
+        assert!(overnight.contains(at(23, 0)));
+        assert!(overnight.contains(at(6, 59)));
+        assert!(!overnight.contains(at(7, 0)));
+        assert!(!overnight.contains(at(12, 0)));
+        let lunch = QuietHours {
+            start: "12:00".into(),
+            end: "13:00".into(),
+        };
+        assert!(lunch.contains(at(12, 30)) && !lunch.contains(at(13, 30)));
+        let invalid = QuietHours {
+            start: "late".into(),
+            end: "07:00".into(),
+        };
+        assert!(!invalid.contains(at(23, 0)));
+
+        let config = NotificationConfig {
+            disabled_events: vec![NotificationKind::SwitchCompleted],
+            quiet_hours: Some(overnight),
+            ..Default::default()
+        };
+        // 免打扰只影响系统通知
+        assert_eq!(
+            channels(&config, NotificationKind::AccountDisabled, at(23, 0)),
+            (false, true, true)
+        );
+        assert_eq!(
+            channels(&config, NotificationKind::AccountDisabled, at(9, 0)),
+            (true, true, true)
+        );
+        assert_eq!(
+            channels(&config, NotificationKind::SwitchCompleted, at(9, 0)),
+            (false, false, false)
+        );
+    }
+
+    #[test]
+    fn test_payload_shape() {
+        let event = NotificationEvent::ValidationRequired {
+            account_id: "a1".into(),
+            email: "a1@example.com".into(),
+            validation_url: Some("https://accounts.google.com/verify".into()),
+        };
+        assert_eq!(event.kind(), NotificationKind::ValidationRequired);
+        let notification = Notification {
+            ts: 1,
+            level: event.level(),
+            title: event.title().to_string(),
+            body: event.body(),
+            event,
+        };
+        let value = serde_json::to_value(&notification).unwrap();
+        assert_eq!(value["kind"], "validation_required");
+        assert_eq!(value["level"], "warning");
+        assert_eq!(
+            value["validation_url"],
+            "https://accounts.google.com/verify"
+        );
+        assert!(value["body"]
+            .as_str()
+            .unwrap()
+            .ends_with("https://accounts.google.com/verify"));
+    }
+}
@@ -1,12 +1,12 @@
 use serde::{Deserialize, Serialize};
 
+use crate::modules::endpoints::{self, Endpoint};
+use crate::modules::metrics;
+use crate::utils::retry::host_of;
+
 // Google OAuth configuration
 const CLIENT_ID: &str = "1071006060591-tmhssin2h21lcre235vtolojh4g403ep.apps.googleusercontent.com";
 const CLIENT_SECRET: &str = "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";
-const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
-const USERINFO_URL: &str = "https://www.googleapis.com/oauth2/v2/userinfo";
-
-const AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenResponse {
@@ -69,7 +69,12 @@ pub fn get_auth_url(redirect_uri: &str, state: &str) -> String {
         ("state", state),
     ];
     
-    let url = url::Url::parse_with_params(AUTH_URL, &params).expect("Invalid Auth URL");
+    let url = url::Url::parse_with_params(&endpoints::url(Endpoint::Auth), &params)
+        .or_else(|e| {
+            crate::modules::logger::log_warn(&format!("Invalid auth URL override, using default: {}", e));
+            url::Url::parse_with_params(Endpoint::Auth.default_url(), &params)
+        })
+        .expect("Invalid Auth URL");
     url.to_string()
 }
 
@@ -86,7 +91,7 @@ pub async fn exchange_code(code: &str, redirect_uri: &str) -> Result<TokenRespon
     ];
 
     let response = client
-        .post(TOKEN_URL)
+        .post(endpoints::url(Endpoint::Token))
         .form(&params)
         .send()
         .await
@@ -145,12 +150,14 @@ pub async fn refresh_access_token(refresh_token: &str, account_id: Option<&str>)
         crate::modules::logger::log_info("Refreshing Token for generic request (no account_id)...");
     }
     
+    let url = endpoints::url(Endpoint::Token);
     let response = client
-        .post(TOKEN_URL)
+        .post(&url)
         .form(&params)
         .send()
         .await
         .map_err(|e| {
+            metrics::record_api_error(&host_of(&url), None);
             if e.is_connect() || e.is_timeout() {
                 format!("Refresh request failed: {}. 无法连接 Google 授权服务器，请检查代理设置。", e)
             } else {
@@ -167,6 +174,7 @@ pub async fn refresh_access_token(refresh_token: &str, account_id: Option<&str>)
         crate::modules::logger::log_info(&format!("Token refreshed successfully! Expires in: {} seconds", token_data.expires_in));
         Ok(token_data)
     } else {
+        metrics::record_api_error(&host_of(&url), Some(response.status().as_u16()));
         let error_text = response.text().await.unwrap_or_default();
         Err(format!("Refresh failed: {}", error_text))
     }
@@ -176,18 +184,23 @@ pub async fn refresh_access_token(refresh_token: &str, account_id: Option<&str>)
 pub async fn get_user_info(access_token: &str, _account_id: Option<&str>) -> Result<UserInfo, String> {
     let client = crate::utils::http::get_client();
     
+    let url = endpoints::url(Endpoint::UserInfo);
     let response = client
-        .get(USERINFO_URL)
+        .get(&url)
         .bearer_auth(access_token)
         .send()
         .await
-        .map_err(|e| format!("User info request failed: {}", e))?;
+        .map_err(|e| {
+            metrics::record_api_error(&host_of(&url), None);
+            format!("User info request failed: {}", e)
+        })?;
 
     if response.status().is_success() {
         response.json::<UserInfo>()
             .await
             .map_err(|e| format!("User info parsing failed: {}", e))
     } else {
+        metrics::record_api_error(&host_of(&url), Some(response.status().as_u16()));
         let error_text = response.text().await.unwrap_or_default();
         Err(format!("Failed to get user info: {}", error_text))
     }
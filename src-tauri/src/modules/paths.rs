@@ -0,0 +1,342 @@
+//! 数据目录及各类文件路径的统一解析
+//!
+//! 优先级：命令行参数 > 环境变量 > 已存在的旧版 `~/.antigravity_tools` > Linux XDG 目录 > 默认目录。
+//! 其余位置（账号、日志、配置、设备基线、更新设置、备份）均由此派生，Docker 中只需设置 ABV_DATA_DIR。
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// XDG 目录下使用的应用目录名
+pub const APP_DIR_NAME: &str = "antigravity_tools";
+const LEGACY_DATA_DIR: &str = ".antigravity_tools";
+
+const ACCOUNTS_DIR: &str = "accounts";
+const LOGS_DIR: &str = "logs";
+const BACKUPS_DIR: &str = "backups";
+pub const CONFIG_FILE: &str = "gui_config.json";
+pub const UPDATE_SETTINGS_FILE: &str = "update_settings.json";
+pub const DEVICE_BASELINE_FILE: &str = "device_original.json";
+
+/// 路径来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathSource {
+    Cli,
+    Env,
+    /// 旧版 ~/.antigravity_tools 已存在，继续沿用
+    Legacy,
+    Xdg,
+    Default,
+    /// 位于数据目录下
+    DataDir,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedPath {
+    pub path: PathBuf,
+    pub source: PathSource,
+    /// 生效的命令行参数或环境变量名
+    pub detail: Option<String>,
+}
+
+impl ResolvedPath {
+    fn new(path: PathBuf, source: PathSource) -> Self {
+        Self {
+            path,
+            source,
+            detail: None,
+        }
+    }
+}
+
+/// 可覆盖的位置：命令行参数与环境变量
+struct Override {
+    cli_flag: &'static str,
+    env: &'static str,
+}
+
+const DATA_DIR_OVERRIDE: Override = Override {
+    cli_flag: "--data-dir",
+    env: "ABV_DATA_DIR",
+};
+const LOG_DIR_OVERRIDE: Override = Override {
+    cli_flag: "--log-dir",
+    env: "ABV_LOG_DIR",
+};
+const CONFIG_DIR_OVERRIDE: Override = Override {
+    cli_flag: "--config-dir",
+    env: "ABV_CONFIG_DIR",
+};
+const BACKUP_DIR_OVERRIDE: Override = Override {
+    cli_flag: "--backup-dir",
+    env: "ABV_BACKUP_DIR",
+};
+
+static CLI_ARGS: Lazy<Vec<String>> = Lazy::new(|| std::env::args().skip(1).collect());
+
+/// Value of `--flag value` or `--flag=value`
+fn cli_value(args: &[String], flag: &str) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == flag {
+            return iter.next().cloned();
+        }
+        if let Some(value) = arg
+            .strip_prefix(flag)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+fn lookup_with(
+    o: &Override,
+    args: &[String],
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Option<ResolvedPath> {
+    let non_empty = |v: String| {
+        let v = v.trim().to_string();
+        (!v.is_empty()).then_some(v)
+    };
+
+    if let Some(value) = cli_value(args, o.cli_flag).and_then(non_empty) {
+        return Some(ResolvedPath {
+            path: PathBuf::from(value),
+            source: PathSource::Cli,
+            detail: Some(o.cli_flag.to_string()),
+        });
+    }
+    env(o.env).and_then(non_empty).map(|value| ResolvedPath {
+        path: PathBuf::from(value),
+        source: PathSource::Env,
+        detail: Some(o.env.to_string()),
+    })
+}
+
+fn lookup(o: &Override) -> Option<ResolvedPath> {
+    lookup_with(o, &CLI_ARGS, &|name| std::env::var(name).ok())
+}
+
+fn ensure_dir(path: &Path, context: &str) -> Result<(), String> {
+    if !path.exists() {
+        fs::create_dir_all(path).map_err(|e| format!("{}: {}", context, e))?;
+    }
+    Ok(())
+}
+
+/// Resolve the data directory without creating it
+pub fn resolve_data_dir() -> Result<ResolvedPath, String> {
+    if let Some(resolved) = lookup(&DATA_DIR_OVERRIDE) {
+        return Ok(resolved);
+    }
+
+    let home = dirs::home_dir().ok_or("failed_to_get_home_dir")?;
+    let legacy = home.join(LEGACY_DATA_DIR);
+
+    #[cfg(target_os = "linux")]
+    {
+        if legacy.exists() {
+            return Ok(ResolvedPath::new(legacy, PathSource::Legacy));
+        }
+        if let Some(xdg_data) = dirs::data_dir() {
+            return Ok(ResolvedPath::new(
+                xdg_data.join(APP_DIR_NAME),
+                PathSource::Xdg,
+            ));
+        }
+    }
+
+    Ok(ResolvedPath::new(legacy, PathSource::Default))
+}
+
+/// Data directory (created if missing)
+pub fn data_dir() -> Result<PathBuf, String> {
+    let resolved = resolve_data_dir()?;
+    let context = match resolved.source {
+        PathSource::Cli | PathSource::Env => "failed_to_create_custom_data_dir",
+        _ => "failed_to_create_data_dir",
+    };
+    ensure_dir(&resolved.path, context)?;
+    Ok(resolved.path)
+}
+
+/// Accounts directory (created if missing)
+pub fn accounts_dir() -> Result<PathBuf, String> {
+    let dir = data_dir()?.join(ACCOUNTS_DIR);
+    ensure_dir(&dir, "failed_to_create_accounts_dir")?;
+    Ok(dir)
+}
+
+/// XDG 布局下日志与配置放在各自的 XDG 目录，否则放在数据目录中
+fn resolve_xdg_or_data_dir(
+    o: &Override,
+    data: &ResolvedPath,
+    xdg_base: Option<PathBuf>,
+    sub_dir: Option<&str>,
+) -> ResolvedPath {
+    if let Some(resolved) = lookup(o) {
+        return resolved;
+    }
+    let (base, source) = match xdg_base {
+        Some(base) if data.source == PathSource::Xdg => (base.join(APP_DIR_NAME), PathSource::Xdg),
+        _ => (data.path.clone(), PathSource::DataDir),
+    };
+    let path = match sub_dir {
+        Some(sub_dir) => base.join(sub_dir),
+        None => base,
+    };
+    ResolvedPath::new(path, source)
+}
+
+fn xdg_state_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    {
+        dirs::state_dir()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+fn resolve_log_dir(data: &ResolvedPath) -> ResolvedPath {
+    resolve_xdg_or_data_dir(&LOG_DIR_OVERRIDE, data, xdg_state_dir(), Some(LOGS_DIR))
+}
+
+fn resolve_config_dir(data: &ResolvedPath) -> ResolvedPath {
+    resolve_xdg_or_data_dir(&CONFIG_DIR_OVERRIDE, data, dirs::config_dir(), None)
+}
+
+/// Log directory (created if missing)
+pub fn log_dir() -> Result<PathBuf, String> {
+    let dir = resolve_log_dir(&resolve_data_dir()?).path;
+    ensure_dir(&dir, "Failed to create log directory")?;
+    Ok(dir)
+}
+
+/// Directory holding gui_config.json and update_settings.json (created if missing)
+pub fn config_dir() -> Result<PathBuf, String> {
+    let dir = resolve_config_dir(&resolve_data_dir()?).path;
+    ensure_dir(&dir, "failed_to_create_config_dir")?;
+    Ok(dir)
+}
+
+pub fn config_file() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join(CONFIG_FILE))
+}
+
+pub fn update_settings_file() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join(UPDATE_SETTINGS_FILE))
+}
+
+/// 全局原始设备指纹（所有账号共享的基线）
+pub fn device_baseline_file() -> Result<PathBuf, String> {
+    Ok(data_dir()?.join(DEVICE_BASELINE_FILE))
+}
+
+fn resolve_backups_dir_in(data_dir: &Path) -> ResolvedPath {
+    lookup(&BACKUP_DIR_OVERRIDE)
+        .unwrap_or_else(|| ResolvedPath::new(data_dir.join(BACKUPS_DIR), PathSource::DataDir))
+}
+
+/// Backups directory for a specific data directory (not created)
+pub fn backups_dir_in(data_dir: &Path) -> PathBuf {
+    resolve_backups_dir_in(data_dir).path
+}
+
+/// 单个路径的解析结果，供设置页展示
+#[derive(Debug, Clone, Serialize)]
+pub struct PathEntry {
+    pub name: &'static str,
+    pub path: String,
+    pub source: PathSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub exists: bool,
+}
+
+impl PathEntry {
+    fn new(name: &'static str, resolved: ResolvedPath) -> Self {
+        Self {
+            name,
+            exists: resolved.path.exists(),
+            path: resolved.path.to_string_lossy().to_string(),
+            source: resolved.source,
+            detail: resolved.detail,
+        }
+    }
+}
+
+/// Report every resolved location and where it came from
+pub fn describe() -> Result<Vec<PathEntry>, String> {
+    let data = resolve_data_dir()?;
+    let config = resolve_config_dir(&data);
+    let derived = |path: PathBuf, from: &ResolvedPath| ResolvedPath {
+        path,
+        source: from.source,
+        detail: from.detail.clone(),
+    };
+
+    let in_data_dir = |name: &str| ResolvedPath::new(data.path.join(name), PathSource::DataDir);
+
+    Ok(vec![
+        PathEntry::new("data_dir", data.clone()),
+        PathEntry::new("accounts", in_data_dir(ACCOUNTS_DIR)),
+        PathEntry::new("logs", resolve_log_dir(&data)),
+        PathEntry::new("config", derived(config.path.join(CONFIG_FILE), &config)),
+        PathEntry::new(
+            "update_settings",
+            derived(config.path.join(UPDATE_SETTINGS_FILE), &config),
+        ),
+        PathEntry::new("device_baseline", in_data_dir(DEVICE_BASELINE_FILE)),
+        PathEntry::new("backups", resolve_backups_dir_in(&data.path)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_cli_value_forms() {
+        let argv = args(&[
+            "--minimized",
+            "--data-dir",
+            "/srv/ag",
+            "--log-dir=/var/log/ag",
+        ]);
+        assert_eq!(cli_value(&argv, "--data-dir").as_deref(), Some("/srv/ag"));
+        assert_eq!(
+            cli_value(&argv, "--log-dir").as_deref(),
+            Some("/var/log/ag")
+        );
+        assert_eq!(cli_value(&argv, "--config-dir"), None);
+        // 前缀相同的其他参数不应被误认
+        assert_eq!(cli_value(&args(&["--data-dirty=1"]), "--data-dir"), None);
+        assert_eq!(cli_value(&args(&["--data-dir"]), "--data-dir"), None);
+    }
+
+    #[test]
+    fn test_cli_overrides_env_and_empty_values_are_ignored() {
+        let env = |name: &str| (name == "ABV_DATA_DIR").then(|| "/data".to_string());
+
+        let resolved = lookup_with(&DATA_DIR_OVERRIDE, &args(&["--data-dir=/cli"]), &env).unwrap();
+        assert_eq!(resolved.path, PathBuf::from("/cli"));
+        assert_eq!(resolved.source, PathSource::Cli);
+
+        let resolved = lookup_with(&DATA_DIR_OVERRIDE, &args(&["--data-dir", "  "]), &env).unwrap();
+        assert_eq!(resolved.path, PathBuf::from("/data"));
+        assert_eq!(resolved.detail.as_deref(), Some("ABV_DATA_DIR"));
+
+        let blank_env = |_: &str| Some(" ".to_string());
+        assert!(lookup_with(&DATA_DIR_OVERRIDE, &[], &blank_env).is_none());
+    }
+}
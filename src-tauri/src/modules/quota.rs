@@ -1,21 +1,20 @@
 use rquest;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use crate::models::config::ModelFilterConfig;
 use crate::models::QuotaData;
+use crate::modules::endpoints::{self, Endpoint};
+use crate::modules::tier::TierInfo;
+use crate::utils::retry::{self, RetryPolicy};
 
-const QUOTA_API_URL: &str = "https://cloudcode-pa.googleapis.com/v1internal:fetchAvailableModels";
-
-const MAX_RETRIES: u32 = 3;
+/// 403 中表示需要用户验证账号的原因
+pub const VALIDATION_REQUIRED: &str = "VALIDATION_REQUIRED";
 
 #[derive(Debug, Serialize, Deserialize)]
 struct QuotaResponse {
-    models: std::collections::HashMap<String, ModelInfo>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ModelInfo {
-    #[serde(rename = "quotaInfo")]
-    quota_info: Option<QuotaInfo>,
+    /// 保留每个模型的完整字段
+    #[serde(default)]
+    models: std::collections::HashMap<String, serde_json::Map<String, serde_json::Value>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -53,76 +52,88 @@ async fn create_client(_account_id: Option<&str>) -> rquest::Client {
     crate::utils::http::get_client()
 }
 
-const CLOUD_CODE_BASE_URL: &str = "https://daily-cloudcode-pa.sandbox.googleapis.com";
 
-/// 获取项目 ID 和订阅等级
-pub async fn fetch_project_id(access_token: &str, email: &str, account_id: Option<&str>) -> (Option<String>, Option<String>) {
+/// 调用 loadCodeAssist 查询项目 ID 与订阅等级（不经缓存，见 `modules::tier`）
+pub async fn load_code_assist(access_token: &str, email: &str, account_id: Option<&str>) -> Result<TierInfo, String> {
     let client = create_client(account_id).await;
     let meta = json!({"metadata": {"ideType": "ANTIGRAVITY"}});
 
-    let res = client
-        .post(format!("{}/v1internal:loadCodeAssist", CLOUD_CODE_BASE_URL))
-        .header(rquest::header::AUTHORIZATION, format!("Bearer {}", access_token))
-        .header(rquest::header::CONTENT_TYPE, "application/json")
-        .header(rquest::header::USER_AGENT, crate::constants::USER_AGENT.as_str())
-        .json(&meta)
-        .send()
-        .await;
-
-    match res {
-        Ok(res) => {
-            if res.status().is_success() {
-                if let Ok(data) = res.json::<LoadProjectResponse>().await {
-                    let project_id = data.project_id.clone();
-                    
-                    // Core logic: Priority to subscription ID from paid_tier, which better reflects actual account benefits than current_tier
-                    let subscription_tier = data.paid_tier
-                        .and_then(|t| t.id)
-                        .or_else(|| data.current_tier.and_then(|t| t.id));
-                    
-                    if let Some(ref tier) = subscription_tier {
-                        crate::modules::logger::log_info(&format!(
-                            "📊 [{}] Subscription identified successfully: {}", email, tier
-                        ));
-                    }
-                    
-                    return (project_id, subscription_tier);
-                }
-            } else {
-                crate::modules::logger::log_warn(&format!(
-                    "⚠️  [{}] loadCodeAssist failed: Status: {}", email, res.status()
-                ));
-            }
-        }
-        Err(e) => {
-            crate::modules::logger::log_error(&format!("❌ [{}] loadCodeAssist network error: {}", email, e));
+    let url = format!("{}/v1internal:loadCodeAssist", endpoints::url(Endpoint::CloudCodeBase));
+
+    let res = retry::send_with_retry(&retry::host_of(&url), &RetryPolicy::load(), || {
+        client
+            .post(&url)
+            .header(rquest::header::AUTHORIZATION, format!("Bearer {}", access_token))
+            .header(rquest::header::CONTENT_TYPE, "application/json")
+            .header(rquest::header::USER_AGENT, crate::constants::USER_AGENT.as_str())
+            .json(&meta)
+            .send()
+    })
+    .await
+    .map_err(|e| {
+        crate::modules::logger::log_error(&format!("❌ [{}] loadCodeAssist network error: {}", email, e));
+        format!("load_code_assist_request_failed: {}", e)
+    })?;
+
+    if !res.status().is_success() {
+        crate::modules::logger::log_warn(&format!(
+            "⚠️  [{}] loadCodeAssist failed: Status: {}", email, res.status()
+        ));
+        return Err(format!("load_code_assist_failed: HTTP {}", res.status()));
+    }
+    let data = res
+        .json::<LoadProjectResponse>()
+        .await
+        .map_err(|e| format!("load_code_assist_parse_failed: {}", e))?;
+
+    let info = TierInfo {
+        project_id: data.project_id,
+        current_tier: data.current_tier.and_then(|t| t.id),
+        paid_tier: data.paid_tier.and_then(|t| t.id),
+    };
+    if let Some(tier) = info.subscription_tier() {
+        crate::modules::logger::log_info(&format!(
+            "📊 [{}] Subscription identified successfully: {}", email, tier
+        ));
+    }
+    Ok(info)
+}
+
+/// 获取项目 ID 和订阅等级
+pub async fn fetch_project_id(access_token: &str, email: &str, account_id: Option<&str>) -> (Option<String>, Option<String>) {
+    match load_code_assist(access_token, email, account_id).await {
+        Ok(info) => {
+            let tier = info.subscription_tier();
+            (info.project_id, tier)
         }
+        Err(_) => (None, None),
     }
-    
-    (None, None)
 }
 
 /// Unified entry point for fetching account quota
+///
+/// 项目 ID 与订阅等级来自 `modules::tier` 的按账号 TTL 缓存，不再每次调用 loadCodeAssist。
 pub async fn fetch_quota(access_token: &str, email: &str, account_id: Option<&str>) -> crate::error::AppResult<(QuotaData, Option<String>)> {
-    fetch_quota_with_cache(access_token, email, None, account_id).await
+    let tier = match account_id {
+        Some(id) => crate::modules::tier::resolve(id, access_token, email).await,
+        None => load_code_assist(access_token, email, None).await.unwrap_or_default(),
+    };
+    let (mut quota, project_id) =
+        fetch_quota_with_cache(access_token, email, tier.project_id.as_deref(), account_id).await?;
+    quota.subscription_tier = tier.subscription_tier();
+    Ok((quota, project_id))
 }
 
-/// Fetch quota with cache support
+/// Fetch quota for a known project id (no tier lookup)
 pub async fn fetch_quota_with_cache(
     access_token: &str,
     email: &str,
-    cached_project_id: Option<&str>,
+    project_id: Option<&str>,
     account_id: Option<&str>,
 ) -> crate::error::AppResult<(QuotaData, Option<String>)> {
     use crate::error::AppError;
-    
-    // Optimization: Skip loadCodeAssist call if project_id is cached to save API quota
-    let (project_id, subscription_tier) = if let Some(pid) = cached_project_id {
-        (Some(pid.to_string()), None)
-    } else {
-        fetch_project_id(access_token, email, account_id).await
-    };
-    
+
+    let project_id = project_id.map(str::to_string);
     let final_project_id = project_id.as_deref().unwrap_or("bamboo-precept-lgxtn");
     
     let client = create_client(account_id).await;
@@ -130,94 +141,116 @@ pub async fn fetch_quota_with_cache(
         "project": final_project_id
     });
     
-    let url = QUOTA_API_URL;
-    let mut last_error: Option<AppError> = None;
+    let url = endpoints::url(Endpoint::QuotaApi);
+    let config = crate::modules::config::load_app_config().unwrap_or_default();
+    let policy = RetryPolicy::from_config(&config.refresh_policy);
 
-    for attempt in 1..=MAX_RETRIES {
-        match client
-            .post(url)
+    let response = retry::send_with_retry(&retry::host_of(&url), &policy, || {
+        client
+            .post(&url)
             .bearer_auth(access_token)
             .header(rquest::header::USER_AGENT, crate::constants::USER_AGENT.as_str())
-            .json(&json!(payload))
+            .json(&payload)
             .send()
-            .await
-        {
-            Ok(response) => {
-                // Convert HTTP error status to AppError
-                if let Err(_) = response.error_for_status_ref() {
-                    let status = response.status();
-                    
-                    // ✅ Special handling for 403 Forbidden - return directly, no retry
-                    if status == rquest::StatusCode::FORBIDDEN {
-                        crate::modules::logger::log_warn(&format!(
-                            "Account unauthorized (403 Forbidden), marking as forbidden"
-                        ));
-                        let mut q = QuotaData::new();
-                        q.is_forbidden = true;
-                        q.subscription_tier = subscription_tier.clone();
-                        return Ok((q, project_id.clone()));
-                    }
-                    
-                    // Continue retry logic for other errors
-                    if attempt < MAX_RETRIES {
-                         let text = response.text().await.unwrap_or_default();
-                         crate::modules::logger::log_warn(&format!("API Error: {} - {} (Attempt {}/{})", status, text, attempt, MAX_RETRIES));
-                         last_error = Some(AppError::Unknown(format!("HTTP {} - {}", status, text)));
-                         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                         continue;
-                    } else {
-                         let text = response.text().await.unwrap_or_default();
-                         return Err(AppError::Unknown(format!("API Error: {} - {}", status, text)));
-                    }
-                }
-
-                let quota_response: QuotaResponse = response
-                    .json()
-                    .await
-                    .map_err(AppError::from)?;
-                
-                let mut quota_data = QuotaData::new();
-                
-                // Use debug level for detailed info to avoid console noise
-                tracing::debug!("Quota API returned {} models", quota_response.models.len());
-
-                for (name, info) in quota_response.models {
-                    if let Some(quota_info) = info.quota_info {
-                        let percentage = quota_info.remaining_fraction
-                            .map(|f| (f * 100.0) as i32)
-                            .unwrap_or(0);
-                        
-                        let reset_time = quota_info.reset_time.clone().unwrap_or_default();
-                        
-                        // Only keep models we care about
-                        if name.contains("gemini") || name.contains("claude") || name.contains("image") || name.contains("imagen") {
-                            quota_data.add_model(name, percentage, reset_time);
-                        }
-                    }
-                }
-                
-                // Set subscription tier
-                quota_data.subscription_tier = subscription_tier.clone();
-                
-                return Ok((quota_data, project_id.clone()));
-            },
-            Err(e) => {
-                crate::modules::logger::log_warn(&format!("Request failed: {} (Attempt {}/{})", e, attempt, MAX_RETRIES));
-                last_error = Some(AppError::from(e));
-                if attempt < MAX_RETRIES {
-                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                }
-            }
+    })
+    .await?;
+
+    let status = response.status();
+    // ✅ Special handling for 403 Forbidden - return directly, no retry
+    if status == rquest::StatusCode::FORBIDDEN {
+        let text = response.text().await.unwrap_or_default();
+        let (reason, validation_url) = parse_forbidden(&text);
+        crate::modules::logger::log_warn(&format!(
+            "[{}] Account unauthorized (403 Forbidden, {}), marking as forbidden",
+            email,
+            reason.as_deref().unwrap_or("unknown reason")
+        ));
+        let mut q = QuotaData::new();
+        q.is_forbidden = true;
+        q.forbidden_reason = reason;
+        q.validation_url = validation_url;
+        return Ok((q, project_id));
+    }
+    if !status.is_success() {
+        // Keep the status code so callers can handle 401 (token refresh) and 429
+        let text = response.text().await.unwrap_or_default();
+        return Err(AppError::Network(
+            format!("API Error: {} - {}", status, text),
+            Some(status.as_u16()),
+        ));
+    }
+
+    let quota_response: QuotaResponse = response.json().await.map_err(AppError::from)?;
+
+    // Use debug level for detailed info to avoid console noise
+    tracing::debug!("Quota API returned {} models", quota_response.models.len());
+
+    let quota_data = build_quota_data(quota_response.models, &config.model_filter);
+    Ok((quota_data, project_id))
+}
+
+/// 403 响应的原因（ErrorInfo.reason，其次 error.status）与验证地址
+fn parse_forbidden(body: &str) -> (Option<String>, Option<String>) {
+    let value: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let error = &value["error"];
+    let info = error["details"]
+        .as_array()
+        .and_then(|details| details.iter().find(|d| d["reason"].is_string()));
+    let reason = info
+        .and_then(|d| d["reason"].as_str())
+        .or_else(|| body.contains(VALIDATION_REQUIRED).then_some(VALIDATION_REQUIRED))
+        .or_else(|| error["status"].as_str())
+        .map(str::to_string);
+    let validation_url = info
+        .and_then(|d| {
+            let metadata = &d["metadata"];
+            metadata["validation_url"]
+                .as_str()
+                .or_else(|| metadata["validationUrl"].as_str())
+        })
+        .map(str::to_string);
+    (reason, validation_url)
+}
+
+/// Build quota data from the fetchAvailableModels `models` map, keeping every field of allowed models
+fn build_quota_data(
+    models: std::collections::HashMap<String, serde_json::Map<String, serde_json::Value>>,
+    filter: &ModelFilterConfig,
+) -> QuotaData {
+    let mut quota_data = QuotaData::new();
+    let mut names: Vec<_> = models.into_iter().filter(|(name, _)| filter.allows(name)).collect();
+    names.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (name, details) in names {
+        let quota_info = details
+            .get("quotaInfo")
+            .and_then(|v| serde_json::from_value::<QuotaInfo>(v.clone()).ok());
+        // 有 quotaInfo 但缺少 remainingFraction 表示额度已用尽；没有 quotaInfo 的模型不计配额
+        let remaining_fraction = quota_info
+            .as_ref()
+            .map(|info| info.remaining_fraction.unwrap_or(0.0));
+        let reset_time = quota_info
+            .and_then(|info| info.reset_time)
+            .unwrap_or_default();
+
+        quota_data.add_model(
+            name,
+            remaining_fraction.map_or(100, |f| (f * 100.0) as i32),
+            reset_time,
+        );
+        if let Some(model) = quota_data.models.last_mut() {
+            model.remaining_fraction = remaining_fraction;
+            model.unmetered = remaining_fraction.is_none();
+            model.details = details;
         }
     }
-    
-    Err(last_error.unwrap_or_else(|| AppError::Unknown("Quota fetch failed".to_string())))
+    quota_data
 }
 
 /// Internal fetch quota logic
 #[allow(dead_code)]
 pub async fn fetch_quota_inner(access_token: &str, email: &str) -> crate::error::AppResult<(QuotaData, Option<String>)> {
-    fetch_quota_with_cache(access_token, email, None, None).await
+    fetch_quota(access_token, email, None).await
 }
 
 /// Batch fetch all account quotas (backup functionality)
@@ -230,3 +263,104 @@ pub async fn fetch_all_quotas(accounts: Vec<(String, String, String)>) -> Vec<(S
     }
     results
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn models() -> std::collections::HashMap<String, serde_json::Map<String, serde_json::Value>> {
+        let response: QuotaResponse = serde_json::from_value(json!({
+            "models": {
+                "claude-sonnet-4-6": {
+                    "displayName": "Claude Sonnet 4.6",
+                    "quotaInfo": { "remainingFraction": 0.4567, "resetTime": "2026-01-01T05:00:00Z" }
+                },
+                "gemini-3-flash": { "quotaInfo": { "resetTime": "2026-01-01T06:00:00Z" } },
+                "new-family-1": { "maxTokens": 65536 },
+                "chat_internal": {}
+            }
+        }))
+        .unwrap();
+        response.models
+    }
+
+    #[test]
+    fn test_keeps_full_payload_with_fraction_and_timestamp() {
+        let quota = build_quota_data(models(), &ModelFilterConfig::default());
+        let names: Vec<_> = quota.models.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["chat_internal", "claude-sonnet-4-6", "gemini-3-flash", "new-family-1"]
+        );
+
+        let claude = &quota.models[1];
+        assert_eq!(claude.percentage, 45);
+        assert_eq!(claude.remaining_fraction, Some(0.4567));
+        assert_eq!(claude.reset_timestamp, Some(1_767_243_600));
+        assert_eq!(claude.details["displayName"], "Claude Sonnet 4.6");
+
+        // 缺少 remainingFraction 表示已用尽；没有 quotaInfo 的模型不计配额
+        assert_eq!(quota.models[2].remaining_fraction, Some(0.0));
+        assert_eq!(quota.models[3].remaining_fraction, None);
+        assert_eq!(quota.models[3].details["maxTokens"], 65536);
+    }
+
+    #[test]
+    fn test_models_without_quota_info_are_unmetered() {
+        let quota = build_quota_data(models(), &ModelFilterConfig::default());
+        let unmetered: Vec<_> =
+            quota.models.iter().filter(|m| m.unmetered).map(|m| m.name.as_str()).collect();
+        assert_eq!(unmetered, vec!["chat_internal", "new-family-1"]);
+
+        // 汇总只看计配额的模型，不会把无 quotaInfo 的模型当成 100%
+        let metered: Vec<_> = quota.metered_models().map(|m| m.percentage).collect();
+        assert_eq!(metered, vec![45, 0]);
+        assert_eq!(
+            crate::modules::quota_protection::group_minimum(
+                &quota,
+                &crate::models::config::QuotaProtectionConfig {
+                    monitored_models: vec!["new-family".into()],
+                    ..Default::default()
+                },
+                "new-family"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_include_exclude_filter() {
+        let filter = ModelFilterConfig {
+            include: vec!["Claude".into(), "gemini".into(), "family".into()],
+            exclude: vec!["flash".into()],
+        };
+        let quota = build_quota_data(models(), &filter);
+        let names: Vec<_> = quota.models.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["claude-sonnet-4-6", "new-family-1"]);
+    }
+
+    #[test]
+    fn test_parse_forbidden() {
+        let body = json!({"error": {
+            "code": 403,
+            "status": "PERMISSION_DENIED",
+            "details": [
+                {"@type": "type.googleapis.com/google.rpc.Help"},
+                {"reason": "VALIDATION_REQUIRED", "metadata": {"validation_url": "https://accounts.google.com/verify"}}
+            ]
+        }})
+        .to_string();
+        assert_eq!(
+            parse_forbidden(&body),
+            (
+                Some(VALIDATION_REQUIRED.to_string()),
+                Some("https://accounts.google.com/verify".to_string())
+            )
+        );
+        assert_eq!(
+            parse_forbidden(r#"{"error": {"code": 403, "status": "PERMISSION_DENIED"}}"#),
+            (Some("PERMISSION_DENIED".to_string()), None)
+        );
+        assert_eq!(parse_forbidden("Forbidden"), (None, None));
+    }
+}
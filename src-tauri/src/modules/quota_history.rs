@@ -0,0 +1,517 @@
+//! 配额历史时间序列（quota_history.db）
+//!
+//! 每次刷新配额时把各模型的剩余比例、重置时间与订阅等级写入 SQLite。
+//! 旧数据按配置逐级降采样（原始 → 每小时 → 每天），降采样后仍保留区间内的最小剩余比例，
+//! 以便统计额度耗尽次数。查询接口提供单模型曲线、每日消耗与耗尽次数统计。
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, Transaction};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::models::config::QuotaHistoryConfig;
+use crate::models::QuotaData;
+
+pub const HISTORY_DB: &str = "quota_history.db";
+
+/// 采样精度（秒），0 表示原始快照
+const RAW: i64 = 0;
+const HOURLY: i64 = 3_600;
+const DAILY: i64 = 86_400;
+
+type SharedConnection = Arc<Mutex<Connection>>;
+
+/// 按数据库路径复用的连接，建表与 WAL 设置只在首次打开时执行
+static CONNECTIONS: Lazy<Mutex<HashMap<PathBuf, SharedConnection>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 单个数据点
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaPoint {
+    pub ts: i64,
+    /// 剩余比例 0.0-1.0（降采样后为区间平均值）
+    pub remaining: f64,
+    /// 区间内最小剩余比例
+    pub min_remaining: f64,
+    pub reset_time: Option<String>,
+    pub tier: Option<String>,
+    /// 采样精度（秒），0 为原始快照
+    pub resolution: i64,
+}
+
+/// 单个账号单个模型的曲线
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaSeries {
+    pub account_id: String,
+    pub model: String,
+    pub points: Vec<QuotaPoint>,
+}
+
+/// 每日消耗（按 UTC 日期）
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyConsumption {
+    pub date: String,
+    pub account_id: String,
+    pub model: String,
+    /// 当日累计消耗的额度比例，配额重置造成的回升不计入
+    pub consumed: f64,
+}
+
+/// 额度耗尽统计
+#[derive(Debug, Clone, Serialize)]
+pub struct ExhaustionStat {
+    pub account_id: String,
+    pub model: String,
+    pub count: u32,
+    pub last_exhausted_at: Option<i64>,
+}
+
+/// 维护结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MaintenanceReport {
+    pub hourly_buckets: usize,
+    pub daily_buckets: usize,
+    pub purged: usize,
+}
+
+pub fn history_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(HISTORY_DB)
+}
+
+fn db_err(context: &str) -> impl Fn(rusqlite::Error) -> String + '_ {
+    move |e| format!("{}: {}", context, e)
+}
+
+/// 取得数据库连接；数据库文件被删除后重新打开并建表
+fn open(data_dir: &Path) -> Result<SharedConnection, String> {
+    let path = history_path(data_dir);
+    let mut connections = CONNECTIONS.lock();
+    if let Some(conn) = connections.remove(&path) {
+        if path.exists() {
+            connections.insert(path, conn.clone());
+            return Ok(conn);
+        }
+    }
+    let conn = Arc::new(Mutex::new(open_new(&path)?));
+    connections.insert(path, conn.clone());
+    Ok(conn)
+}
+
+fn open_new(path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(path)
+        .map_err(|e| format!("failed_to_open_quota_history: {}", e))?;
+    conn.busy_timeout(Duration::from_secs(5))
+        .map_err(db_err("failed_to_configure_quota_history"))?;
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL;
+        CREATE TABLE IF NOT EXISTS quota_samples (
+            account_id    TEXT NOT NULL,
+            model         TEXT NOT NULL,
+            ts            INTEGER NOT NULL,
+            resolution    INTEGER NOT NULL,
+            remaining     REAL NOT NULL,
+            min_remaining REAL NOT NULL,
+            samples       INTEGER NOT NULL,
+            reset_time    TEXT,
+            tier          TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_quota_samples_series
+            ON quota_samples(account_id, model, ts);
+        CREATE INDEX IF NOT EXISTS idx_quota_samples_resolution
+            ON quota_samples(resolution, ts);",
+    )
+    .map_err(db_err("failed_to_init_quota_history"))?;
+    Ok(conn)
+}
+
+/// 在指定数据目录中写入一次快照（每个模型一行）
+fn record_in_dir(data_dir: &Path, account_id: &str, quota: &QuotaData) -> Result<usize, String> {
+    if quota.is_forbidden || quota.metered_models().next().is_none() {
+        return Ok(0);
+    }
+    let conn = open(data_dir)?;
+    let mut conn = conn.lock();
+    let tx = conn
+        .transaction()
+        .map_err(db_err("failed_to_record_quota_history"))?;
+    for model in quota.metered_models() {
+        let remaining = model.remaining().clamp(0.0, 1.0);
+        let reset_time = (!model.reset_time.is_empty()).then_some(model.reset_time.as_str());
+        tx.execute(
+            "INSERT INTO quota_samples
+                (account_id, model, ts, resolution, remaining, min_remaining, samples, reset_time, tier)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5, 1, ?6, ?7)",
+            params![
+                account_id,
+                model.name,
+                quota.last_updated,
+                RAW,
+                remaining,
+                reset_time,
+                quota.subscription_tier
+            ],
+        )
+        .map_err(db_err("failed_to_record_quota_history"))?;
+    }
+    tx.commit()
+        .map_err(db_err("failed_to_record_quota_history"))?;
+    Ok(quota.metered_models().count())
+}
+
+/// 把早于 `cutoff` 的 `from` 精度数据合并为 `to` 大小的桶
+fn downsample_tx(tx: &Transaction, from: i64, to: i64, cutoff: i64) -> Result<usize, String> {
+    // 对齐到目标桶边界，避免同一个桶被拆成两行
+    let cutoff = cutoff.div_euclid(to) * to;
+    let buckets = tx
+        .execute(
+            "INSERT INTO quota_samples
+                (account_id, model, ts, resolution, remaining, min_remaining, samples, reset_time, tier)
+             SELECT account_id, model, (ts / ?2) * ?2 AS bucket, ?2,
+                    SUM(remaining * samples) / SUM(samples), MIN(min_remaining), SUM(samples),
+                    MAX(reset_time), MAX(tier)
+             FROM quota_samples
+             WHERE resolution = ?1 AND ts < ?3
+             GROUP BY account_id, model, bucket",
+            params![from, to, cutoff],
+        )
+        .map_err(db_err("failed_to_downsample_quota_history"))?;
+    tx.execute(
+        "DELETE FROM quota_samples WHERE resolution = ?1 AND ts < ?2",
+        params![from, cutoff],
+    )
+    .map_err(db_err("failed_to_downsample_quota_history"))?;
+    Ok(buckets)
+}
+
+/// 按保留期设置降采样并清除过期数据
+fn maintain_in_dir(
+    data_dir: &Path,
+    config: &QuotaHistoryConfig,
+    now: i64,
+) -> Result<MaintenanceReport, String> {
+    if !history_path(data_dir).exists() {
+        return Ok(MaintenanceReport::default());
+    }
+    let days = |d: u32| d as i64 * DAILY;
+
+    let conn = open(data_dir)?;
+    let mut conn = conn.lock();
+    let tx = conn
+        .transaction()
+        .map_err(db_err("failed_to_maintain_quota_history"))?;
+    let mut report = MaintenanceReport {
+        hourly_buckets: downsample_tx(&tx, RAW, HOURLY, now - days(config.raw_retention_days))?,
+        ..Default::default()
+    };
+    let hourly_cutoff = now - days(config.raw_retention_days.max(config.hourly_retention_days));
+    report.daily_buckets = downsample_tx(&tx, HOURLY, DAILY, hourly_cutoff)?;
+    if config.daily_retention_days > 0 {
+        report.purged = tx
+            .execute(
+                "DELETE FROM quota_samples WHERE ts < ?1",
+                params![hourly_cutoff.min(now - days(config.daily_retention_days))],
+            )
+            .map_err(db_err("failed_to_maintain_quota_history"))?;
+    }
+    tx.commit()
+        .map_err(db_err("failed_to_maintain_quota_history"))?;
+    Ok(report)
+}
+
+/// 按序列和时间顺序读取数据点；过滤条件为 `None` 时不过滤
+fn load_points(
+    data_dir: &Path,
+    account_id: Option<&str>,
+    model: Option<&str>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<BTreeMap<(String, String), Vec<QuotaPoint>>, String> {
+    let mut series: BTreeMap<(String, String), Vec<QuotaPoint>> = BTreeMap::new();
+    if !history_path(data_dir).exists() {
+        return Ok(series);
+    }
+    let conn = open(data_dir)?;
+    let conn = conn.lock();
+    let mut stmt = conn
+        .prepare(
+            "SELECT account_id, model, ts, remaining, min_remaining, reset_time, tier, resolution
+             FROM quota_samples
+             WHERE (?1 IS NULL OR account_id = ?1)
+               AND (?2 IS NULL OR model = ?2)
+               AND (?3 IS NULL OR ts >= ?3)
+               AND (?4 IS NULL OR ts <= ?4)
+             ORDER BY account_id, model, ts, resolution DESC",
+        )
+        .map_err(db_err("failed_to_query_quota_history"))?;
+    let rows = stmt
+        .query_map(params![account_id, model, since, until], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                QuotaPoint {
+                    ts: row.get(2)?,
+                    remaining: row.get(3)?,
+                    min_remaining: row.get(4)?,
+                    reset_time: row.get(5)?,
+                    tier: row.get(6)?,
+                    resolution: row.get(7)?,
+                },
+            ))
+        })
+        .map_err(db_err("failed_to_query_quota_history"))?;
+
+    for row in rows {
+        let (account_id, model, point) = row.map_err(db_err("failed_to_query_quota_history"))?;
+        series.entry((account_id, model)).or_default().push(point);
+    }
+    Ok(series)
+}
+
+fn utc_date(ts: i64) -> String {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// 相邻数据点间的下降量之和，计入后一个点所在的日期
+fn daily_consumption_of(
+    account_id: &str,
+    model: &str,
+    points: &[QuotaPoint],
+    since: Option<i64>,
+) -> Vec<DailyConsumption> {
+    let mut by_day: BTreeMap<String, f64> = BTreeMap::new();
+    for pair in points.windows(2) {
+        let (prev, next) = (&pair[0], &pair[1]);
+        if since.is_some_and(|since| next.ts < since) {
+            continue;
+        }
+        let drop = prev.remaining - next.remaining;
+        // 回升视为配额重置，不计入消耗
+        if drop > 0.0 {
+            *by_day.entry(utc_date(next.ts)).or_default() += drop;
+        }
+    }
+    by_day
+        .into_iter()
+        .map(|(date, consumed)| DailyConsumption {
+            date,
+            account_id: account_id.to_string(),
+            model: model.to_string(),
+            consumed,
+        })
+        .collect()
+}
+
+/// 统计进入耗尽状态（剩余降到 0）的次数
+fn exhaustion_of(account_id: &str, model: &str, points: &[QuotaPoint]) -> ExhaustionStat {
+    let mut stat = ExhaustionStat {
+        account_id: account_id.to_string(),
+        model: model.to_string(),
+        count: 0,
+        last_exhausted_at: None,
+    };
+    let mut exhausted = false;
+    for point in points {
+        let now_exhausted = point.min_remaining <= 0.0;
+        if now_exhausted && !exhausted {
+            stat.count += 1;
+            stat.last_exhausted_at = Some(point.ts);
+        }
+        exhausted = now_exhausted;
+    }
+    stat
+}
+
+fn history_config() -> QuotaHistoryConfig {
+    crate::modules::config::load_app_config()
+        .map(|config| config.quota_history)
+        .unwrap_or_default()
+}
+
+/// 记录一次配额快照；失败只记警告，不影响配额刷新
+pub fn record(account_id: &str, quota: &QuotaData) {
+    if !history_config().enabled {
+        return;
+    }
+    let result = crate::modules::account::get_data_dir()
+        .and_then(|data_dir| record_in_dir(&data_dir, account_id, quota));
+    if let Err(e) = result {
+        crate::modules::logger::log_warn(&format!(
+            "Failed to record quota history for {}: {}",
+            account_id, e
+        ));
+    }
+}
+
+/// 按配置降采样并清除过期数据（由调度器定期调用）
+pub fn maintain(config: &QuotaHistoryConfig) -> Result<MaintenanceReport, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    maintain_in_dir(&data_dir, config, chrono::Utc::now().timestamp())
+}
+
+/// 单个账号各模型的剩余比例曲线
+pub fn usage_curves(
+    account_id: &str,
+    model: Option<&str>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<QuotaSeries>, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(
+        load_points(&data_dir, Some(account_id), model, since, until)?
+            .into_iter()
+            .map(|((account_id, model), points)| QuotaSeries {
+                account_id,
+                model,
+                points,
+            })
+            .collect(),
+    )
+}
+
+/// 所有账号所有模型自 `since` 起的数据点，按 (account_id, model) 分组
+pub fn recent_series(since: i64) -> Result<BTreeMap<(String, String), Vec<QuotaPoint>>, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    load_points(&data_dir, None, None, Some(since), None)
+}
+
+/// 每日消耗（按账号、模型、UTC 日期）
+pub fn daily_consumption(
+    account_id: Option<&str>,
+    model: Option<&str>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<DailyConsumption>, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    // 多取一天，使 since 当天的第一个点也能与前一个点比较
+    let series = load_points(
+        &data_dir,
+        account_id,
+        model,
+        since.map(|s| s - DAILY),
+        until,
+    )?;
+    Ok(series
+        .iter()
+        .flat_map(|((account_id, model), points)| {
+            daily_consumption_of(account_id, model, points, since)
+        })
+        .collect())
+}
+
+/// 各账号各模型额度耗尽次数
+pub fn exhaustion_stats(
+    account_id: Option<&str>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<ExhaustionStat>, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    let mut stats: Vec<ExhaustionStat> = load_points(&data_dir, account_id, None, since, until)?
+        .iter()
+        .map(|((account_id, model), points)| exhaustion_of(account_id, model, points))
+        .filter(|stat| stat.count > 0)
+        .collect();
+    stats.sort_by_key(|stat| std::cmp::Reverse(stat.count));
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::{TestDataDir, NOW};
+
+    fn snapshot(ts: i64, percentage: i32) -> QuotaData {
+        let mut quota = QuotaData::new();
+        quota.last_updated = ts;
+        quota.subscription_tier = Some("PRO".to_string());
+        quota.add_model(
+            "gemini-3-flash".into(),
+            percentage,
+            "2026-01-02T00:00:00Z".into(),
+        );
+        quota
+    }
+
+    fn points(dir: &Path) -> Vec<QuotaPoint> {
+        load_points(dir, Some("acc"), Some("gemini-3-flash"), None, None)
+            .unwrap()
+            .into_values()
+            .next()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_downsampling_keeps_average_and_minimum() {
+        let dir = TestDataDir::new();
+        let day0 = NOW;
+        for (offset, pct) in [(0, 100), (600, 0), (1_200, 80), (4_000, 50)] {
+            record_in_dir(dir.path(), "acc", &snapshot(day0 + offset, pct)).unwrap();
+        }
+
+        let config = QuotaHistoryConfig {
+            enabled: true,
+            raw_retention_days: 1,
+            hourly_retention_days: 30,
+            daily_retention_days: 365,
+        };
+        let report = maintain_in_dir(dir.path(), &config, day0 + 2 * DAILY).unwrap();
+        assert_eq!(report.hourly_buckets, 2);
+        // 写入与维护复用同一个连接
+        assert!(Arc::ptr_eq(
+            &open(dir.path()).unwrap(),
+            &open(dir.path()).unwrap()
+        ));
+
+        let points = points(dir.path());
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].ts, day0);
+        assert_eq!(points[0].resolution, HOURLY);
+        assert!((points[0].remaining - 0.6).abs() < 1e-9);
+        assert_eq!(points[0].min_remaining, 0.0);
+        assert_eq!(points[0].tier.as_deref(), Some("PRO"));
+
+        // 超过每日保留期的数据被清除
+        let report = maintain_in_dir(dir.path(), &config, day0 + 400 * DAILY).unwrap();
+        assert_eq!(report.daily_buckets, 1);
+        assert_eq!(report.purged, 1);
+        assert!(self::points(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_daily_consumption_ignores_resets_and_exhaustion_is_counted_once() {
+        let dir = TestDataDir::new();
+        let day0 = NOW;
+        let samples = [
+            (0, 100),
+            (3_600, 40),
+            (7_200, 0),
+            (7_800, 0),
+            (DAILY, 100),
+            (DAILY + 60, 70),
+        ];
+        for (offset, pct) in samples {
+            record_in_dir(dir.path(), "acc", &snapshot(day0 + offset, pct)).unwrap();
+        }
+        let points = points(dir.path());
+
+        let daily = daily_consumption_of("acc", "gemini-3-flash", &points, None);
+        let by_date: Vec<_> = daily
+            .iter()
+            .map(|d| (d.date.as_str(), d.consumed))
+            .collect();
+        assert_eq!(by_date.len(), 2);
+        assert_eq!(by_date[0].0, "2026-01-01");
+        assert!((by_date[0].1 - 1.0).abs() < 1e-9);
+        assert_eq!(by_date[1].0, "2026-01-02");
+        assert!((by_date[1].1 - 0.3).abs() < 1e-9);
+
+        let stat = exhaustion_of("acc", "gemini-3-flash", &points);
+        assert_eq!(stat.count, 1);
+        assert_eq!(stat.last_exhausted_at, Some(day0 + 7_200));
+    }
+}
@@ -0,0 +1,275 @@
+//! 号池配额汇总
+//!
+//! 汇总所有启用账号的配额：监控模型按 `monitored_models` 分组（与配额保护一致，取组内最低剩余），
+//! 其余模型各自成组。403 账号和 `last_updated` 超过 `max_age_minutes` 的账号不计入。
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::models::config::QuotaProtectionConfig;
+use crate::models::Account;
+use crate::modules::quota_protection::group_minimum;
+
+/// 默认数据有效期（分钟），满额账号的刷新间隔可达数小时
+pub const DEFAULT_MAX_AGE_MINUTES: u32 = 180;
+const UNKNOWN_TIER: &str = "unknown";
+
+/// (账号, 剩余百分比, 重置时间)
+type Entry<'a> = (&'a Account, i32, Option<i64>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolAccount {
+    pub account_id: String,
+    pub email: String,
+    pub remaining_percentage: i32,
+    pub protected: bool,
+}
+
+/// 单个模型（或监控分组）的汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolModelSummary {
+    pub model: String,
+    /// 是否为 `monitored_models` 分组
+    pub monitored: bool,
+    /// 有该模型数据的账号数
+    pub accounts: usize,
+    /// 有剩余额度且未受保护的账号数
+    pub usable_accounts: usize,
+    pub total_remaining: i64,
+    pub average_remaining: f64,
+    pub next_reset_at: Option<i64>,
+    /// 按订阅等级分组的账号
+    pub by_tier: BTreeMap<String, Vec<PoolAccount>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolSummary {
+    pub generated_at: i64,
+    pub max_age_minutes: u32,
+    /// 参与汇总的账号数
+    pub accounts: usize,
+    /// 因数据过期被跳过的账号
+    pub stale_accounts: Vec<String>,
+    pub models: Vec<PoolModelSummary>,
+}
+
+fn summarize_group(
+    model: &str,
+    monitored: bool,
+    entries: Vec<Entry>,
+    now: i64,
+) -> PoolModelSummary {
+    let total_remaining: i64 = entries
+        .iter()
+        .map(|(_, remaining, _)| *remaining as i64)
+        .sum();
+    let mut summary = PoolModelSummary {
+        model: model.to_string(),
+        monitored,
+        accounts: entries.len(),
+        usable_accounts: 0,
+        total_remaining,
+        average_remaining: if entries.is_empty() {
+            0.0
+        } else {
+            total_remaining as f64 / entries.len() as f64
+        },
+        next_reset_at: entries
+            .iter()
+            .filter(|(_, remaining, _)| *remaining < 100)
+            .filter_map(|(_, _, reset)| *reset)
+            .filter(|&reset| reset > now)
+            .min(),
+        by_tier: BTreeMap::new(),
+    };
+
+    for (account, remaining, _) in entries {
+        let protected = account.protected_models.contains(model);
+        if remaining > 0 && !protected {
+            summary.usable_accounts += 1;
+        }
+        let tier = account
+            .quota
+            .as_ref()
+            .and_then(|q| q.subscription_tier.clone())
+            .unwrap_or_else(|| UNKNOWN_TIER.to_string());
+        summary.by_tier.entry(tier).or_default().push(PoolAccount {
+            account_id: account.id.clone(),
+            email: account.email.clone(),
+            remaining_percentage: remaining,
+            protected,
+        });
+    }
+    summary
+}
+
+fn build_summary(
+    accounts: &[Account],
+    protection: &QuotaProtectionConfig,
+    max_age_minutes: u32,
+    now: i64,
+) -> PoolSummary {
+    let mut stale_accounts = Vec::new();
+    let mut fresh = Vec::new();
+    for account in accounts.iter().filter(|a| !a.disabled) {
+        let Some(quota) = account.quota.as_ref().filter(|q| !q.is_forbidden) else {
+            continue;
+        };
+        if now - quota.last_updated > max_age_minutes as i64 * 60 {
+            stale_accounts.push(account.email.clone());
+        } else {
+            fresh.push(account);
+        }
+    }
+
+    let mut models = Vec::new();
+    for key in &protection.monitored_models {
+        let entries = fresh
+            .iter()
+            .filter_map(|a| {
+                let (remaining, reset) = group_minimum(a.quota.as_ref()?, protection, key)?;
+                Some((*a, remaining, reset))
+            })
+            .collect();
+        models.push(summarize_group(key, true, entries, now));
+    }
+
+    // 不属于任何监控分组的模型各自汇总
+    let mut others: BTreeMap<&str, Vec<Entry>> = BTreeMap::new();
+    for account in &fresh {
+        let Some(quota) = account.quota.as_ref() else {
+            continue;
+        };
+        for model in quota
+            .metered_models()
+            .filter(|m| !protection.monitors(&m.name))
+        {
+            others.entry(model.name.as_str()).or_default().push((
+                *account,
+                model.percentage,
+                model.reset_at(),
+            ));
+        }
+    }
+    models.extend(
+        others
+            .into_iter()
+            .map(|(model, entries)| summarize_group(model, false, entries, now)),
+    );
+
+    PoolSummary {
+        generated_at: now,
+        max_age_minutes,
+        accounts: fresh.len(),
+        stale_accounts,
+        models,
+    }
+}
+
+/// 汇总所有启用账号的配额
+pub fn pool_summary(max_age_minutes: Option<u32>) -> Result<PoolSummary, String> {
+    let config = crate::modules::config::load_app_config()?;
+    let accounts = crate::modules::account::list_accounts()?;
+    Ok(build_summary(
+        &accounts,
+        &config.quota_protection,
+        max_age_minutes.unwrap_or(DEFAULT_MAX_AGE_MINUTES),
+        chrono::Utc::now().timestamp(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::{account_with_quota, NOW};
+
+    fn account(
+        id: &str,
+        tier: Option<&str>,
+        models: &[(&str, i32, &str)],
+        age_secs: i64,
+    ) -> Account {
+        let mut account = account_with_quota(id, models);
+        let quota = account.quota.as_mut().unwrap();
+        quota.last_updated = NOW - age_secs;
+        quota.subscription_tier = tier.map(String::from);
+        account
+    }
+
+    fn config() -> QuotaProtectionConfig {
+        QuotaProtectionConfig {
+            monitored_models: vec!["claude".into()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_groups_monitored_models_and_tiers() {
+        let mut protected = account(
+            "c",
+            Some("g1-pro-tier"),
+            &[("claude-sonnet-4-6", 5, "2026-01-01T01:00:00Z")],
+            0,
+        );
+        protected.protected_models.insert("claude".into());
+        let accounts = vec![
+            account(
+                "a",
+                Some("g1-pro-tier"),
+                &[
+                    ("claude-sonnet-4-6", 80, "2026-01-01T03:00:00Z"),
+                    ("claude-opus-4-6", 40, "2026-01-01T02:00:00Z"),
+                    ("gemini-3-flash", 100, ""),
+                ],
+                60,
+            ),
+            account(
+                "b",
+                None,
+                &[("claude-sonnet-4-6", 0, "2026-01-01T04:00:00Z")],
+                60,
+            ),
+            protected,
+        ];
+
+        let summary = build_summary(&accounts, &config(), DEFAULT_MAX_AGE_MINUTES, NOW);
+        assert_eq!(summary.accounts, 3);
+        let claude = &summary.models[0];
+        assert_eq!(claude.model, "claude");
+        assert!(claude.monitored);
+        assert_eq!(claude.accounts, 3);
+        // a 取组内最低 40；b 已耗尽；c 受保护
+        assert_eq!(claude.usable_accounts, 1);
+        assert_eq!(claude.total_remaining, 45);
+        assert!((claude.average_remaining - 15.0).abs() < 1e-9);
+        assert_eq!(claude.next_reset_at, Some(NOW + 3600));
+        assert_eq!(claude.by_tier["g1-pro-tier"].len(), 2);
+        assert_eq!(claude.by_tier["unknown"][0].account_id, "b");
+
+        let flash = &summary.models[1];
+        assert_eq!(flash.model, "gemini-3-flash");
+        assert!(!flash.monitored);
+        assert_eq!((flash.accounts, flash.usable_accounts), (1, 1));
+        assert_eq!(flash.next_reset_at, None);
+    }
+
+    #[test]
+    fn test_skips_stale_disabled_and_forbidden() {
+        let mut disabled = account("d", None, &[("claude-sonnet-4-6", 100, "")], 0);
+        disabled.disabled = true;
+        let mut forbidden = account("f", None, &[("claude-sonnet-4-6", 100, "")], 0);
+        forbidden.quota.as_mut().unwrap().is_forbidden = true;
+        let accounts = vec![
+            account("fresh", None, &[("claude-sonnet-4-6", 60, "")], 30 * 60),
+            account("stale", None, &[("claude-sonnet-4-6", 90, "")], 31 * 60),
+            disabled,
+            forbidden,
+        ];
+
+        let summary = build_summary(&accounts, &config(), 30, NOW);
+        assert_eq!(summary.accounts, 1);
+        assert_eq!(summary.stale_accounts, vec!["stale@example.com"]);
+        assert_eq!(summary.models.len(), 1);
+        assert_eq!(summary.models[0].total_remaining, 60);
+    }
+}
@@ -0,0 +1,383 @@
+//! 模型配额保护
+//!
+//! `QuotaProtectionConfig.monitored_models` 中的每一项（模型名或前缀）是一个分组，模型归入最长匹配的一项。
+//! 组内每个模型按自身的阈值比较：任一模型降到触发阈值及以下时把分组加入 `protected_models`，
+//! 所有模型都回升到各自的解除阈值以上才移除（回差，避免反复进出保护）。
+//! 开启 `protect_until_reset` 时，触发后至少保护到该模型的 reset_time。
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::config::QuotaProtectionConfig;
+use crate::models::{Account, ModelProtection, ProtectionReason, QuotaData};
+
+/// 一次保护状态变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtectionChange {
+    pub model: String,
+    pub protected: bool,
+    pub reason: ProtectionReason,
+}
+
+/// 分组（按最长匹配归属）中剩余最少的模型：(剩余百分比, 重置时间)
+pub fn group_minimum(
+    quota: &QuotaData,
+    config: &QuotaProtectionConfig,
+    key: &str,
+) -> Option<(i32, Option<i64>)> {
+    quota
+        .metered_models()
+        .filter(|m| config.group_of(&m.name) == key)
+        .min_by_key(|m| m.percentage)
+        .map(|m| (m.percentage, m.reset_at()))
+}
+
+/// 分组内各模型按自身阈值比较后最严格的结果
+#[derive(Debug, Clone, Copy)]
+struct GroupLevel {
+    /// 离触发阈值最近的模型：剩余、触发阈值与重置时间
+    remaining: i32,
+    threshold: u32,
+    reset_at: Option<i64>,
+    /// 离解除阈值最近的模型：剩余与解除阈值
+    release_remaining: i32,
+    release: u32,
+}
+
+fn group_level(quota: &QuotaData, config: &QuotaProtectionConfig, key: &str) -> Option<GroupLevel> {
+    let models: Vec<_> = quota
+        .metered_models()
+        .filter(|m| config.group_of(&m.name) == key)
+        .collect();
+    let trigger = models
+        .iter()
+        .min_by_key(|m| m.percentage - config.threshold_for(&m.name) as i32)?;
+    let recover = models
+        .iter()
+        .min_by_key(|m| m.percentage - config.release_for(&m.name) as i32)?;
+    Some(GroupLevel {
+        remaining: trigger.percentage,
+        threshold: config.threshold_for(&trigger.name),
+        reset_at: trigger.reset_at(),
+        release_remaining: recover.percentage,
+        release: config.release_for(&recover.name),
+    })
+}
+
+fn protect(
+    account: &mut Account,
+    key: &str,
+    reason: ProtectionReason,
+    until: Option<i64>,
+    now: i64,
+    changes: &mut Vec<ProtectionChange>,
+) {
+    crate::modules::logger::log_info(&format!(
+        "[Quota] Triggering model protection: {} (Group: {} Reason: {:?})",
+        account.email, key, reason
+    ));
+    account.protected_models.insert(key.to_string());
+    account.protection.insert(
+        key.to_string(),
+        ModelProtection {
+            since: now,
+            until,
+            reason: reason.clone(),
+        },
+    );
+    changes.push(ProtectionChange {
+        model: key.to_string(),
+        protected: true,
+        reason,
+    });
+}
+
+fn release(
+    account: &mut Account,
+    key: &str,
+    reason: ProtectionReason,
+    changes: &mut Vec<ProtectionChange>,
+) {
+    crate::modules::logger::log_info(&format!(
+        "[Quota] Model protection recovered: {} (Group: {} Reason: {:?})",
+        account.email, key, reason
+    ));
+    account.protected_models.remove(key);
+    account.protection.remove(key);
+    changes.push(ProtectionChange {
+        model: key.to_string(),
+        protected: false,
+        reason,
+    });
+}
+
+/// 根据账号当前配额更新 `protected_models`，返回本次发生的状态变化
+pub fn evaluate(
+    account: &mut Account,
+    config: &QuotaProtectionConfig,
+    now: i64,
+) -> Vec<ProtectionChange> {
+    let mut changes = Vec::new();
+    let mut protected: Vec<String> = account.protected_models.iter().cloned().collect();
+    protected.sort();
+
+    if !config.enabled {
+        for key in &protected {
+            release(
+                account,
+                key,
+                ProtectionReason::ProtectionDisabled,
+                &mut changes,
+            );
+        }
+        return changes;
+    }
+    for key in protected
+        .iter()
+        .filter(|key| !config.monitored_models.contains(key))
+    {
+        release(account, key, ProtectionReason::NotMonitored, &mut changes);
+    }
+
+    // 没有配额数据（或 403）时保持现状
+    let Some(quota) = account.quota.as_ref().filter(|q| !q.is_forbidden) else {
+        return changes;
+    };
+    let groups: Vec<(String, GroupLevel)> = config
+        .monitored_models
+        .iter()
+        .filter_map(|key| group_level(quota, config, key).map(|level| (key.clone(), level)))
+        .collect();
+
+    // 新配额中已没有该分组的模型，解除保护以免永久卡住
+    let missing: Vec<String> = protected
+        .into_iter()
+        .filter(|key| account.protected_models.contains(key))
+        .filter(|key| !groups.iter().any(|(group, ..)| group == key))
+        .collect();
+    for key in &missing {
+        release(account, key, ProtectionReason::NoLongerReported, &mut changes);
+    }
+
+    for (key, level) in groups {
+        if account.protected_models.contains(&key) {
+            // 旧数据只有 protected_models 没有触发记录，按普通回差处理
+            let until = account
+                .protection
+                .get(&key)
+                .and_then(|p| p.until)
+                .filter(|_| config.protect_until_reset);
+            let remaining = level.release_remaining;
+            if until.is_some_and(|until| now < until) || remaining <= level.release as i32 {
+                continue;
+            }
+            let reason = match until {
+                Some(reset_at) => ProtectionReason::ResetPassed {
+                    reset_at,
+                    remaining,
+                },
+                None => ProtectionReason::Recovered {
+                    remaining,
+                    release: level.release,
+                },
+            };
+            release(account, &key, reason, &mut changes);
+        } else if level.remaining <= level.threshold as i32 {
+            let until = level
+                .reset_at
+                .filter(|&reset| reset > now)
+                .filter(|_| config.protect_until_reset);
+            let reason = ProtectionReason::BelowThreshold {
+                remaining: level.remaining,
+                threshold: level.threshold,
+            };
+            protect(account, &key, reason, until, now, &mut changes);
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::ModelThreshold;
+    use crate::modules::test_support::{account, NOW};
+
+    fn config() -> QuotaProtectionConfig {
+        QuotaProtectionConfig {
+            enabled: true,
+            threshold_percentage: 10,
+            monitored_models: vec!["claude".into(), "gemini-3-flash".into()],
+            ..Default::default()
+        }
+    }
+
+    fn set_quota(account: &mut Account, claude: i32, flash: i32) {
+        let mut quota = QuotaData::new();
+        quota.add_model(
+            "claude-sonnet-4-6".into(),
+            claude,
+            "2026-01-01T05:00:00Z".into(),
+        );
+        quota.add_model("claude-opus-4-6".into(), 100, String::new());
+        quota.add_model("gemini-3-flash".into(), flash, String::new());
+        account.quota = Some(quota);
+    }
+
+    #[test]
+    fn test_hysteresis_and_per_model_threshold() {
+        let mut config = config();
+        config.model_thresholds = vec![ModelThreshold {
+            pattern: "gemini".into(),
+            threshold_percentage: 30,
+            release_percentage: Some(50),
+        }];
+        let mut account = account("a1");
+
+        set_quota(&mut account, 10, 30);
+        let changes = evaluate(&mut account, &config, NOW);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            changes[0].reason,
+            ProtectionReason::BelowThreshold {
+                remaining: 10,
+                threshold: 10
+            }
+        );
+        assert!(account.protected_models.contains("claude"));
+        assert_eq!(account.protection["gemini-3-flash"].since, NOW);
+
+        // 回升但未超过解除阈值（claude 默认 15，gemini 50）时保持保护
+        set_quota(&mut account, 15, 45);
+        assert!(evaluate(&mut account, &config, NOW + 60).is_empty());
+        assert_eq!(account.protected_models.len(), 2);
+
+        set_quota(&mut account, 16, 45);
+        let changes = evaluate(&mut account, &config, NOW + 120);
+        assert_eq!(
+            changes,
+            vec![ProtectionChange {
+                model: "claude".into(),
+                protected: false,
+                reason: ProtectionReason::Recovered {
+                    remaining: 16,
+                    release: 15
+                },
+            }]
+        );
+        assert!(!account.protection.contains_key("claude"));
+
+        config.monitored_models.pop();
+        let changes = evaluate(&mut account, &config, NOW + 180);
+        assert_eq!(changes[0].reason, ProtectionReason::NotMonitored);
+        assert!(account.protected_models.is_empty());
+    }
+
+    #[test]
+    fn test_protect_until_reset() {
+        let config = QuotaProtectionConfig {
+            protect_until_reset: true,
+            ..config()
+        };
+        let mut account = account("a1");
+        set_quota(&mut account, 5, 100);
+        evaluate(&mut account, &config, NOW);
+        assert_eq!(account.protection["claude"].until, Some(NOW + 5 * 3600));
+
+        // 重置之前即使额度回升也不解除
+        set_quota(&mut account, 80, 100);
+        assert!(evaluate(&mut account, &config, NOW + 3600).is_empty());
+
+        let changes = evaluate(&mut account, &config, NOW + 5 * 3600);
+        assert_eq!(
+            changes[0].reason,
+            ProtectionReason::ResetPassed {
+                reset_at: NOW + 5 * 3600,
+                remaining: 80
+            }
+        );
+
+        let disabled = QuotaProtectionConfig {
+            enabled: false,
+            ..config.clone()
+        };
+        set_quota(&mut account, 0, 100);
+        evaluate(&mut account, &config, NOW + 6 * 3600);
+        let changes = evaluate(&mut account, &disabled, NOW + 6 * 3600);
+        assert_eq!(changes[0].reason, ProtectionReason::ProtectionDisabled);
+        assert!(account.protection.is_empty());
+    }
+
+    #[test]
+    fn test_group_missing_from_quota_is_released() {
+        let config = config();
+        let mut account = account("a1");
+        set_quota(&mut account, 5, 100);
+        evaluate(&mut account, &config, NOW);
+        assert!(account.protected_models.contains("claude"));
+
+        // claude 模型从响应中消失（下线或改名）
+        let mut quota = QuotaData::new();
+        quota.add_model("gemini-3-flash".into(), 100, String::new());
+        account.quota = Some(quota);
+        let changes = evaluate(&mut account, &config, NOW + 60);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].reason, ProtectionReason::NoLongerReported);
+        assert!(account.protected_models.is_empty());
+        assert!(account.protection.is_empty());
+    }
+
+    #[test]
+    fn test_specific_rule_applies_inside_broader_group() {
+        let config = QuotaProtectionConfig {
+            monitored_models: vec!["gemini".into()],
+            model_thresholds: vec![
+                ModelThreshold {
+                    pattern: "gemini".into(),
+                    threshold_percentage: 30,
+                    release_percentage: None,
+                },
+                ModelThreshold {
+                    pattern: "gemini-3-flash".into(),
+                    threshold_percentage: 40,
+                    release_percentage: None,
+                },
+            ],
+            ..config()
+        };
+        assert_eq!(config.threshold_for("gemini-3-pro-high"), 30);
+        assert_eq!(config.threshold_for("gemini-3-flash"), 40);
+
+        // 组内最低的 gemini-3-pro-high 仍高于 30，但 gemini-3-flash 已低于它自己的 40
+        let mut first = account("a1");
+        let mut quota = QuotaData::new();
+        quota.add_model("gemini-3-pro-high".into(), 35, String::new());
+        quota.add_model("gemini-3-flash".into(), 38, String::new());
+        first.quota = Some(quota);
+        let changes = evaluate(&mut first, &config, NOW);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].model, "gemini");
+        assert_eq!(
+            changes[0].reason,
+            ProtectionReason::BelowThreshold {
+                remaining: 38,
+                threshold: 40
+            }
+        );
+
+        // 重叠的监控项：模型只计入最长匹配的分组
+        let config = QuotaProtectionConfig {
+            monitored_models: vec!["gemini".into(), "gemini-3-flash".into()],
+            ..config
+        };
+        let mut quota = QuotaData::new();
+        quota.add_model("gemini-3-pro-high".into(), 50, String::new());
+        quota.add_model("gemini-3-flash".into(), 5, String::new());
+        assert_eq!(group_minimum(&quota, &config, "gemini"), Some((50, None)));
+        assert_eq!(group_minimum(&quota, &config, "gemini-3-flash"), Some((5, None)));
+        let mut second = account("a2");
+        second.quota = Some(quota);
+        let changes = evaluate(&mut second, &config, NOW);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].model, "gemini-3-flash");
+    }
+}
@@ -1,64 +1,372 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use tokio::time::{self, Duration};
-use crate::modules::{config, logger, account};
+use crate::models::{Account, AppConfig};
+use crate::modules::{config, logger, account, trash, quota_history, auto_switch};
+use crate::modules::integration::SystemManager;
+use crate::modules::audit::{self, EventSource};
 
-/// 启动周期性配额刷新调度器（反代预热功能已移除）
-pub fn start_scheduler(app_handle: Option<tauri::AppHandle>) {
-    tauri::async_runtime::spawn(async move {
-        logger::log_info("Scheduler started. Periodic quota refresh enabled.");
+/// 重新扫描账号列表并执行维护任务的间隔
+const RESCAN_INTERVAL_SECS: i64 = 600;
+/// 配额重置后稍等片刻再刷新，给服务端留出更新时间
+const RESET_GRACE_SECS: i64 = 30;
+/// 低于保护阈值加该余量（百分点）即视为接近阈值
+const NEAR_THRESHOLD_MARGIN: i32 = 10;
+/// 满额账号的刷新间隔倍数
+const FULL_INTERVAL_FACTOR: i64 = 4;
+/// 被禁止访问（403）的账号很少变化
+const FORBIDDEN_INTERVAL_SECS: i64 = 6 * 3600;
+/// 同一账号两次刷新的最小间隔
+const MIN_INTERVAL_SECS: i64 = 60;
+/// 刷新失败后的最长退避
+const MAX_BACKOFF_SECS: i64 = 3600;
 
-        // 每 10 分钟扫描一次
-        let mut interval = time::interval(Duration::from_secs(600));
+/// 计算账号下一次值得刷新的时间；禁用账号不参与调度
+pub fn next_refresh_at(account: &Account, config: &AppConfig, now: i64) -> Option<i64> {
+    if account.disabled {
+        return None;
+    }
+    let Some(quota) = account.quota.as_ref() else {
+        // 从未获取过配额，立即刷新
+        return Some(now);
+    };
+    if quota.is_forbidden {
+        return Some(quota.last_updated + FORBIDDEN_INTERVAL_SECS);
+    }
 
-        loop {
-            interval.tick().await;
+    let base = config.refresh_interval.max(1) as i64 * 60;
+    let mut models: Vec<_> = quota
+        .metered_models()
+        .filter(|m| config.quota_protection.monitors(&m.name))
+        .collect();
+    if models.is_empty() {
+        models = quota.metered_models().collect();
+    }
+    let Some(min_percentage) = models.iter().map(|m| m.percentage).min() else {
+        return Some(quota.last_updated + base);
+    };
 
-            // 加载配置
-            let Ok(app_config) = config::load_app_config() else {
-                continue;
-            };
+    // 任一模型接近其自身的保护阈值
+    let near_threshold = models.iter().any(|m| {
+        let threshold = config.quota_protection.threshold_for(&m.name) as i32;
+        m.percentage <= threshold + NEAR_THRESHOLD_MARGIN
+    });
+    let interval = if min_percentage >= 100 {
+        base * FULL_INTERVAL_FACTOR
+    } else if near_threshold {
+        (base / 3).max(MIN_INTERVAL_SECS)
+    } else {
+        base
+    };
+    let mut due = quota.last_updated + interval;
 
-            if !app_config.auto_refresh {
-                continue;
+    // 最早一次尚未反映到数据中的重置（满额模型的重置没有意义）
+    let earliest_reset = models
+        .iter()
+        .filter(|m| m.percentage < 100)
+        .filter_map(|m| m.reset_at())
+        .filter(|&reset| reset + RESET_GRACE_SECS > quota.last_updated)
+        .min();
+    if let Some(reset) = earliest_reset {
+        due = due.min(reset + RESET_GRACE_SECS);
+    }
+
+    Some(due.max(quota.last_updated + MIN_INTERVAL_SECS))
+}
+
+/// 连续失败后的退避时长
+fn failure_backoff(failures: u32) -> i64 {
+    (MIN_INTERVAL_SECS << failures.min(6)).min(MAX_BACKOFF_SECS)
+}
+
+/// 按到期时间排序的刷新队列；重新调度时旧条目惰性丢弃
+#[derive(Default)]
+struct RefreshQueue {
+    heap: BinaryHeap<Reverse<(i64, String)>>,
+    scheduled: HashMap<String, i64>,
+}
+
+impl RefreshQueue {
+    fn schedule(&mut self, account_id: &str, due: i64) {
+        if self.scheduled.get(account_id) == Some(&due) {
+            return;
+        }
+        self.scheduled.insert(account_id.to_string(), due);
+        self.heap.push(Reverse((due, account_id.to_string())));
+    }
+
+    fn unschedule(&mut self, account_id: &str) {
+        self.scheduled.remove(account_id);
+    }
+
+    /// 只保留指定账号
+    fn retain(&mut self, account_ids: &HashSet<String>) {
+        self.scheduled.retain(|id, _| account_ids.contains(id));
+    }
+
+    fn clear(&mut self) {
+        self.heap.clear();
+        self.scheduled.clear();
+    }
+
+    fn next_due(&mut self) -> Option<i64> {
+        while let Some(Reverse((due, id))) = self.heap.peek() {
+            if self.scheduled.get(id) == Some(due) {
+                return Some(*due);
+            }
+            self.heap.pop();
+        }
+        None
+    }
+
+    /// 取出所有已到期的账号
+    fn pop_due(&mut self, now: i64) -> Vec<String> {
+        let mut due_ids = Vec::new();
+        while let Some(due) = self.next_due() {
+            if due > now {
+                break;
+            }
+            if let Some(Reverse((_, id))) = self.heap.pop() {
+                self.scheduled.remove(&id);
+                due_ids.push(id);
             }
+        }
+        due_ids
+    }
+}
+
+/// 调度器状态：刷新队列与失败退避
+#[derive(Default)]
+struct Scheduler {
+    queue: RefreshQueue,
+    /// account_id -> (连续失败次数, 退避截止时间)
+    backoff: HashMap<String, (u32, i64)>,
+}
+
+impl Scheduler {
+    fn plan(&mut self, account: &Account, config: &AppConfig, now: i64) {
+        let Some(due) = next_refresh_at(account, config, now) else {
+            self.queue.unschedule(&account.id);
+            return;
+        };
+        let not_before = self.backoff.get(&account.id).map_or(due, |(_, until)| *until);
+        self.queue.schedule(&account.id, due.max(not_before));
+    }
+
+    /// 重新读取全部账号并安排刷新时间
+    fn rescan(&mut self, config: &AppConfig, now: i64) {
+        if !config.auto_refresh {
+            self.queue.clear();
+            return;
+        }
+        let Ok(accounts) = account::list_accounts() else {
+            return;
+        };
+        let ids: HashSet<String> = accounts.iter().map(|a| a.id.clone()).collect();
+        self.queue.retain(&ids);
+        self.backoff.retain(|id, _| ids.contains(id));
+        for account in &accounts {
+            self.plan(account, config, now);
+        }
+    }
+
+    /// 刷新到期账号并根据新数据重新安排，返回刷新成功的账号数
+    async fn refresh_due(&mut self, account_ids: Vec<String>, config: &AppConfig) -> usize {
+        let accounts: Vec<Account> = account_ids
+            .iter()
+            .filter_map(|id| account::load_account(id).ok())
+            .filter(|a| !a.disabled)
+            .collect();
+        if accounts.is_empty() {
+            return 0;
+        }
+        let previous: HashMap<String, Option<i64>> = accounts
+            .iter()
+            .map(|a| (a.id.clone(), a.quota.as_ref().map(|q| q.last_updated)))
+            .collect();
+
+        logger::log_info(&format!("[Scheduler] 刷新 {} 个到期账号的配额...", accounts.len()));
+        match account::refresh_quotas_for(accounts).await {
+            Ok(stats) => logger::log_info(&format!(
+                "[Scheduler] 配额刷新完成: {} 成功, {} 失败",
+                stats.success, stats.failed
+            )),
+            Err(e) => logger::log_error(&format!("[Scheduler] 配额刷新失败: {}", e)),
+        }
 
-            // 获取所有账号
-            let Ok(accounts) = account::list_accounts() else {
+        let now = chrono::Utc::now().timestamp();
+        let mut refreshed = 0;
+        for (id, last_updated) in previous {
+            let Ok(account) = account::load_account(&id) else {
                 continue;
             };
+            let updated = account.quota.as_ref().map(|q| q.last_updated);
+            if updated.is_some() && updated != last_updated {
+                refreshed += 1;
+                self.backoff.remove(&id);
+            } else {
+                let failures = self.backoff.get(&id).map_or(0, |(n, _)| *n) + 1;
+                self.backoff.insert(id.clone(), (failures, now + failure_backoff(failures)));
+            }
+            self.plan(&account, config, now);
+        }
+        refreshed
+    }
+}
+
+/// 启动配额刷新调度器：按各账号的重置时间与剩余额度计算下一次刷新时间
+pub fn start_scheduler(app_handle: Option<tauri::AppHandle>) {
+    tauri::async_runtime::spawn(audit::with_source(EventSource::Scheduler, async move {
+        logger::log_info("Scheduler started. Reset-aware quota refresh enabled.");
+
+        let mut scheduler = Scheduler::default();
+        let mut app_config = AppConfig::default();
+        let mut next_rescan = 0;
+
+        loop {
+            let now = chrono::Utc::now().timestamp();
+
+            if now >= next_rescan {
+                next_rescan = now + RESCAN_INTERVAL_SECS;
 
-            if accounts.is_empty() {
+                // 加载配置
+                if let Ok(loaded) = config::load_app_config() {
+                    app_config = loaded;
+
+                    // 清除超过保留期的回收站账号
+                    if let Err(e) = trash::purge_expired(app_config.trash_retention_days) {
+                        logger::log_warn(&format!("[Scheduler] 回收站清理失败: {}", e));
+                    }
+
+                    // 配额历史降采样与过期清理
+                    if let Err(e) = quota_history::maintain(&app_config.quota_history) {
+                        logger::log_warn(&format!("[Scheduler] 配额历史维护失败: {}", e));
+                    }
+
+                    scheduler.rescan(&app_config, now);
+                }
+            }
+
+            // 配额保护触发的自动切换
+            let integration = app_handle
+                .clone()
+                .map_or(SystemManager::Headless, SystemManager::Desktop);
+            auto_switch::run_pending(&integration).await;
+
+            let wake_at = [
+                scheduler.queue.next_due(),
+                auto_switch::pending_retry_at(),
+            ]
+            .into_iter()
+            .flatten()
+            .fold(next_rescan, i64::min);
+            if wake_at > now {
+                time::sleep(Duration::from_secs((wake_at - now) as u64)).await;
                 continue;
             }
 
-            logger::log_info(&format!(
-                "[Scheduler] 开始周期性配额刷新，共 {} 个账号...",
-                accounts.len()
-            ));
-
-            // 执行批量刷新
-            match account::refresh_all_quotas_logic().await {
-                Ok(stats) => {
-                    logger::log_info(&format!(
-                        "[Scheduler] 配额刷新完成: {} 成功, {} 失败",
-                        stats.success, stats.failed
-                    ));
-                }
-                Err(e) => {
-                    logger::log_error(&format!(
-                        "[Scheduler] 配额刷新失败: {}", e
-                    ));
-                }
+            let due_ids = scheduler.queue.pop_due(now);
+            if due_ids.is_empty() {
+                continue;
             }
+            let refreshed = scheduler.refresh_due(due_ids, &app_config).await;
 
             // 同步到前端
-            if let Some(handle) = app_handle.as_ref() {
-                let handle_inner = handle.clone();
-                tokio::spawn(async move {
-                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                    let _ = crate::commands::refresh_all_quotas_internal(Some(handle_inner)).await;
+            if refreshed > 0 {
+                if let Some(handle) = app_handle.as_ref() {
+                    use tauri::Emitter;
+                    let _ = handle.emit("accounts://refreshed", ());
+                    crate::modules::tray::update_tray_menus(handle);
                     logger::log_info("[Scheduler] 配额数据已同步到前端");
-                });
+                }
             }
         }
-    });
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::{account_with_quota, NOW};
+
+    fn config() -> AppConfig {
+        let mut config = AppConfig {
+            refresh_interval: 15,
+            ..Default::default()
+        };
+        config.quota_protection.threshold_percentage = 10;
+        config.quota_protection.monitored_models = vec!["claude".into(), "gemini-3-flash".into()];
+        config
+    }
+
+    #[test]
+    fn test_next_refresh_follows_reset_and_remaining_quota() {
+        let config = config();
+        let base = 15 * 60;
+
+        // 普通账号按配置间隔刷新
+        let normal = account_with_quota("acc", &[("gemini-3-flash", 60, "")]);
+        assert_eq!(next_refresh_at(&normal, &config, NOW), Some(NOW + base));
+
+        // 监控模型即将重置：重置后不久刷新；未监控模型的重置被忽略
+        let resetting = account_with_quota("acc", &[
+            ("claude-sonnet-4-6", 60, "2026-01-01T00:05:00Z"),
+            ("gemini-3-pro-image", 0, "2026-01-01T00:02:00Z"),
+        ]);
+        assert_eq!(
+            next_refresh_at(&resetting, &config, NOW),
+            Some(NOW + 300 + RESET_GRACE_SECS)
+        );
+
+        // 接近阈值时更频繁
+        let low = account_with_quota("acc", &[("gemini-3-flash", 15, "")]);
+        assert_eq!(next_refresh_at(&low, &config, NOW), Some(NOW + base / 3));
+
+        // 满额 / 被禁止 / 禁用
+        let full = account_with_quota("acc", &[("gemini-3-flash", 100, "2026-01-01T00:05:00Z")]);
+        assert_eq!(
+            next_refresh_at(&full, &config, NOW),
+            Some(NOW + base * FULL_INTERVAL_FACTOR)
+        );
+        let mut forbidden = account_with_quota("acc", &[]);
+        forbidden.quota.as_mut().unwrap().is_forbidden = true;
+        assert_eq!(
+            next_refresh_at(&forbidden, &config, NOW),
+            Some(NOW + FORBIDDEN_INTERVAL_SECS)
+        );
+        let mut disabled = normal.clone();
+        disabled.disabled = true;
+        assert_eq!(next_refresh_at(&disabled, &config, NOW), None);
+    }
+
+    #[test]
+    fn test_refresh_is_never_scheduled_back_to_back() {
+        let config = config();
+        // 重置时间已过但数据尚未刷新时，仍保持最小间隔
+        let stale = account_with_quota("acc", &[("claude-opus", 0, "2026-01-01T00:00:10Z")]);
+        assert_eq!(
+            next_refresh_at(&stale, &config, NOW),
+            Some(NOW + MIN_INTERVAL_SECS)
+        );
+        assert_eq!(failure_backoff(1), 120);
+        assert_eq!(failure_backoff(20), MAX_BACKOFF_SECS);
+    }
+
+    #[test]
+    fn test_queue_pops_in_order_and_drops_rescheduled_entries() {
+        let mut queue = RefreshQueue::default();
+        queue.schedule("a", 300);
+        queue.schedule("b", 100);
+        queue.schedule("c", 200);
+        // 重新调度后旧条目失效
+        queue.schedule("a", 150);
+        assert_eq!(queue.next_due(), Some(100));
+
+        assert_eq!(queue.pop_due(160), vec!["b".to_string(), "a".to_string()]);
+        assert_eq!(queue.next_due(), Some(200));
+
+        queue.retain(&HashSet::new());
+        assert_eq!(queue.next_due(), None);
+        assert!(queue.pop_due(i64::MAX).is_empty());
+    }
 }
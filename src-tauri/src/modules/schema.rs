@@ -0,0 +1,422 @@
+//! 账号 / 账号索引 / gui_config.json 的 schema 版本与迁移注册表
+//!
+//! 迁移按 schema 版本号顺序作用于原始 JSON（serde_json::Value），执行前将原文件备份到
+//! `backups/schema/<kind>-v<from>/`。由更新版本写入的数据会被拒绝，而不是在反序列化时被静默截断。
+
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::account::{ACCOUNT_INDEX_VERSION, ACCOUNT_SCHEMA_VERSION};
+use crate::models::config::APP_CONFIG_SCHEMA_VERSION;
+use crate::utils::atomic_file;
+
+/// 备份目录（`paths::backups_dir_in`）下存放迁移前原文件的子目录
+pub const BACKUP_DIR: &str = "schema";
+
+/// 受版本管理的数据类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    /// accounts/<id>.json 或 accounts.db 中的账号记录
+    Account,
+    /// accounts.json
+    AccountIndex,
+    /// gui_config.json
+    AppConfig,
+}
+
+/// 单个迁移步骤：from -> from + 1
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    apply: fn(&mut Map<String, Value>),
+}
+
+const ACCOUNT_MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "drop removed reverse-proxy fields",
+    apply: strip_account_proxy_fields,
+}];
+
+const INDEX_MIGRATIONS: &[Migration] = &[Migration {
+    from: 2,
+    description: "drop proxy_disabled from account summaries",
+    apply: strip_summary_proxy_fields,
+}];
+
+const CONFIG_MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "drop removed reverse-proxy and warmup settings",
+    apply: strip_config_proxy_fields,
+}];
+
+impl SchemaKind {
+    fn label(self) -> &'static str {
+        match self {
+            SchemaKind::Account => "account",
+            SchemaKind::AccountIndex => "account_index",
+            SchemaKind::AppConfig => "gui_config",
+        }
+    }
+
+    pub fn current_version(self) -> u32 {
+        match self {
+            SchemaKind::Account => ACCOUNT_SCHEMA_VERSION,
+            SchemaKind::AccountIndex => ACCOUNT_INDEX_VERSION,
+            SchemaKind::AppConfig => APP_CONFIG_SCHEMA_VERSION,
+        }
+    }
+
+    pub fn migrations(self) -> &'static [Migration] {
+        match self {
+            SchemaKind::Account => ACCOUNT_MIGRATIONS,
+            SchemaKind::AccountIndex => INDEX_MIGRATIONS,
+            SchemaKind::AppConfig => CONFIG_MIGRATIONS,
+        }
+    }
+}
+
+/// Read the schema version stored in a JSON object
+fn read_version(kind: SchemaKind, obj: &Map<String, Value>) -> Result<u32, String> {
+    match kind {
+        // 索引沿用 "2.0" 形式的字符串版本，只取主版本号
+        SchemaKind::AccountIndex => match obj.get("version") {
+            None => Ok(2),
+            Some(Value::String(s)) => s
+                .split('.')
+                .next()
+                .and_then(|major| major.trim().parse().ok())
+                .ok_or_else(|| format!("invalid_schema_version: account_index version {:?}", s)),
+            Some(other) => Err(format!(
+                "invalid_schema_version: account_index version {}",
+                other
+            )),
+        },
+        // 引入版本号之前写入的账号 / 配置视为 v1
+        SchemaKind::Account | SchemaKind::AppConfig => match obj.get("schema_version") {
+            None => Ok(1),
+            Some(v) => v.as_u64().map(|v| v as u32).ok_or_else(|| {
+                format!(
+                    "invalid_schema_version: {} schema_version {}",
+                    kind.label(),
+                    v
+                )
+            }),
+        },
+    }
+}
+
+fn write_version(kind: SchemaKind, obj: &mut Map<String, Value>, version: u32) {
+    match kind {
+        SchemaKind::AccountIndex => {
+            obj.insert(
+                "version".to_string(),
+                Value::String(format!("{}.0", version)),
+            );
+        }
+        SchemaKind::Account | SchemaKind::AppConfig => {
+            obj.insert("schema_version".to_string(), Value::from(version));
+        }
+    }
+}
+
+fn newer_version_error(kind: SchemaKind, version: u32) -> String {
+    format!(
+        "unsupported_schema_version: {} data was written by a newer version of the app (schema {}, this version supports up to {}). Please upgrade instead of downgrading.",
+        kind.label(),
+        version,
+        kind.current_version()
+    )
+}
+
+/// Migrate a JSON value in place to the current schema.
+/// Returns the original version if any migration ran.
+pub fn migrate(kind: SchemaKind, value: &mut Value) -> Result<Option<u32>, String> {
+    // 非对象交给后续反序列化报错
+    let Some(obj) = value.as_object_mut() else {
+        return Ok(None);
+    };
+
+    let current = kind.current_version();
+    let original = read_version(kind, obj)?;
+    if original > current {
+        return Err(newer_version_error(kind, original));
+    }
+
+    let mut version = original;
+    while version < current {
+        let step = kind
+            .migrations()
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| {
+                format!(
+                    "missing_schema_migration: no {} migration from version {}",
+                    kind.label(),
+                    version
+                )
+            })?;
+        (step.apply)(obj);
+        version += 1;
+        write_version(kind, obj, version);
+        crate::modules::logger::log_info(&format!(
+            "Migrated {} schema v{} -> v{}: {}",
+            kind.label(),
+            step.from,
+            version,
+            step.description
+        ));
+    }
+
+    Ok((original != current).then_some(original))
+}
+
+/// Migrate a value loaded from `path`, backing up the original file content first.
+/// Returns true if the value was migrated and should be written back.
+pub fn migrate_loaded(
+    kind: SchemaKind,
+    data_dir: &Path,
+    path: &Path,
+    original: &[u8],
+    value: &mut Value,
+) -> Result<bool, String> {
+    // 先在副本上迁移，确认成功后再备份，避免为失败的迁移留下备份
+    let mut migrated = value.clone();
+    let Some(from) = migrate(kind, &mut migrated)? else {
+        return Ok(false);
+    };
+    backup_original(data_dir, kind, path, from, original)?;
+    *value = migrated;
+    Ok(true)
+}
+
+/// Path of the pre-migration backup for a file
+pub fn backup_path(data_dir: &Path, kind: SchemaKind, path: &Path, from: u32) -> PathBuf {
+    crate::modules::paths::backups_dir_in(data_dir)
+        .join(BACKUP_DIR)
+        .join(format!("{}-v{}", kind.label(), from))
+        .join(path.file_name().unwrap_or_default())
+}
+
+/// Keep the original file content before its first migration (existing backups are not overwritten)
+fn backup_original(
+    data_dir: &Path,
+    kind: SchemaKind,
+    path: &Path,
+    from: u32,
+    content: &[u8],
+) -> Result<(), String> {
+    let backup = backup_path(data_dir, kind, path, from);
+    if backup.exists() {
+        return Ok(());
+    }
+    if let Some(dir) = backup.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("failed_to_create_schema_backup_dir: {}", e))?;
+    }
+    atomic_file::write_atomic(&backup, content)
+        .map_err(|e| format!("failed_to_backup_before_migration: {}", e))?;
+    crate::modules::logger::log_info(&format!(
+        "Backed up {} v{} to {:?} before migration",
+        kind.label(),
+        from,
+        backup
+    ));
+    Ok(())
+}
+
+/// Refuse to overwrite a file written by a newer version of the app
+pub fn ensure_supported_file(kind: SchemaKind, path: &Path) -> Result<(), String> {
+    let Ok(content) = fs::read(path) else {
+        return Ok(());
+    };
+    // 损坏的文件按原有逻辑允许覆盖
+    let Ok(Value::Object(obj)) = serde_json::from_slice::<Value>(&content) else {
+        return Ok(());
+    };
+    match read_version(kind, &obj) {
+        Ok(version) if version > kind.current_version() => Err(newer_version_error(kind, version)),
+        _ => Ok(()),
+    }
+}
+
+// ===== 迁移实现 =====
+
+fn remove_keys(obj: &mut Map<String, Value>, keys: &[&str]) {
+    for key in keys {
+        obj.remove(*key);
+    }
+}
+
+/// account v1 -> v2
+fn strip_account_proxy_fields(obj: &mut Map<String, Value>) {
+    remove_keys(
+        obj,
+        &[
+            "proxy_disabled",
+            "proxy_disabled_reason",
+            "proxy_disabled_at",
+            "proxy_id",
+            "proxy_bound_at",
+        ],
+    );
+}
+
+/// account_index v2 -> v3
+fn strip_summary_proxy_fields(obj: &mut Map<String, Value>) {
+    if let Some(Value::Array(accounts)) = obj.get_mut("accounts") {
+        for summary in accounts.iter_mut().filter_map(Value::as_object_mut) {
+            summary.remove("proxy_disabled");
+        }
+    }
+}
+
+/// gui_config v1 -> v2；旧版 proxy 下的模型映射只保留在 v1 备份中
+fn strip_config_proxy_fields(obj: &mut Map<String, Value>) {
+    remove_keys(obj, &["proxy", "scheduled_warmup"]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_registry_chains_to_current_version() {
+        for kind in [
+            SchemaKind::Account,
+            SchemaKind::AccountIndex,
+            SchemaKind::AppConfig,
+        ] {
+            let steps = kind.migrations();
+            for pair in steps.windows(2) {
+                assert_eq!(
+                    pair[0].from + 1,
+                    pair[1].from,
+                    "{:?} migrations must be contiguous",
+                    kind
+                );
+            }
+            assert_eq!(
+                steps.last().unwrap().from + 1,
+                kind.current_version(),
+                "{:?}",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_account_v1_strips_proxy_fields() {
+        let mut value = json!({
+            "id": "acc-1",
+            "email": "a@example.com",
+            "proxy_disabled": true,
+            "proxy_disabled_reason": "manual",
+            "proxy_disabled_at": 1700000000,
+            "proxy_id": "p-1",
+            "proxy_bound_at": 1700000001,
+            "custom_label": "work"
+        });
+
+        assert_eq!(migrate(SchemaKind::Account, &mut value).unwrap(), Some(1));
+        assert_eq!(
+            value,
+            json!({"id": "acc-1", "email": "a@example.com", "custom_label": "work", "schema_version": ACCOUNT_SCHEMA_VERSION})
+        );
+        // 已是当前版本时不再迁移
+        assert_eq!(migrate(SchemaKind::Account, &mut value).unwrap(), None);
+    }
+
+    #[test]
+    fn test_index_v2_strips_summary_proxy_fields() {
+        let mut value = json!({
+            "version": "2.0",
+            "accounts": [{"id": "acc-1", "email": "a@example.com", "proxy_disabled": false}],
+            "current_account_id": "acc-1"
+        });
+
+        assert_eq!(
+            migrate(SchemaKind::AccountIndex, &mut value).unwrap(),
+            Some(2)
+        );
+        assert_eq!(
+            value["version"],
+            json!(format!("{}.0", ACCOUNT_INDEX_VERSION))
+        );
+        assert_eq!(
+            value["accounts"][0],
+            json!({"id": "acc-1", "email": "a@example.com"})
+        );
+        assert_eq!(value["current_account_id"], json!("acc-1"));
+    }
+
+    #[test]
+    fn test_config_v1_migrates_to_current() {
+        let mut value = json!({
+            "language": "en",
+            "proxy": {"anthropic_mapping": {"claude-3": "gemini-pro"}},
+            "scheduled_warmup": {"enabled": true}
+        });
+
+        assert_eq!(migrate(SchemaKind::AppConfig, &mut value).unwrap(), Some(1));
+        assert_eq!(
+            value,
+            json!({"language": "en", "schema_version": APP_CONFIG_SCHEMA_VERSION})
+        );
+    }
+
+    #[test]
+    fn test_newer_versions_are_refused() {
+        let mut account = json!({"schema_version": ACCOUNT_SCHEMA_VERSION + 1});
+        let err = migrate(SchemaKind::Account, &mut account).unwrap_err();
+        assert!(err.starts_with("unsupported_schema_version"), "{}", err);
+
+        let mut index =
+            json!({"version": format!("{}.0", ACCOUNT_INDEX_VERSION + 1), "accounts": []});
+        assert!(migrate(SchemaKind::AccountIndex, &mut index).is_err());
+        // 被拒绝的数据保持原样
+        assert_eq!(index["accounts"], json!([]));
+
+        let mut garbage = json!({"version": "latest"});
+        assert!(migrate(SchemaKind::AccountIndex, &mut garbage)
+            .unwrap_err()
+            .starts_with("invalid_schema_version"));
+    }
+
+    #[test]
+    fn test_migrate_loaded_backs_up_original_and_guards_newer_files() {
+        let dir =
+            std::env::temp_dir().join(format!("antigravity_schema_test_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("gui_config.json");
+        let original =
+            r#"{"language":"en","scheduled_warmup":{},"proxy":{"anthropic_mapping":{"a":"b"}}}"#;
+        fs::write(&path, original).unwrap();
+
+        let mut value: Value = serde_json::from_str(original).unwrap();
+        assert!(migrate_loaded(
+            SchemaKind::AppConfig,
+            &dir,
+            &path,
+            original.as_bytes(),
+            &mut value
+        )
+        .unwrap());
+        let backup = backup_path(&dir, SchemaKind::AppConfig, &path, 1);
+        // 旧版模型映射只保留在备份中
+        assert_eq!(fs::read_to_string(&backup).unwrap(), original);
+        assert!(value.get("scheduled_warmup").is_none());
+        assert!(value.get("proxy").is_none());
+
+        assert!(ensure_supported_file(SchemaKind::AppConfig, &path).is_ok());
+        fs::write(
+            &path,
+            format!(r#"{{"schema_version":{}}}"#, APP_CONFIG_SCHEMA_VERSION + 1),
+        )
+        .unwrap();
+        assert!(ensure_supported_file(SchemaKind::AppConfig, &path).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
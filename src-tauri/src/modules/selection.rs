@@ -0,0 +1,433 @@
+//! 账号选择引擎
+//!
+//! 按 `AccountSelectionConfig` 中的策略为所有可用账号打分排序，并给出每个账号的各项得分与说明。
+//! 已禁用、需要验证、403、所选模型额度耗尽或处于保护中的账号不参与排序，单独列出排除原因。
+
+use serde::Serialize;
+
+use crate::models::config::{AccountSelectionConfig, SelectionStrategy};
+use crate::models::Account;
+
+/// 重置时间得分的参考范围：超过该时长才重置的账号得 0 分
+const RESET_HORIZON_SECS: f64 = 24.0 * 3600.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Factor {
+    Remaining,
+    Reset,
+    Tier,
+    LeastRecentlyUsed,
+}
+
+/// 单项得分，`score` 归一化到 0-1
+#[derive(Debug, Clone, Serialize)]
+pub struct FactorScore {
+    pub factor: Factor,
+    pub score: f64,
+    pub weight: f64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RankedAccount {
+    pub rank: usize,
+    pub account_id: String,
+    pub email: String,
+    pub current: bool,
+    pub score: f64,
+    pub factors: Vec<FactorScore>,
+    pub explanation: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExcludedAccount {
+    pub account_id: String,
+    pub email: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountRanking {
+    pub strategy: SelectionStrategy,
+    pub model: String,
+    pub ranked: Vec<RankedAccount>,
+    pub excluded: Vec<ExcludedAccount>,
+}
+
+fn exclusion_reason(account: &Account, model: &str, now: i64) -> Option<String> {
+    if account.disabled {
+        return Some(match &account.disabled_reason {
+            Some(reason) => format!("disabled: {}", reason),
+            None => "disabled".to_string(),
+        });
+    }
+    if account.validation_blocked
+        && account
+            .validation_blocked_until
+            .is_none_or(|until| until > now)
+    {
+        return Some("validation required".to_string());
+    }
+    if account.quota.as_ref().is_some_and(|q| q.is_forbidden) {
+        return Some("forbidden (403)".to_string());
+    }
+    // 保护分组与所选模型（名称或前缀）有交集即排除
+    if let Some(key) = account
+        .protected_models
+        .iter()
+        .find(|key| model.starts_with(key.as_str()) || key.starts_with(model))
+    {
+        return Some(format!("protected: {}", key));
+    }
+    if model_quota(account, model).is_some_and(|(percentage, _)| percentage <= 0) {
+        return Some(format!("exhausted: {}", model));
+    }
+    None
+}
+
+fn weights(config: &AccountSelectionConfig) -> [(Factor, f64); 4] {
+    let only = |factor: Factor| {
+        [
+            Factor::Remaining,
+            Factor::Reset,
+            Factor::Tier,
+            Factor::LeastRecentlyUsed,
+        ]
+        .map(|f| (f, if f == factor { 1.0 } else { 0.0 }))
+    };
+    match config.strategy {
+        SelectionStrategy::HighestRemaining => only(Factor::Remaining),
+        SelectionStrategy::SoonestReset => only(Factor::Reset),
+        SelectionStrategy::TierPreference => only(Factor::Tier),
+        SelectionStrategy::LeastRecentlyUsed => only(Factor::LeastRecentlyUsed),
+        SelectionStrategy::Weighted => [
+            (Factor::Remaining, config.weights.remaining),
+            (Factor::Reset, config.weights.reset),
+            (Factor::Tier, config.weights.tier),
+            (
+                Factor::LeastRecentlyUsed,
+                config.weights.least_recently_used,
+            ),
+        ],
+    }
+}
+
+/// 所选模型（名称或前缀）中剩余最少的一个：(剩余百分比, 重置时间)
+fn model_quota(account: &Account, model: &str) -> Option<(i32, Option<i64>)> {
+    account
+        .quota
+        .as_ref()?
+        .metered_models()
+        .filter(|m| m.name.starts_with(model))
+        .min_by_key(|m| m.percentage)
+        .map(|m| (m.percentage, m.reset_at()))
+}
+
+fn factor_score(
+    factor: Factor,
+    account: &Account,
+    config: &AccountSelectionConfig,
+    lru_range: (i64, i64),
+    now: i64,
+) -> (f64, String) {
+    let model = &config.model;
+    match factor {
+        Factor::Remaining => match model_quota(account, model) {
+            Some((percentage, _)) => (
+                percentage.clamp(0, 100) as f64 / 100.0,
+                format!("{} {}% remaining", model, percentage),
+            ),
+            None => (0.0, format!("no {} quota data", model)),
+        },
+        Factor::Reset => match model_quota(account, model) {
+            Some((percentage, _)) if percentage >= 100 => (1.0, format!("{} quota full", model)),
+            Some((_, Some(reset))) if reset <= now => (1.0, format!("{} reset due", model)),
+            Some((_, Some(reset))) => {
+                let secs = (reset - now) as f64;
+                (
+                    1.0 - (secs / RESET_HORIZON_SECS).min(1.0),
+                    format!("{} resets in {:.1}h", model, secs / 3600.0),
+                )
+            }
+            _ => (0.0, format!("no {} reset time", model)),
+        },
+        Factor::Tier => {
+            let tier = account
+                .quota
+                .as_ref()
+                .and_then(|q| q.subscription_tier.as_deref());
+            let Some(tier) = tier else {
+                return (0.0, "unknown tier".to_string());
+            };
+            let lower = tier.to_lowercase();
+            let preferences = &config.tier_preference;
+            match preferences
+                .iter()
+                .position(|p| lower.contains(&p.to_lowercase()))
+            {
+                Some(index) => (
+                    1.0 - index as f64 / preferences.len() as f64,
+                    format!("tier {} (preference #{})", tier, index + 1),
+                ),
+                None => (0.0, format!("tier {} not preferred", tier)),
+            }
+        }
+        Factor::LeastRecentlyUsed => {
+            let (oldest, newest) = lru_range;
+            let score = if newest > oldest {
+                (newest - account.last_used) as f64 / (newest - oldest) as f64
+            } else {
+                1.0
+            };
+            let hours = (now - account.last_used).max(0) as f64 / 3600.0;
+            (score, format!("last used {:.1}h ago", hours))
+        }
+    }
+}
+
+/// 为账号排序，`current_id` 对应的账号标记为 current 但仍参与排序
+pub fn rank(
+    accounts: &[Account],
+    current_id: Option<&str>,
+    config: &AccountSelectionConfig,
+    now: i64,
+) -> AccountRanking {
+    let mut excluded = Vec::new();
+    let mut candidates = Vec::new();
+    for account in accounts {
+        match exclusion_reason(account, &config.model, now) {
+            Some(reason) => excluded.push(ExcludedAccount {
+                account_id: account.id.clone(),
+                email: account.email.clone(),
+                reason,
+            }),
+            None => candidates.push(account),
+        }
+    }
+
+    let lru_range = (
+        candidates.iter().map(|a| a.last_used).min().unwrap_or(now),
+        candidates.iter().map(|a| a.last_used).max().unwrap_or(now),
+    );
+    let weights = weights(config);
+    let total_weight: f64 = weights.iter().map(|(_, w)| w.max(0.0)).sum();
+
+    let mut ranked: Vec<RankedAccount> = candidates
+        .into_iter()
+        .map(|account| {
+            let factors: Vec<FactorScore> = weights
+                .iter()
+                .map(|&(factor, weight)| {
+                    let (score, detail) = factor_score(factor, account, config, lru_range, now);
+                    FactorScore {
+                        factor,
+                        score,
+                        weight: weight.max(0.0),
+                        detail,
+                    }
+                })
+                .collect();
+            let score = if total_weight > 0.0 {
+                factors.iter().map(|f| f.score * f.weight).sum::<f64>() / total_weight
+            } else {
+                0.0
+            };
+            RankedAccount {
+                rank: 0,
+                account_id: account.id.clone(),
+                email: account.email.clone(),
+                current: current_id == Some(account.id.as_str()),
+                score,
+                factors,
+                explanation: String::new(),
+            }
+        })
+        .collect();
+
+    // 得分相同时依次按剩余额度、最久未使用、邮箱排序
+    let factor_of = |r: &RankedAccount, factor: Factor| {
+        r.factors
+            .iter()
+            .find(|f| f.factor == factor)
+            .map_or(0.0, |f| f.score)
+    };
+    ranked.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| {
+                factor_of(b, Factor::Remaining).total_cmp(&factor_of(a, Factor::Remaining))
+            })
+            .then_with(|| {
+                factor_of(b, Factor::LeastRecentlyUsed)
+                    .total_cmp(&factor_of(a, Factor::LeastRecentlyUsed))
+            })
+            .then_with(|| a.email.cmp(&b.email))
+    });
+    for (index, entry) in ranked.iter_mut().enumerate() {
+        entry.rank = index + 1;
+        let reasons: Vec<String> = entry
+            .factors
+            .iter()
+            .filter(|f| f.weight > 0.0)
+            .map(|f| format!("{} (score {:.2} x{:.2})", f.detail, f.score, f.weight))
+            .collect();
+        entry.explanation = format!(
+            "#{} with score {:.3}: {}",
+            entry.rank,
+            entry.score,
+            reasons.join(", ")
+        );
+    }
+
+    AccountRanking {
+        strategy: config.strategy,
+        model: config.model.clone(),
+        ranked,
+        excluded,
+    }
+}
+
+/// 按配置（可覆盖策略与模型）对所有账号排序
+pub fn rank_accounts(
+    strategy: Option<SelectionStrategy>,
+    model: Option<String>,
+) -> Result<AccountRanking, String> {
+    let mut config = crate::modules::config::load_app_config()?.account_selection;
+    if let Some(strategy) = strategy {
+        config.strategy = strategy;
+    }
+    if let Some(model) = model.filter(|m| !m.trim().is_empty()) {
+        config.model = model;
+    }
+    let accounts = crate::modules::account::list_accounts()?;
+    let current_id = crate::modules::account::get_current_account_id()?;
+    Ok(rank(
+        &accounts,
+        current_id.as_deref(),
+        &config,
+        chrono::Utc::now().timestamp(),
+    ))
+}
+
+/// 排名最高的非当前账号
+pub fn best_next_account() -> Result<Option<RankedAccount>, String> {
+    Ok(rank_accounts(None, None)?
+        .ranked
+        .into_iter()
+        .find(|r| !r.current))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::SelectionWeights;
+    use crate::modules::test_support::{account_with_quota, NOW};
+
+    fn account(id: &str, percentage: i32, reset_time: &str, tier: &str, last_used: i64) -> Account {
+        let mut account = account_with_quota(id, &[("claude-sonnet-4-6", percentage, reset_time)]);
+        account.quota.as_mut().unwrap().subscription_tier = Some(tier.into());
+        account.last_used = last_used;
+        account
+    }
+
+    fn accounts() -> Vec<Account> {
+        let mut disabled = account("disabled", 100, "", "g1-ultra-tier", NOW);
+        disabled.disabled = true;
+        let mut forbidden = account("forbidden", 100, "", "g1-ultra-tier", NOW);
+        forbidden.quota.as_mut().unwrap().is_forbidden = true;
+        vec![
+            account("a", 40, "2026-01-01T01:00:00Z", "free-tier", NOW - 3600),
+            account("b", 90, "2026-01-01T12:00:00Z", "g1-pro-tier", NOW),
+            account("c", 10, "2026-01-01T00:30:00Z", "g1-ultra-tier", NOW - 7200),
+            disabled,
+            forbidden,
+        ]
+    }
+
+    fn order(ranking: &AccountRanking) -> Vec<&str> {
+        ranking
+            .ranked
+            .iter()
+            .map(|r| r.account_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_single_strategies() {
+        let accounts = accounts();
+        let mut config = AccountSelectionConfig::default();
+
+        let ranking = rank(&accounts, Some("b"), &config, NOW);
+        assert_eq!(order(&ranking), vec!["b", "a", "c"]);
+        assert!(ranking.ranked[0].current);
+        assert!(ranking.ranked[0]
+            .explanation
+            .contains("claude 90% remaining"));
+        let excluded: Vec<_> = ranking.excluded.iter().map(|e| e.reason.as_str()).collect();
+        assert_eq!(excluded, vec!["disabled", "forbidden (403)"]);
+
+        config.strategy = SelectionStrategy::SoonestReset;
+        assert_eq!(
+            order(&rank(&accounts, None, &config, NOW)),
+            vec!["c", "a", "b"]
+        );
+
+        config.strategy = SelectionStrategy::TierPreference;
+        assert_eq!(
+            order(&rank(&accounts, None, &config, NOW)),
+            vec!["c", "b", "a"]
+        );
+
+        config.strategy = SelectionStrategy::LeastRecentlyUsed;
+        let ranking = rank(&accounts, None, &config, NOW);
+        assert_eq!(order(&ranking), vec!["c", "a", "b"]);
+        assert!(ranking.ranked[0].explanation.contains("last used 2.0h ago"));
+    }
+
+    #[test]
+    fn test_weighted_mix() {
+        let accounts = accounts();
+        let config = AccountSelectionConfig {
+            strategy: SelectionStrategy::Weighted,
+            weights: SelectionWeights {
+                remaining: 1.0,
+                reset: 0.0,
+                tier: 1.0,
+                least_recently_used: 0.0,
+            },
+            ..Default::default()
+        };
+        let ranking = rank(&accounts, None, &config, NOW);
+        // b: (0.9 + 0.5) / 2, c: (0.1 + 1.0) / 2, a: (0.4 + 0) / 2
+        assert_eq!(order(&ranking), vec!["b", "c", "a"]);
+        assert!((ranking.ranked[0].score - 0.7).abs() < 1e-9);
+        assert_eq!(ranking.ranked[0].factors.len(), 4);
+    }
+
+    #[test]
+    fn test_exhausted_and_protected_accounts_excluded() {
+        // 不排除的话两者在最早重置和最久未使用下都会排第一
+        let mut accounts = accounts();
+        accounts.push(account("exhausted", 0, "2026-01-01T00:10:00Z", "g1-ultra-tier", 0));
+        let mut protected = account("protected", 5, "2026-01-01T00:10:00Z", "g1-ultra-tier", 0);
+        protected.protected_models.insert("claude".into());
+        accounts.push(protected);
+
+        let mut config = AccountSelectionConfig::default();
+        for strategy in [
+            SelectionStrategy::SoonestReset,
+            SelectionStrategy::TierPreference,
+            SelectionStrategy::LeastRecentlyUsed,
+        ] {
+            config.strategy = strategy;
+            let ranking = rank(&accounts, None, &config, NOW);
+            assert_eq!(ranking.ranked[0].account_id, "c");
+            let excluded: Vec<_> = ranking.excluded.iter().map(|e| e.reason.as_str()).collect();
+            assert_eq!(
+                excluded,
+                vec!["disabled", "forbidden (403)", "exhausted: claude", "protected: claude"]
+            );
+        }
+    }
+}
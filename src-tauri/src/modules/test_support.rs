@@ -0,0 +1,118 @@
+//! 测试共用工具（仅测试）
+//!
+//! 临时数据目录、固定时间点、账号构造函数与环境变量覆盖，供各模块的单元测试复用。
+
+use parking_lot::{Mutex, MutexGuard};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::models::{Account, QuotaData, TokenData};
+
+/// 测试使用的固定时间点：2026-01-01T00:00:00Z
+pub const NOW: i64 = 1_767_225_600;
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// 进程环境变量的测试锁
+///
+/// 直接或间接（数据目录、上游地址、密钥环）读取 `ABV_*` 环境变量的测试都要持有它，
+/// 避免读到其他测试的 [`EnvOverride`]。
+pub fn lock_env() -> MutexGuard<'static, ()> {
+    ENV_LOCK.lock()
+}
+
+/// 临时设置环境变量，drop 时恢复原值；存续期间持有 [`lock_env`]
+pub struct EnvOverride {
+    saved: Vec<(&'static str, Option<String>)>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+/// 测试使用的固定主密钥，不依赖本机 machine id 或数据目录中的密钥文件
+pub const TEST_MASTER_KEY: &str = "test-master-key";
+
+impl EnvOverride {
+    /// 数据与配置目录指向 `dir`，并使用 [`TEST_MASTER_KEY`]
+    pub fn data_dir(dir: &TestDataDir) -> Self {
+        Self::data_dir_with(dir, Vec::new())
+    }
+
+    /// 同 [`EnvOverride::data_dir`]，另外设置 `vars`
+    pub fn data_dir_with(dir: &TestDataDir, mut vars: Vec<(&'static str, String)>) -> Self {
+        let path = dir.path().to_string_lossy().to_string();
+        vars.push(("ABV_DATA_DIR", path.clone()));
+        vars.push(("ABV_CONFIG_DIR", path));
+        vars.push(("ABV_ENCRYPTION_KEY", TEST_MASTER_KEY.to_string()));
+        Self::set(&vars)
+    }
+
+    pub fn set(vars: &[(&'static str, String)]) -> Self {
+        let lock = lock_env();
+        let saved = vars
+            .iter()
+            .map(|(name, _)| (*name, std::env::var(name).ok()))
+            .collect();
+        for (name, value) in vars {
+            std::env::set_var(name, value);
+        }
+        Self { saved, _lock: lock }
+    }
+}
+
+impl Drop for EnvOverride {
+    fn drop(&mut self) {
+        for (name, value) in &self.saved {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+    }
+}
+
+/// 独立的临时数据目录，drop 时删除
+pub struct TestDataDir {
+    path: PathBuf,
+}
+
+impl TestDataDir {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "antigravity_test_{}_{}",
+            std::process::id(),
+            uuid::Uuid::new_v4()
+        ));
+        fs::create_dir_all(&path).expect("Failed to create temp dir");
+        Self { path }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl Drop for TestDataDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// 没有配额数据的账号，邮箱为 `<id>@example.com`
+pub fn account(id: &str) -> Account {
+    Account::new(
+        id.into(),
+        format!("{}@example.com", id),
+        TokenData::new("a".into(), "r".into(), 3600, None, None, None),
+    )
+}
+
+/// 带配额的账号：`models` 为 (模型名, 剩余百分比, 重置时间)，配额更新时间为 [`NOW`]
+pub fn account_with_quota(id: &str, models: &[(&str, i32, &str)]) -> Account {
+    let mut account = account(id);
+    let mut quota = QuotaData::new();
+    quota.last_updated = NOW;
+    for (name, percentage, reset_time) in models {
+        quota.add_model(name.to_string(), *percentage, reset_time.to_string());
+    }
+    account.quota = Some(quota);
+    account
+}
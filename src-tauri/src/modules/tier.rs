@@ -0,0 +1,317 @@
+//! 订阅等级检测
+//!
+//! loadCodeAssist 返回项目 ID 以及 currentTier / paidTier。结果按账号缓存在 `tiers.json` 中，
+//! 超过 `tier_cache_ttl_minutes` 才重新查询；等级变化写入历史，并记录 `TierChanged` 审计事件、
+//! 向前端发送 `accounts://tier-changed`。
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::modules::audit::{self, AuditEvent};
+use crate::modules::data_lock;
+use crate::utils::atomic_file;
+
+const TIERS_FILE: &str = "tiers.json";
+/// 每个账号保留的等级变化历史条数
+const MAX_HISTORY: usize = 50;
+
+/// 串行化 tiers.json 的读改写
+static TIERS_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// 生效等级的变化：(原等级, 新等级)
+type Transition = (Option<String>, Option<String>);
+
+/// loadCodeAssist 的查询结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TierInfo {
+    pub project_id: Option<String>,
+    pub current_tier: Option<String>,
+    pub paid_tier: Option<String>,
+}
+
+impl TierInfo {
+    /// 生效的订阅等级：paid_tier 优先，比 current_tier 更能反映账号实际权益
+    pub fn subscription_tier(&self) -> Option<String> {
+        self.paid_tier.clone().or_else(|| self.current_tier.clone())
+    }
+}
+
+/// 一次等级变化后的状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TierSnapshot {
+    pub ts: i64,
+    pub current_tier: Option<String>,
+    pub paid_tier: Option<String>,
+}
+
+/// 单个账号的缓存与历史
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TierRecord {
+    /// 最近一次成功查询的时间
+    pub checked_at: i64,
+    #[serde(flatten)]
+    pub info: TierInfo,
+    #[serde(default)]
+    pub history: Vec<TierSnapshot>,
+}
+
+impl TierRecord {
+    fn is_fresh(&self, ttl_secs: i64, now: i64) -> bool {
+        self.checked_at > 0 && now - self.checked_at < ttl_secs
+    }
+
+    /// 写入一次查询结果，生效等级变化时返回变化
+    fn apply(&mut self, info: TierInfo, now: i64) -> Option<Transition> {
+        let known = self.checked_at > 0;
+        let previous = self.info.subscription_tier();
+        if !known
+            || self.info.current_tier != info.current_tier
+            || self.info.paid_tier != info.paid_tier
+        {
+            self.history.push(TierSnapshot {
+                ts: now,
+                current_tier: info.current_tier.clone(),
+                paid_tier: info.paid_tier.clone(),
+            });
+            let excess = self.history.len().saturating_sub(MAX_HISTORY);
+            self.history.drain(..excess);
+        }
+
+        // 响应中缺少项目 ID 时沿用旧值
+        let project_id = info.project_id.clone().or(self.info.project_id.take());
+        self.info = TierInfo { project_id, ..info };
+        self.checked_at = now;
+
+        let current = self.info.subscription_tier();
+        (known && previous != current).then_some((previous, current))
+    }
+}
+
+/// 等级变化事件（发送给前端）
+#[derive(Debug, Clone, Serialize)]
+pub struct TierChange {
+    pub account_id: String,
+    pub email: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+fn tiers_path_in(data_dir: &Path) -> PathBuf {
+    data_dir.join(TIERS_FILE)
+}
+
+fn load_in_dir(data_dir: &Path) -> Result<HashMap<String, TierRecord>, String> {
+    let path = tiers_path_in(data_dir);
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("read_tiers_failed: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("parse_tiers_failed: {}", e))
+}
+
+fn save_in_dir(data_dir: &Path, records: &HashMap<String, TierRecord>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(records)
+        .map_err(|e| format!("serialize_tiers_failed: {}", e))?;
+    atomic_file::write_atomic(&tiers_path_in(data_dir), content)
+        .map_err(|e| format!("write_tiers_failed: {}", e))
+}
+
+/// 保存查询结果，返回合并后的信息与等级变化
+fn store_in_dir(
+    data_dir: &Path,
+    account_id: &str,
+    info: TierInfo,
+    now: i64,
+) -> Result<(TierInfo, Option<Transition>), String> {
+    let _lock = TIERS_LOCK.lock();
+    let _data_lock = data_lock::acquire_in_dir(data_dir)?;
+    let mut records = load_in_dir(data_dir)?;
+    let record = records.entry(account_id.to_string()).or_default();
+    let change = record.apply(info, now);
+    let info = record.info.clone();
+    save_in_dir(data_dir, &records)?;
+    Ok((info, change))
+}
+
+/// 删除账号的缓存记录与等级历史，返回删除条数
+pub fn remove_records_in_dir(data_dir: &Path, account_ids: &[String]) -> Result<usize, String> {
+    let _lock = TIERS_LOCK.lock();
+    let _data_lock = data_lock::acquire_in_dir(data_dir)?;
+    let mut records = load_in_dir(data_dir)?;
+    let before = records.len();
+    records.retain(|id, _| !account_ids.contains(id));
+    let removed = before - records.len();
+    if removed > 0 {
+        save_in_dir(data_dir, &records)?;
+    }
+    Ok(removed)
+}
+
+fn notify_change(account_id: &str, email: &str, from: Option<String>, to: Option<String>) {
+    crate::modules::logger::log_warn(&format!(
+        "[Tier] {} subscription changed: {} -> {}",
+        email,
+        from.as_deref().unwrap_or("none"),
+        to.as_deref().unwrap_or("none")
+    ));
+    audit::record(
+        account_id,
+        email,
+        AuditEvent::TierChanged {
+            from: from.clone(),
+            to: to.clone(),
+        },
+    );
+    if let Some(handle) = crate::modules::log_bridge::app_handle() {
+        use tauri::Emitter;
+        let _ = handle.emit(
+            "accounts://tier-changed",
+            TierChange {
+                account_id: account_id.to_string(),
+                email: email.to_string(),
+                from,
+                to,
+            },
+        );
+    }
+}
+
+/// 账号的缓存记录与等级历史
+pub fn get_record(account_id: &str) -> Result<Option<TierRecord>, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    Ok(load_in_dir(&data_dir)?.remove(account_id))
+}
+
+/// 查询并写入缓存，等级变化时发出通知
+async fn lookup(account_id: &str, access_token: &str, email: &str) -> Result<TierInfo, String> {
+    let info =
+        crate::modules::quota::load_code_assist(access_token, email, Some(account_id)).await?;
+    let data_dir = crate::modules::account::get_data_dir()?;
+    // 等待数据目录锁可能阻塞，放到阻塞线程池里
+    let id = account_id.to_string();
+    let (info, change) = tokio::task::spawn_blocking(move || {
+        store_in_dir(&data_dir, &id, info, chrono::Utc::now().timestamp())
+    })
+    .await
+    .map_err(|e| format!("store_tier_task_failed: {}", e))??;
+    if let Some((from, to)) = change {
+        notify_change(account_id, email, from, to);
+    }
+    Ok(info)
+}
+
+/// 获取账号的项目 ID 与订阅等级，缓存未过期时不发请求；查询失败时退回缓存（可能已过期）
+pub async fn resolve(account_id: &str, access_token: &str, email: &str) -> TierInfo {
+    let now = chrono::Utc::now().timestamp();
+    let ttl_minutes = crate::modules::config::load_app_config()
+        .unwrap_or_default()
+        .tier_cache_ttl_minutes;
+    let cached = get_record(account_id).unwrap_or_else(|e| {
+        crate::modules::logger::log_warn(&format!("[Tier] {}", e));
+        None
+    });
+    if let Some(record) = cached
+        .as_ref()
+        .filter(|r| r.is_fresh(ttl_minutes as i64 * 60, now))
+    {
+        return record.info.clone();
+    }
+
+    match lookup(account_id, access_token, email).await {
+        Ok(info) => info,
+        Err(e) => {
+            crate::modules::logger::log_warn(&format!("[Tier] {} lookup failed: {}", email, e));
+            cached.map(|r| r.info).unwrap_or_default()
+        }
+    }
+}
+
+/// 立即重新查询账号的订阅等级
+pub async fn refresh_account_tier(account_id: &str) -> Result<TierRecord, String> {
+    let mut account = crate::modules::account::load_account(account_id)?;
+    let token = crate::modules::oauth::ensure_fresh_token(&account.token, Some(account_id)).await?;
+    if token.access_token != account.token.access_token {
+        account.token = token;
+        crate::modules::account::save_account(&account)?;
+    }
+    lookup(account_id, &account.token.access_token, &account.email).await?;
+    get_record(account_id)?.ok_or_else(|| "tier_record_missing".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDataDir;
+
+    fn info(project: Option<&str>, current: &str, paid: Option<&str>) -> TierInfo {
+        TierInfo {
+            project_id: project.map(String::from),
+            current_tier: Some(current.to_string()),
+            paid_tier: paid.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_apply_detects_effective_tier_change() {
+        let mut record = TierRecord::default();
+        // 首次查询只记录历史，不视为变化
+        assert_eq!(
+            record.apply(info(Some("p1"), "free-tier", Some("g1-pro-tier")), 100),
+            None
+        );
+        assert_eq!(record.history.len(), 1);
+        assert!(record.is_fresh(60, 150));
+        assert!(!record.is_fresh(60, 160));
+
+        // current_tier 变化但 paid_tier 不变：写历史但生效等级不变
+        assert_eq!(
+            record.apply(info(None, "standard-tier", Some("g1-pro-tier")), 200),
+            None
+        );
+        assert_eq!(record.history.len(), 2);
+        assert_eq!(record.info.project_id.as_deref(), Some("p1"));
+
+        assert_eq!(
+            record.apply(info(None, "free-tier", None), 300),
+            Some((Some("g1-pro-tier".into()), Some("free-tier".into())))
+        );
+        assert_eq!(record.history.len(), 3);
+        assert_eq!(record.apply(info(None, "free-tier", None), 400), None);
+        assert_eq!(record.history.len(), 3);
+        assert_eq!(record.checked_at, 400);
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = TestDataDir::new();
+
+        let (stored, change) = store_in_dir(
+            dir.path(),
+            "a1",
+            info(Some("p1"), "free-tier", Some("g1-pro-tier")),
+            100,
+        )
+        .unwrap();
+        assert_eq!(stored.subscription_tier().as_deref(), Some("g1-pro-tier"));
+        assert_eq!(change, None);
+        let (_, change) =
+            store_in_dir(dir.path(), "a1", info(None, "free-tier", None), 200).unwrap();
+        assert!(change.is_some());
+
+        let records = load_in_dir(dir.path()).unwrap();
+        assert_eq!(records["a1"].history.len(), 2);
+        assert_eq!(records["a1"].info.project_id.as_deref(), Some("p1"));
+
+        store_in_dir(dir.path(), "a2", info(None, "free-tier", None), 300).unwrap();
+        let removed = remove_records_in_dir(dir.path(), &["a1".into(), "gone".into()]).unwrap();
+        assert_eq!(removed, 1);
+        let records = load_in_dir(dir.path()).unwrap();
+        assert!(!records.contains_key("a1") && records.contains_key("a2"));
+        // 写入时持有的数据目录锁已释放
+        assert!(!dir.path().join("data.lock").exists());
+    }
+}
@@ -0,0 +1,291 @@
+//! 账号回收站（trash/）
+//!
+//! 删除账号时，账号数据（令牌保持加密）连同删除时间与原索引位置写入 `trash/<entry_id>.json`，
+//! 之后可恢复或手动清除；超过 `trash_retention_days` 的条目由调度器自动清除。
+//! 账号索引的读写仍由 `modules::account` 负责。
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::Account;
+use crate::modules::schema::{self, SchemaKind};
+use crate::utils::atomic_file;
+
+const TRASH_DIR: &str = "trash";
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// 回收站条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: String,
+    pub deleted_at: i64,
+    /// 删除前在账号索引中的位置
+    pub position: Option<usize>,
+    /// 删除前是否为当前账号
+    #[serde(default)]
+    pub was_current: bool,
+    /// 完整账号数据，令牌保持加密
+    pub account: Account,
+}
+
+/// 回收站列表项（不含令牌）
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashItem {
+    pub id: String,
+    pub account_id: String,
+    pub email: String,
+    pub name: Option<String>,
+    pub custom_label: Option<String>,
+    pub deleted_at: i64,
+    pub position: Option<usize>,
+    /// 自动清除时间，保留期为 0 时为空
+    pub expires_at: Option<i64>,
+}
+
+impl TrashItem {
+    fn new(entry: &TrashEntry, retention_days: u32) -> Self {
+        Self {
+            id: entry.id.clone(),
+            account_id: entry.account.id.clone(),
+            email: entry.account.email.clone(),
+            name: entry.account.name.clone(),
+            custom_label: entry.account.custom_label.clone(),
+            deleted_at: entry.deleted_at,
+            position: entry.position,
+            expires_at: expires_at(entry.deleted_at, retention_days),
+        }
+    }
+}
+
+fn expires_at(deleted_at: i64, retention_days: u32) -> Option<i64> {
+    (retention_days > 0).then(|| deleted_at + retention_days as i64 * SECONDS_PER_DAY)
+}
+
+pub fn trash_dir_in(data_dir: &Path) -> PathBuf {
+    data_dir.join(TRASH_DIR)
+}
+
+fn entry_path(data_dir: &Path, entry_id: &str) -> Result<PathBuf, String> {
+    // 条目 ID 来自前端，禁止路径穿越
+    if entry_id.is_empty()
+        || !entry_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(format!("invalid_trash_entry_id: {}", entry_id));
+    }
+    Ok(trash_dir_in(data_dir).join(format!("{}.json", entry_id)))
+}
+
+/// 写入回收站条目（原子替换）
+pub fn write_entry(data_dir: &Path, entry: &TrashEntry) -> Result<(), String> {
+    let dir = trash_dir_in(data_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("failed_to_create_trash_dir: {}", e))?;
+
+    let content = serde_json::to_string_pretty(entry)
+        .map_err(|e| format!("failed_to_serialize_trash_entry: {}", e))?;
+    atomic_file::write_atomic(&entry_path(data_dir, &entry.id)?, content)
+        .map_err(|e| format!("failed_to_write_trash_entry: {}", e))
+}
+
+fn parse_entry(content: &str) -> Result<TrashEntry, String> {
+    let mut value: serde_json::Value =
+        serde_json::from_str(content).map_err(|e| format!("failed_to_parse_trash_entry: {}", e))?;
+    // 回收站中的账号可能早于当前 schema
+    if let Some(account) = value.get_mut("account") {
+        schema::migrate(SchemaKind::Account, account)?;
+    }
+    serde_json::from_value(value).map_err(|e| format!("failed_to_parse_trash_entry: {}", e))
+}
+
+/// 读取单个回收站条目
+pub fn read_entry(data_dir: &Path, entry_id: &str) -> Result<TrashEntry, String> {
+    let path = entry_path(data_dir, entry_id)?;
+    if !path.exists() {
+        return Err(format!("trash_entry_not_found: {}", entry_id));
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("failed_to_read_trash_entry: {}", e))?;
+    parse_entry(&content)
+}
+
+/// 列出回收站条目，最近删除的在前
+pub fn list_entries(data_dir: &Path) -> Result<Vec<TrashEntry>, String> {
+    let dir = trash_dir_in(data_dir);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for file in fs::read_dir(&dir)
+        .map_err(|e| format!("failed_to_read_trash_dir: {}", e))?
+        .flatten()
+    {
+        let path = file.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| parse_entry(&content))
+        {
+            Ok(entry) => entries.push(entry),
+            Err(e) => crate::modules::logger::log_warn(&format!(
+                "Skipping unreadable trash entry {:?}: {}",
+                path.file_name().unwrap_or_default(),
+                e
+            )),
+        }
+    }
+
+    entries.sort_by_key(|e| std::cmp::Reverse(e.deleted_at));
+    Ok(entries)
+}
+
+/// 删除回收站条目，返回实际删除的条数
+pub fn remove_entries(data_dir: &Path, entry_ids: &[String]) -> Result<usize, String> {
+    let mut removed = 0;
+    for entry_id in entry_ids {
+        let path = entry_path(data_dir, entry_id)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("failed_to_delete_trash_entry: {}", e))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// 清除指定数据目录中超过保留期的条目
+fn purge_expired_in_dir(data_dir: &Path, retention_days: u32, now: i64) -> Result<usize, String> {
+    let expired: Vec<String> = list_entries(data_dir)?
+        .into_iter()
+        .filter(|entry| expires_at(entry.deleted_at, retention_days).is_some_and(|t| t <= now))
+        .map(|entry| entry.id)
+        .collect();
+    purge_entries(data_dir, &expired)
+}
+
+/// 永久删除条目，并清除这些账号的订阅等级记录
+fn purge_entries(data_dir: &Path, entry_ids: &[String]) -> Result<usize, String> {
+    let account_ids: Vec<String> = entry_ids
+        .iter()
+        .filter_map(|id| read_entry(data_dir, id).ok())
+        .map(|entry| entry.account.id)
+        .collect();
+    let purged = remove_entries(data_dir, entry_ids)?;
+    if let Err(e) = crate::modules::tier::remove_records_in_dir(data_dir, &account_ids) {
+        crate::modules::logger::log_warn(&format!("Failed to drop tier records: {}", e));
+    }
+    Ok(purged)
+}
+
+/// 清除超过保留期的条目（保留期为 0 时永久保留）
+pub fn purge_expired(retention_days: u32) -> Result<usize, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    let _data_lock = crate::modules::data_lock::acquire()?;
+    let purged = purge_expired_in_dir(&data_dir, retention_days, chrono::Utc::now().timestamp())?;
+    if purged > 0 {
+        crate::modules::logger::log_info(&format!(
+            "Purged {} expired account(s) from trash (retention {} days)",
+            purged, retention_days
+        ));
+    }
+    Ok(purged)
+}
+
+fn retention_days() -> u32 {
+    crate::modules::config::load_app_config()
+        .map(|config| config.trash_retention_days)
+        .unwrap_or_else(|_| crate::models::config::default_trash_retention_days())
+}
+
+/// 列出回收站中的账号
+pub fn list_trash() -> Result<Vec<TrashItem>, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    let retention = retention_days();
+    Ok(list_entries(&data_dir)?
+        .iter()
+        .map(|entry| TrashItem::new(entry, retention))
+        .collect())
+}
+
+/// 永久删除指定条目；未指定时清空回收站
+pub fn purge_trash(entry_ids: Option<Vec<String>>) -> Result<usize, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    let _data_lock = crate::modules::data_lock::acquire()?;
+    let ids = match entry_ids {
+        Some(ids) => ids,
+        None => list_entries(&data_dir)?.into_iter().map(|e| e.id).collect(),
+    };
+    let purged = purge_entries(&data_dir, &ids)?;
+    crate::modules::logger::log_info(&format!("Purged {} account(s) from trash", purged));
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDataDir;
+    use crate::models::TokenData;
+
+    fn entry(id: &str, deleted_at: i64) -> TrashEntry {
+        TrashEntry {
+            id: id.to_string(),
+            deleted_at,
+            position: Some(1),
+            was_current: false,
+            account: Account::new(
+                format!("acc-{}", id),
+                format!("{}@example.com", id),
+                TokenData::new(
+                    "ag_enc_v2_a".into(),
+                    "ag_enc_v2_r".into(),
+                    0,
+                    None,
+                    None,
+                    None,
+                ),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_entries_roundtrip_and_purge_after_retention() {
+        let dir = TestDataDir::new();
+        let now = 1_700_000_000;
+        write_entry(dir.path(), &entry("old", now - 31 * SECONDS_PER_DAY)).unwrap();
+        write_entry(dir.path(), &entry("new", now - SECONDS_PER_DAY)).unwrap();
+
+        let listed = list_entries(dir.path()).unwrap();
+        let ids: Vec<_> = listed.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["new", "old"]);
+        assert_eq!(
+            read_entry(dir.path(), "old").unwrap().account.email,
+            "old@example.com"
+        );
+
+        // 保留期为 0 表示永久保留
+        assert_eq!(purge_expired_in_dir(dir.path(), 0, now).unwrap(), 0);
+        assert_eq!(purge_expired_in_dir(dir.path(), 30, now).unwrap(), 1);
+        let ids: Vec<_> = list_entries(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec!["new".to_string()]);
+    }
+
+    #[test]
+    fn test_entry_ids_cannot_escape_trash_dir() {
+        let dir = TestDataDir::new();
+        for bad in ["", "../accounts", "a/b", "..\\x", "x.json"] {
+            assert!(read_entry(dir.path(), bad)
+                .unwrap_err()
+                .starts_with("invalid_trash_entry_id"));
+        }
+        assert!(read_entry(dir.path(), "missing")
+            .unwrap_err()
+            .starts_with("trash_entry_not_found"));
+    }
+}
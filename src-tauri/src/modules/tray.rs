@@ -5,6 +5,7 @@ use tauri::{
     Manager, Emitter, Listener,
 };
 use crate::modules;
+use crate::modules::audit::EventSource;
 
 pub fn create_tray(app: &tauri::AppHandle) -> tauri::Result<()> {
     // 1. Load config to get language settings
@@ -72,7 +73,7 @@ pub fn create_tray(app: &tauri::AppHandle) -> tauri::Result<()> {
                 }
                 "refresh_curr" => {
                     // Execute refresh asynchronously
-                    tauri::async_runtime::spawn(async move {
+                    tauri::async_runtime::spawn(modules::audit::with_source(EventSource::Tray, async move {
                         if let Ok(Some(account_id)) = modules::get_current_account_id() {
                              // Notify frontend to start
                              let _ = app_handle.emit("tray://refresh-current", ());
@@ -83,9 +84,12 @@ pub fn create_tray(app: &tauri::AppHandle) -> tauri::Result<()> {
                                  match modules::account::fetch_quota_with_retry(&mut account).await {
                                      Ok(quota) => {
                                          // Save
-                                         let _ = modules::update_account_quota(&account.id, quota);
+                                         let _ = modules::update_account_quota_async(&account.id, quota).await;
                                          // Update tray display
                                          update_tray_menus(&app_handle);
+                                         modules::auto_switch::spawn_pending(
+                                             modules::integration::SystemManager::Desktop(app_handle.clone()),
+                                         );
                                      },
                                      Err(e) => {
                                          // Error handling, log only
@@ -94,35 +98,38 @@ pub fn create_tray(app: &tauri::AppHandle) -> tauri::Result<()> {
                                  }
                              }
                         }
-                    });
+                    }));
                 }
                 "switch_next" => {
-                    tauri::async_runtime::spawn(async move {
-                         // 1. Get all accounts
-                         if let Ok(accounts) = modules::list_accounts() {
-                             if accounts.is_empty() { return; }
-                             
-                             let current_id = modules::get_current_account_id().unwrap_or(None);
-                             let next_account = if let Some(curr) = current_id {
-                                 let idx = accounts.iter().position(|a| a.id == curr).unwrap_or(0);
-                                 let next_idx = (idx + 1) % accounts.len();
-                                 &accounts[next_idx]
-                             } else {
-                                 &accounts[0]
-                             };
-                             
-                             // 2. Switch
-                             let integration = crate::modules::integration::DesktopIntegration {
-                                 app_handle: app_handle.clone(),
-                             };
-                             if let Ok(_) = modules::switch_account(&next_account.id, &integration).await {
-                                 // 3. Notify frontend
-                                 let _ = app_handle.emit("tray://account-switched", next_account.id.clone());
-                                 // 4. Update tray
-                                 update_tray_menus(&app_handle);
+                    tauri::async_runtime::spawn(modules::audit::with_source(EventSource::Tray, async move {
+                         // 1. Pick the best ranked account other than the current one
+                         let next_account = match modules::selection::best_next_account() {
+                             Ok(Some(next)) => next,
+                             Ok(None) => {
+                                 modules::logger::log_warn("Tray switch: no eligible account");
+                                 return;
                              }
+                             Err(e) => {
+                                 modules::logger::log_error(&format!("Tray switch failed: {}", e));
+                                 return;
+                             }
+                         };
+                         modules::logger::log_info(&format!(
+                             "Tray switch to {}: {}",
+                             next_account.email, next_account.explanation
+                         ));
+
+                         // 2. Switch
+                         let integration = crate::modules::integration::DesktopIntegration {
+                             app_handle: app_handle.clone(),
+                         };
+                         if let Ok(_) = modules::switch_account(&next_account.account_id, &integration).await {
+                             // 3. Notify frontend
+                             let _ = app_handle.emit("tray://account-switched", next_account.account_id.clone());
+                             // 4. Update tray
+                             update_tray_menus(&app_handle);
                          }
-                    });
+                    }));
                 }
                 _ => {}
             }
@@ -304,9 +304,8 @@ pub fn should_check_for_updates(settings: &UpdateSettings) -> bool {
 
 /// Load update settings from config file
 pub fn load_update_settings() -> Result<UpdateSettings, String> {
-    let data_dir = crate::modules::account::get_data_dir()
+    let settings_path = crate::modules::paths::update_settings_file()
         .map_err(|e| format!("Failed to get data dir: {}", e))?;
-    let settings_path = data_dir.join("update_settings.json");
 
     if !settings_path.exists() {
         return Ok(UpdateSettings::default());
@@ -321,14 +320,13 @@ pub fn load_update_settings() -> Result<UpdateSettings, String> {
 
 /// Save update settings to config file
 pub fn save_update_settings(settings: &UpdateSettings) -> Result<(), String> {
-    let data_dir = crate::modules::account::get_data_dir()
+    let settings_path = crate::modules::paths::update_settings_file()
         .map_err(|e| format!("Failed to get data dir: {}", e))?;
-    let settings_path = data_dir.join("update_settings.json");
 
     let content = serde_json::to_string_pretty(settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-    std::fs::write(&settings_path, content)
+    crate::utils::atomic_file::write_atomic(&settings_path, content)
         .map_err(|e| format!("Failed to write settings file: {}", e))
 }
 
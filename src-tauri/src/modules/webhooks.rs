@@ -0,0 +1,806 @@
+//! 出站 webhook
+//!
+//! 账号与配额的关键事件以 JSON POST 到 `AppConfig.webhooks` 中配置的地址。配置了密钥时附带
+//! `X-Webhook-Signature: sha256=<hex>`，签名内容为 `<X-Webhook-Timestamp>.<body>`。
+//! - `X-Webhook-Id`：事件 ID，与请求体 `id` 相同，发往多个地址或重试时不变，接收方据此去重
+//! - `X-Webhook-Delivery`：投递 ID，每个地址一条，与投递日志的 `delivery_id` 对应
+//!
+//! 待投递的事件持久化在数据目录的 `webhook_queue.json`，由独立的投递任务按指数退避重试，
+//! 重启后继续；每次尝试的结果追加到 `webhook_deliveries.jsonl`。
+
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::models::config::{WebhookEventKind, WebhooksConfig};
+use crate::models::ProtectionReason;
+use crate::modules::audit::EventSource;
+use crate::modules::logger;
+use crate::utils::{atomic_file, retry};
+
+const QUEUE_FILE: &str = "webhook_queue.json";
+const DELIVERY_LOG_FILE: &str = "webhook_deliveries.jsonl";
+/// 投递日志超过该大小时只保留最近的记录
+const MAX_LOG_BYTES: u64 = 512 * 1024;
+const COMPACT_LOG_LINES: usize = 1000;
+const DEFAULT_LOG_LIMIT: usize = 200;
+/// 投递任务的最长休眠时间，期间由 [`dispatch`] 排入的重试最迟在此后被看到
+const POLL_INTERVAL_SECS: i64 = 60;
+
+/// 串行化队列文件与投递日志的读写
+static QUEUE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+/// 同一时间只有一个投递循环
+static FLUSH_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+/// 写回队列失败后的暂停状态
+static PAUSE: Lazy<Mutex<Pause>> = Lazy::new(|| Mutex::new(Pause::default()));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    AccountDisabled {
+        account_id: String,
+        email: String,
+        reason: String,
+    },
+    ValidationBlocked {
+        account_id: String,
+        email: String,
+        reason: Option<String>,
+        validation_url: Option<String>,
+    },
+    ProtectionEntered {
+        account_id: String,
+        email: String,
+        model: String,
+        reason: ProtectionReason,
+    },
+    ProtectionLeft {
+        account_id: String,
+        email: String,
+        model: String,
+        reason: ProtectionReason,
+    },
+    AccountSwitched {
+        account_id: String,
+        email: String,
+        previous_account_id: Option<String>,
+        source: EventSource,
+    },
+    RefreshFailed {
+        total: usize,
+        success: usize,
+        failed: usize,
+        errors: Vec<String>,
+    },
+}
+
+impl WebhookEvent {
+    pub fn kind(&self) -> WebhookEventKind {
+        match self {
+            WebhookEvent::AccountDisabled { .. } => WebhookEventKind::AccountDisabled,
+            WebhookEvent::ValidationBlocked { .. } => WebhookEventKind::ValidationBlocked,
+            WebhookEvent::ProtectionEntered { .. } => WebhookEventKind::ProtectionEntered,
+            WebhookEvent::ProtectionLeft { .. } => WebhookEventKind::ProtectionLeft,
+            WebhookEvent::AccountSwitched { .. } => WebhookEventKind::AccountSwitched,
+            WebhookEvent::RefreshFailed { .. } => WebhookEventKind::RefreshFailed,
+        }
+    }
+
+    /// 事件名（与请求体 `event` 字段、`X-Webhook-Event` 头一致）
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::AccountDisabled { .. } => "account_disabled",
+            WebhookEvent::ValidationBlocked { .. } => "validation_blocked",
+            WebhookEvent::ProtectionEntered { .. } => "protection_entered",
+            WebhookEvent::ProtectionLeft { .. } => "protection_left",
+            WebhookEvent::AccountSwitched { .. } => "account_switched",
+            WebhookEvent::RefreshFailed { .. } => "refresh_failed",
+        }
+    }
+}
+
+/// 请求体
+#[derive(Serialize)]
+struct Envelope<'a> {
+    /// 事件 ID，同一事件发往多个地址或重试时保持不变，可用于去重
+    id: String,
+    ts: i64,
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+}
+
+/// 队列中等待投递的一条事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingDelivery {
+    /// 投递 ID（`X-Webhook-Delivery`）
+    pub id: String,
+    /// 事件 ID（`X-Webhook-Id`，即请求体 `id`）；旧版队列中没有，从请求体读取
+    #[serde(default)]
+    pub event_id: String,
+    pub url: String,
+    pub event: String,
+    pub body: String,
+    /// 已尝试次数
+    pub attempts: u32,
+    pub created_at: i64,
+    pub next_attempt_at: i64,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryOutcome {
+    Delivered,
+    /// 失败，已排队重试
+    Retrying,
+    /// 不可重试的错误或已用完尝试次数
+    Abandoned,
+}
+
+/// 投递日志中的一条记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryRecord {
+    pub ts: i64,
+    pub delivery_id: String,
+    pub url: String,
+    pub event: String,
+    pub attempt: u32,
+    pub status: Option<u16>,
+    pub outcome: DeliveryOutcome,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// 一次 HTTP 尝试的结果
+#[derive(Debug, Clone)]
+struct Attempt {
+    status: Option<u16>,
+    error: Option<String>,
+    duration_ms: u64,
+}
+
+impl PendingDelivery {
+    fn event_id(&self) -> String {
+        if !self.event_id.is_empty() {
+            return self.event_id.clone();
+        }
+        serde_json::from_str::<serde_json::Value>(&self.body)
+            .ok()
+            .and_then(|body| body["id"].as_str().map(str::to_string))
+            .unwrap_or_else(|| self.id.clone())
+    }
+}
+
+/// 队列写回失败时暂停投递：到期事件仍留在队列里，立即重跑只会重复发送
+#[derive(Debug, Default)]
+struct Pause {
+    failures: u32,
+    until: i64,
+}
+
+impl Pause {
+    fn fail(&mut self, config: &WebhooksConfig, now: i64) {
+        self.failures += 1;
+        self.until = now + backoff_secs(config, self.failures) as i64;
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl Attempt {
+    fn succeeded(&self) -> bool {
+        self.status.is_some_and(|s| (200..300).contains(&s))
+    }
+
+    /// 网络错误、408、429 与 5xx 可以重试
+    fn retryable(&self) -> bool {
+        match self.status {
+            None => true,
+            Some(status) => status == 408 || retry::is_retryable_status(status),
+        }
+    }
+}
+
+fn hmac_hex(secret: &str, message: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(message);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// `X-Webhook-Signature` 的值
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    format!(
+        "sha256={}",
+        hmac_hex(secret, format!("{}.{}", timestamp, body).as_bytes())
+    )
+}
+
+/// 第 `attempts` 次失败后的等待秒数
+fn backoff_secs(config: &WebhooksConfig, attempts: u32) -> u64 {
+    let factor = 2u64.saturating_pow(attempts.saturating_sub(1));
+    config
+        .retry_base_secs
+        .max(1)
+        .saturating_mul(factor)
+        .min(config.retry_max_secs.max(1))
+}
+
+/// 根据一次尝试的结果更新投递状态
+fn apply_attempt(
+    delivery: &mut PendingDelivery,
+    attempt: &Attempt,
+    config: &WebhooksConfig,
+    now: i64,
+) -> DeliveryOutcome {
+    if attempt.succeeded() {
+        return DeliveryOutcome::Delivered;
+    }
+    delivery.last_error = attempt
+        .error
+        .clone()
+        .or_else(|| attempt.status.map(|s| format!("HTTP {}", s)));
+    if !attempt.retryable() || delivery.attempts >= config.max_attempts {
+        return DeliveryOutcome::Abandoned;
+    }
+    delivery.next_attempt_at = now + backoff_secs(config, delivery.attempts) as i64;
+    DeliveryOutcome::Retrying
+}
+
+fn queue_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(QUEUE_FILE)
+}
+
+fn log_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(DELIVERY_LOG_FILE)
+}
+
+fn load_queue(data_dir: &Path) -> Result<Vec<PendingDelivery>, String> {
+    let path = queue_path(data_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("failed_to_read_webhook_queue: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_webhook_queue: {}", e))
+}
+
+fn save_queue(data_dir: &Path, queue: &[PendingDelivery]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(queue)
+        .map_err(|e| format!("failed_to_serialize_webhook_queue: {}", e))?;
+    atomic_file::write_atomic(&queue_path(data_dir), content)
+        .map_err(|e| format!("failed_to_save_webhook_queue: {}", e))
+}
+
+/// 为订阅了该事件的每个地址排入一条投递，返回排入数量
+fn enqueue_in(
+    data_dir: &Path,
+    config: &WebhooksConfig,
+    event: &WebhookEvent,
+    now: i64,
+) -> Result<usize, String> {
+    let urls: Vec<&str> = config
+        .endpoints
+        .iter()
+        .filter(|e| e.subscribes(event.kind()))
+        .map(|e| e.url.as_str())
+        .collect();
+    if urls.is_empty() {
+        return Ok(0);
+    }
+    let event_id = uuid::Uuid::new_v4().to_string();
+    let body = serde_json::to_string(&Envelope {
+        id: event_id.clone(),
+        ts: now,
+        event,
+    })
+    .map_err(|e| format!("failed_to_serialize_webhook_event: {}", e))?;
+
+    let _guard = QUEUE_LOCK.lock();
+    let mut queue = load_queue(data_dir)?;
+    queue.extend(urls.iter().map(|url| PendingDelivery {
+        id: uuid::Uuid::new_v4().to_string(),
+        event_id: event_id.clone(),
+        url: url.to_string(),
+        event: event.name().to_string(),
+        body: body.clone(),
+        attempts: 0,
+        created_at: now,
+        next_attempt_at: now,
+        last_error: None,
+    }));
+    save_queue(data_dir, &queue)?;
+    Ok(urls.len())
+}
+
+/// 按 ID 写回本轮结果：`None` 表示已完成（送达或放弃），从队列移除
+fn settle_in(
+    data_dir: &Path,
+    results: Vec<(String, Option<PendingDelivery>)>,
+) -> Result<(), String> {
+    let _guard = QUEUE_LOCK.lock();
+    let mut queue = load_queue(data_dir)?;
+    for (id, updated) in results {
+        let Some(pos) = queue.iter().position(|d| d.id == id) else {
+            continue;
+        };
+        match updated {
+            Some(delivery) => queue[pos] = delivery,
+            None => {
+                queue.remove(pos);
+            }
+        }
+    }
+    save_queue(data_dir, &queue)
+}
+
+fn append_log_in(data_dir: &Path, record: &DeliveryRecord) -> Result<(), String> {
+    let path = log_path(data_dir);
+    let mut line = serde_json::to_string(record)
+        .map_err(|e| format!("failed_to_serialize_webhook_delivery: {}", e))?;
+    line.push('\n');
+
+    let _guard = QUEUE_LOCK.lock();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("failed_to_open_webhook_log: {}", e))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("failed_to_write_webhook_log: {}", e))?;
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    drop(file);
+
+    if len > MAX_LOG_BYTES {
+        let content =
+            fs::read_to_string(&path).map_err(|e| format!("failed_to_read_webhook_log: {}", e))?;
+        let lines: Vec<&str> = content.lines().collect();
+        let mut kept = lines[lines.len().saturating_sub(COMPACT_LOG_LINES)..].join("\n");
+        kept.push('\n');
+        atomic_file::write_atomic(&path, kept)
+            .map_err(|e| format!("failed_to_compact_webhook_log: {}", e))?;
+    }
+    Ok(())
+}
+
+fn delivery_record(
+    delivery: &PendingDelivery,
+    attempt: Option<&Attempt>,
+    outcome: DeliveryOutcome,
+) -> DeliveryRecord {
+    DeliveryRecord {
+        ts: chrono::Utc::now().timestamp(),
+        delivery_id: delivery.id.clone(),
+        url: delivery.url.clone(),
+        event: delivery.event.clone(),
+        attempt: delivery.attempts,
+        status: attempt.and_then(|a| a.status),
+        outcome,
+        error: delivery.last_error.clone(),
+        duration_ms: attempt.map_or(0, |a| a.duration_ms),
+    }
+}
+
+fn log_delivery(data_dir: &Path, record: DeliveryRecord) {
+    let line = format!(
+        "[Webhook] {} -> {}: {:?} (attempt {}{})",
+        record.event,
+        record.url,
+        record.outcome,
+        record.attempt,
+        record
+            .error
+            .as_deref()
+            .map(|e| format!(", {}", e))
+            .unwrap_or_default()
+    );
+    match record.outcome {
+        DeliveryOutcome::Delivered => logger::log_info(&line),
+        DeliveryOutcome::Retrying => logger::log_warn(&line),
+        DeliveryOutcome::Abandoned => logger::log_error(&line),
+    }
+    if let Err(e) = append_log_in(data_dir, &record) {
+        logger::log_warn(&format!("[Webhook] Failed to write delivery log: {}", e));
+    }
+}
+
+async fn post(
+    url: &str,
+    secret: Option<&str>,
+    event_id: &str,
+    delivery_id: &str,
+    event: &str,
+    body: &str,
+) -> Attempt {
+    let start = std::time::Instant::now();
+    let timestamp = chrono::Utc::now().timestamp();
+    let mut request = crate::utils::http::SHARED_CLIENT
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Id", event_id)
+        .header("X-Webhook-Delivery", delivery_id)
+        .header("X-Webhook-Event", event)
+        .header("X-Webhook-Timestamp", timestamp.to_string());
+    if let Some(secret) = secret.filter(|s| !s.is_empty()) {
+        request = request.header("X-Webhook-Signature", signature(secret, timestamp, body));
+    }
+    let (status, error) = match request.body(body.to_string()).send().await {
+        Ok(response) => (Some(response.status().as_u16()), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    Attempt {
+        status,
+        error,
+        duration_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+/// 投递所有到期的事件
+///
+/// 同一地址按入队顺序投递，某条失败后该地址的后续事件随它一起顺延。
+pub async fn flush_due() {
+    let Ok(data_dir) = crate::modules::account::get_data_dir() else {
+        return;
+    };
+    let _flush = FLUSH_LOCK.lock().await;
+    let config = crate::modules::config::load_app_config()
+        .map(|config| config.webhooks)
+        .unwrap_or_default();
+    let now = chrono::Utc::now().timestamp();
+    if now < PAUSE.lock().until {
+        return;
+    }
+    let due: Vec<PendingDelivery> = {
+        let _guard = QUEUE_LOCK.lock();
+        match load_queue(&data_dir) {
+            Ok(queue) => queue
+                .into_iter()
+                .filter(|d| d.next_attempt_at <= now)
+                .collect(),
+            Err(e) => {
+                logger::log_warn(&format!("[Webhook] {}", e));
+                return;
+            }
+        }
+    };
+    if due.is_empty() {
+        return;
+    }
+
+    let mut blocked: HashMap<String, i64> = HashMap::new();
+    let mut results = Vec::new();
+    for mut delivery in due {
+        // 排在失败投递之后，随它一起顺延
+        if let Some(&next_attempt_at) = blocked.get(&delivery.url) {
+            delivery.next_attempt_at = next_attempt_at;
+            results.push((delivery.id.clone(), Some(delivery)));
+            continue;
+        }
+        // 地址已从配置中移除或停用时放弃剩余投递
+        let Some(endpoint) = config
+            .endpoints
+            .iter()
+            .find(|e| e.enabled && e.url == delivery.url)
+        else {
+            delivery.last_error = Some("endpoint_removed_or_disabled".to_string());
+            log_delivery(
+                &data_dir,
+                delivery_record(&delivery, None, DeliveryOutcome::Abandoned),
+            );
+            results.push((delivery.id, None));
+            continue;
+        };
+
+        delivery.attempts += 1;
+        let attempt = post(
+            &delivery.url,
+            endpoint.secret.as_deref(),
+            &delivery.event_id(),
+            &delivery.id,
+            &delivery.event,
+            &delivery.body,
+        )
+        .await;
+        let outcome = apply_attempt(
+            &mut delivery,
+            &attempt,
+            &config,
+            chrono::Utc::now().timestamp(),
+        );
+        log_delivery(
+            &data_dir,
+            delivery_record(&delivery, Some(&attempt), outcome),
+        );
+        if outcome == DeliveryOutcome::Retrying {
+            blocked.insert(delivery.url.clone(), delivery.next_attempt_at);
+            results.push((delivery.id.clone(), Some(delivery)));
+        } else {
+            results.push((delivery.id, None));
+        }
+    }
+
+    match settle_in(&data_dir, results) {
+        Ok(()) => PAUSE.lock().clear(),
+        Err(e) => {
+            let mut pause = PAUSE.lock();
+            pause.fail(&config, chrono::Utc::now().timestamp());
+            logger::log_warn(&format!(
+                "[Webhook] {}, pausing deliveries for {}s",
+                e,
+                pause.until - now
+            ));
+        }
+    }
+}
+
+/// 队列中最早的下次投递时间（暂停期间不早于暂停结束）
+fn next_attempt_at() -> Option<i64> {
+    let data_dir = crate::modules::account::get_data_dir().ok()?;
+    let next = {
+        let _guard = QUEUE_LOCK.lock();
+        load_queue(&data_dir)
+            .ok()?
+            .iter()
+            .map(|d| d.next_attempt_at)
+            .min()?
+    };
+    Some(next.max(PAUSE.lock().until))
+}
+
+/// 启动投递任务：按队列中最早的下次投递时间重试，与配额调度器互不阻塞
+pub fn start_delivery_loop() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            flush_due().await;
+            let now = chrono::Utc::now().timestamp();
+            let wake_at = next_attempt_at()
+                .unwrap_or(i64::MAX)
+                .min(now + POLL_INTERVAL_SECS);
+            tokio::time::sleep(std::time::Duration::from_secs(
+                wake_at.saturating_sub(now).max(1) as u64,
+            ))
+            .await;
+        }
+    });
+}
+
+/// 排入事件并立即尝试投递；失败只记警告，不影响调用方
+pub fn dispatch(event: WebhookEvent) {
+    let config = crate::modules::config::load_app_config()
+        .map(|config| config.webhooks)
+        .unwrap_or_default();
+    if config.endpoints.is_empty() {
+        return;
+    }
+    let result = crate::modules::account::get_data_dir().and_then(|data_dir| {
+        enqueue_in(&data_dir, &config, &event, chrono::Utc::now().timestamp())
+    });
+    match result {
+        Ok(0) => {}
+        Ok(_) => {
+            tauri::async_runtime::spawn(flush_due());
+        }
+        Err(e) => logger::log_warn(&format!(
+            "[Webhook] Failed to queue {} event: {}",
+            event.name(),
+            e
+        )),
+    }
+}
+
+/// 投递日志（按时间倒序）
+pub fn deliveries(limit: Option<usize>) -> Result<Vec<DeliveryRecord>, String> {
+    let path = log_path(&crate::modules::account::get_data_dir()?);
+    let _guard = QUEUE_LOCK.lock();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("failed_to_read_webhook_log: {}", e))?;
+    Ok(content
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .take(limit.unwrap_or(DEFAULT_LOG_LIMIT))
+        .collect())
+}
+
+/// 向指定地址发送一条 `ping` 事件（不入队、不重试），使用该地址已配置的密钥
+pub async fn send_test(url: &str) -> Result<DeliveryRecord, String> {
+    let config = crate::modules::config::load_app_config()?.webhooks;
+    let secret = config
+        .endpoints
+        .iter()
+        .find(|e| e.url == url)
+        .and_then(|e| e.secret.clone());
+    let id = uuid::Uuid::new_v4().to_string();
+    let body = serde_json::json!({
+        "id": id,
+        "ts": chrono::Utc::now().timestamp(),
+        "event": "ping",
+    })
+    .to_string();
+    let attempt = post(url, secret.as_deref(), &id, &id, "ping", &body).await;
+    let record = DeliveryRecord {
+        ts: chrono::Utc::now().timestamp(),
+        delivery_id: id,
+        url: url.to_string(),
+        event: "ping".to_string(),
+        attempt: 1,
+        status: attempt.status,
+        outcome: if attempt.succeeded() {
+            DeliveryOutcome::Delivered
+        } else {
+            DeliveryOutcome::Abandoned
+        },
+        error: attempt
+            .error
+            .clone()
+            .or_else(|| attempt.status.map(|s| format!("HTTP {}", s))),
+        duration_ms: attempt.duration_ms,
+    };
+    log_delivery(&crate::modules::account::get_data_dir()?, record.clone());
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDataDir;
+    use crate::models::config::WebhookEndpoint;
+
+    fn endpoint(url: &str, events: Vec<WebhookEventKind>, enabled: bool) -> WebhookEndpoint {
+        WebhookEndpoint {
+            url: url.to_string(),
+            secret: None,
+            events,
+            enabled,
+        }
+    }
+
+    fn attempt(status: Option<u16>) -> Attempt {
+        Attempt {
+            status,
+            error: status.is_none().then(|| "connection refused".to_string()),
+            duration_ms: 5,
+        }
+    }
+
+    #[test]
+    fn test_signature() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac_hex("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        let body = r#"{"event":"account_disabled"}"#;
+        let sig = signature("s3cret", 1700000000, body);
+        assert_eq!(
+            sig,
+            format!(
+                "sha256={}",
+                hmac_hex("s3cret", format!("1700000000.{}", body).as_bytes())
+            )
+        );
+        assert_ne!(sig, signature("s3cret", 1700000001, body));
+    }
+
+    #[test]
+    fn test_queue_filters_retries_and_settles() {
+        let dir = TestDataDir::new();
+        let config = WebhooksConfig {
+            endpoints: vec![
+                endpoint("http://chat.local/all", Vec::new(), true),
+                endpoint(
+                    "http://chat.local/switches",
+                    vec![WebhookEventKind::AccountSwitched],
+                    true,
+                ),
+                endpoint("http://chat.local/off", Vec::new(), false),
+            ],
+            max_attempts: 3,
+            retry_base_secs: 30,
+            retry_max_secs: 45,
+        };
+        let event = WebhookEvent::AccountDisabled {
+            account_id: "a1".into(),
+            email: "a1@example.com".into(),
+            reason: "invalid_grant: revoked".into(),
+        };
+        assert_eq!(enqueue_in(dir.path(), &config, &event, 100).unwrap(), 1);
+        let mut queue = load_queue(dir.path()).unwrap();
+        assert_eq!(queue.len(), 1);
+        let body: serde_json::Value = serde_json::from_str(&queue[0].body).unwrap();
+        assert_eq!(body["event"], "account_disabled");
+        assert_eq!(body["reason"], "invalid_grant: revoked");
+        assert_eq!(queue[0].url, "http://chat.local/all");
+
+        // 503 退避重试，间隔翻倍且不超过上限；用完次数后放弃
+        let mut delivery = queue.remove(0);
+        delivery.attempts = 1;
+        assert_eq!(
+            apply_attempt(&mut delivery, &attempt(Some(503)), &config, 200),
+            DeliveryOutcome::Retrying
+        );
+        assert_eq!(delivery.next_attempt_at, 230);
+        assert_eq!(delivery.last_error.as_deref(), Some("HTTP 503"));
+        delivery.attempts = 2;
+        assert_eq!(
+            apply_attempt(&mut delivery, &attempt(None), &config, 300),
+            DeliveryOutcome::Retrying
+        );
+        assert_eq!(delivery.next_attempt_at, 345);
+        delivery.attempts = 3;
+        assert_eq!(
+            apply_attempt(&mut delivery, &attempt(None), &config, 400),
+            DeliveryOutcome::Abandoned
+        );
+        let mut fresh = delivery.clone();
+        fresh.attempts = 1;
+        assert_eq!(
+            apply_attempt(&mut fresh, &attempt(Some(404)), &config, 400),
+            DeliveryOutcome::Abandoned
+        );
+        assert_eq!(
+            apply_attempt(&mut fresh, &attempt(Some(204)), &config, 400),
+            DeliveryOutcome::Delivered
+        );
+
+        // 写回：重试中的更新，完成的移除，期间新入队的保留
+        let id = delivery.id.clone();
+        delivery.next_attempt_at = 999;
+        let switched = WebhookEvent::AccountSwitched {
+            account_id: "a2".into(),
+            email: "a2@example.com".into(),
+            previous_account_id: Some("a1".into()),
+            source: EventSource::Tray,
+        };
+        assert_eq!(enqueue_in(dir.path(), &config, &switched, 500).unwrap(), 2);
+        settle_in(dir.path(), vec![(id.clone(), Some(delivery))]).unwrap();
+        let queue = load_queue(dir.path()).unwrap();
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue[0].next_attempt_at, 999);
+        settle_in(dir.path(), vec![(id, None)]).unwrap();
+        let queue = load_queue(dir.path()).unwrap();
+        assert_eq!(queue.len(), 2);
+        assert!(queue.iter().all(|d| d.event == "account_switched"));
+        // 同一事件发往两个地址：事件 ID 相同且与请求体一致，投递 ID 不同
+        assert_eq!(queue[0].event_id, queue[1].event_id);
+        assert_ne!(queue[0].id, queue[1].id);
+        let body: serde_json::Value = serde_json::from_str(&queue[0].body).unwrap();
+        assert_eq!(body["id"], queue[0].event_id.as_str());
+        let mut legacy = queue[0].clone();
+        legacy.event_id.clear();
+        assert_eq!(legacy.event_id(), queue[0].event_id);
+    }
+
+    #[test]
+    fn test_pause_backs_off_after_failed_write_back() {
+        let config = WebhooksConfig {
+            retry_base_secs: 30,
+            retry_max_secs: 45,
+            ..Default::default()
+        };
+        let mut pause = Pause::default();
+        pause.fail(&config, 100);
+        assert_eq!(pause.until, 130);
+        pause.fail(&config, 130);
+        assert_eq!(pause.until, 175);
+        pause.clear();
+        assert_eq!((pause.failures, pause.until), (0, 0));
+    }
+}
@@ -0,0 +1,267 @@
+//! 持久化文件的原子写入
+//!
+//! 先写入同目录下的临时文件并 fsync，再原子替换目标文件并 fsync 目录。
+//! 写入过程中崩溃只会留下临时文件，目标文件要么是旧版本，要么是完整的新版本。
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// 上一版本备份文件的后缀（<name>.bak）
+pub const BACKUP_SUFFIX: &str = "bak";
+
+#[cfg(test)]
+thread_local! {
+    /// 测试用：在替换目标文件前模拟进程崩溃
+    static CRASH_BEFORE_RENAME: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Atomically replace `path` with `content`
+pub fn write_atomic(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    write_durable(path, content.as_ref(), false, false)
+}
+
+/// Atomically replace `path` with `content`, keeping the previous version as `<name>.bak`
+pub fn write_atomic_with_backup(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    write_durable(path, content.as_ref(), true, false)
+}
+
+/// Atomically replace `path` with `content` readable only by the owner (0600 on Unix)
+///
+/// 临时文件创建时即为 0600，写入内容前不会以默认 umask 权限出现在磁盘上。
+pub fn write_atomic_private(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    write_durable(path, content.as_ref(), false, true)
+}
+
+/// Path of the `.bak` copy kept by `write_atomic_with_backup`
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(BACKUP_SUFFIX);
+    path.with_file_name(name)
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+fn write_durable(path: &Path, content: &[u8], keep_backup: bool, private: bool) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let dir = parent_dir(path);
+    // Use unique temp file name per write to avoid collision
+    let temp_path = dir.join(format!("{}.tmp.{}", file_name.to_string_lossy(), Uuid::new_v4()));
+
+    let result = (|| {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        if private {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&temp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        drop(file);
+
+        // 保留原文件权限（如外部程序的 storage.json）；私有文件始终为 0600
+        if !private {
+            if let Ok(metadata) = fs::metadata(path) {
+                fs::set_permissions(&temp_path, metadata.permissions())?;
+            }
+        }
+
+        if keep_backup && path.exists() {
+            let backup = backup_path(path);
+            fs::copy(path, &backup)?;
+            OpenOptions::new().write(true).open(&backup)?.sync_all()?;
+        }
+
+        #[cfg(test)]
+        if CRASH_BEFORE_RENAME.with(|c| c.get()) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "simulated crash"));
+        }
+
+        replace_file(&temp_path, path)?;
+        sync_dir(dir)
+    })();
+
+    if let Err(e) = result {
+        // 模拟崩溃时保留临时文件，与真实崩溃后的磁盘状态一致
+        #[cfg(test)]
+        if CRASH_BEFORE_RENAME.with(|c| c.get()) {
+            return Err(e);
+        }
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// Flush directory entry changes (rename) to disk
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    // Windows: MOVEFILE_WRITE_THROUGH already flushes the rename
+    Ok(())
+}
+
+/// Platform-specific atomic file replacement
+#[cfg(target_os = "windows")]
+pub fn replace_file(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+
+    type Bool = i32;
+    type Dword = u32;
+
+    #[link(name = "Kernel32")]
+    extern "system" {
+        fn MoveFileExW(lp_existing_file_name: *const u16, lp_new_file_name: *const u16, dw_flags: Dword) -> Bool;
+    }
+
+    let src_wide: Vec<u16> = src
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let dst_wide: Vec<u16> = dst
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+    // MOVEFILE_REPLACE_EXISTING = 0x1
+    // MOVEFILE_WRITE_THROUGH = 0x8
+    const MOVEFILE_REPLACE_EXISTING: u32 = 0x1;
+    const MOVEFILE_WRITE_THROUGH: u32 = 0x8;
+    let flags = MOVEFILE_REPLACE_EXISTING | MOVEFILE_WRITE_THROUGH;
+
+    let result = unsafe { MoveFileExW(src_wide.as_ptr(), dst_wide.as_ptr(), flags) };
+    if result == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Non-Windows: use standard rename
+#[cfg(not(target_os = "windows"))]
+pub fn replace_file(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::rename(src, dst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::test_support::TestDataDir;
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn crash_before_rename<T>(f: impl FnOnce() -> T) -> T {
+        CRASH_BEFORE_RENAME.with(|c| c.set(true));
+        let result = f();
+        CRASH_BEFORE_RENAME.with(|c| c.set(false));
+        result
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_content() {
+        let dir = TestDataDir::new();
+        let path = dir.path().join("gui_config.json");
+
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(entries(dir.path()), vec!["gui_config.json"]);
+    }
+
+    #[test]
+    fn test_interrupted_write_keeps_previous_version() {
+        let dir = TestDataDir::new();
+        let path = dir.path().join("accounts.json");
+        write_atomic(&path, r#"{"version":"2.0"}"#).unwrap();
+
+        let err = crash_before_rename(|| write_atomic(&path, "{\"trunc")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+
+        // 目标文件仍为完整的旧版本，仅遗留一个临时文件
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"version":"2.0"}"#);
+        let leftovers: Vec<_> = entries(dir.path())
+            .into_iter()
+            .filter(|n| n.starts_with("accounts.json.tmp."))
+            .collect();
+        assert_eq!(leftovers.len(), 1);
+
+        // 下一次写入不受遗留临时文件影响
+        write_atomic(&path, "recovered").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "recovered");
+    }
+
+    #[test]
+    fn test_interrupted_first_write_leaves_no_target() {
+        let dir = TestDataDir::new();
+        let path = dir.path().join("update_settings.json");
+
+        assert!(crash_before_rename(|| write_atomic(&path, "{}")).is_err());
+        assert!(!path.exists(), "partial content must never appear at the target path");
+    }
+
+    #[test]
+    fn test_failed_replace_cleans_temp_file() {
+        let dir = TestDataDir::new();
+        // 目标路径是目录，rename 必然失败
+        let path = dir.path().join("storage.json");
+        fs::create_dir_all(path.join("child")).unwrap();
+
+        assert!(write_atomic(&path, "{}").is_err());
+        assert_eq!(entries(dir.path()), vec!["storage.json"]);
+    }
+
+    #[test]
+    fn test_backup_keeps_previous_version() {
+        let dir = TestDataDir::new();
+        let path = dir.path().join("gui_config.json");
+
+        write_atomic_with_backup(&path, "v1").unwrap();
+        assert!(!backup_path(&path).exists(), "no backup for the first version");
+
+        write_atomic_with_backup(&path, "v2").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v2");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "v1");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_write_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TestDataDir::new();
+        let path = dir.path().join("master.key");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_atomic_private(&path, "secret").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "secret");
+    }
+}
@@ -1,24 +1,393 @@
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
     Aes256Gcm, Nonce,
 };
 use base64::{engine::general_purpose, Engine as _};
-use serde::{Deserialize, Deserializer, Serializer};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use rand::RngCore;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use sha2::Digest;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-const FIXED_NONCE: &[u8; 12] = b"antigravsalt";
+/// 旧版 ag_enc_ 格式使用的固定 nonce，仅用于解密历史数据，不再用于加密
+const LEGACY_FIXED_NONCE: &[u8; 12] = b"antigravsalt";
 const ENCRYPTED_PREFIX: &str = "ag_enc_";
+/// 随机 nonce 信封格式: ag_enc_v2_<key_id>:<base64(nonce || ciphertext)>
+const ENVELOPE_V2_PREFIX: &str = "ag_enc_v2_";
+const NONCE_LEN: usize = 12;
 
-/// 生成加密密钥 (基于设备 ID)
-fn get_encryption_key() -> [u8; 32] {
-    // 使用设备唯一标识生成密钥
-    let device_id = machine_uid::get().unwrap_or_else(|_| "default".to_string());
+/// 主密钥覆盖（优先级最高），适用于无法获取 machine_uid 的容器环境
+const ENCRYPTION_KEY_ENV: &str = "ABV_ENCRYPTION_KEY";
+/// machine_uid 不可用且未配置环境变量时，持久化在数据目录中的随机主密钥
+const LOCAL_MASTER_KEY_FILE: &str = "master.key";
+/// 轮换生成的数据密钥（由主密钥包裹后存储）
+const KEY_RING_FILE: &str = "encryption_keys.json";
+
+/// 主密钥来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MasterKeySource {
+    Env,
+    MachineId,
+    LocalKeyFile,
+}
+
+/// 密钥环文件（encryption_keys.json）
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KeyRingFile {
+    active_key_id: Option<String>,
+    #[serde(default)]
+    keys: Vec<StoredKey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredKey {
+    id: String,
+    /// 数据密钥，使用主密钥以 v2 信封加密
+    wrapped_key: String,
+    created_at: i64,
+}
+
+/// 内存中的密钥环：当前加密密钥 + 全部可用于解密的候选密钥
+struct KeyRing {
+    master_source: MasterKeySource,
+    active: [u8; 32],
+    keys: Vec<[u8; 32]>,
+}
+
+impl KeyRing {
+    fn find(&self, kid: &str) -> Option<&[u8; 32]> {
+        self.keys.iter().find(|k| key_id(k) == kid)
+    }
+}
+
+/// 当前密钥状态（供命令层展示）
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyStatus {
+    pub active_key_id: String,
+    pub master_source: MasterKeySource,
+}
+
+/// 密钥环及其所属的数据目录
+type CachedKeyRing = (PathBuf, Arc<KeyRing>);
+
+/// 按数据目录缓存的密钥环，数据目录变化时重新加载
+static KEY_RING: Lazy<RwLock<Option<CachedKeyRing>>> = Lazy::new(|| RwLock::new(None));
+
+fn derive_key(material: &str) -> [u8; 32] {
     let mut key = [0u8; 32];
-    let hash = sha2::Sha256::digest(device_id.as_bytes());
-    key.copy_from_slice(&hash);
+    key.copy_from_slice(&sha2::Sha256::digest(material.as_bytes()));
     key
 }
 
+/// 基于设备 ID 的密钥；machine_uid 获取失败时返回 None（不再静默退化为 "default"）
+fn machine_key() -> Option<[u8; 32]> {
+    match machine_uid::get() {
+        Ok(id) if !id.trim().is_empty() => Some(derive_key(id.trim())),
+        _ => None,
+    }
+}
+
+/// 旧版本在 machine_uid 获取失败时使用的密钥，仅用于解密历史数据
+fn legacy_default_key() -> [u8; 32] {
+    derive_key("default")
+}
+
+/// 密钥标识：密钥 SHA-256 指纹的前 8 位十六进制，写入密文头部，
+/// 解密时据此选择密钥，支持密钥轮换
+fn key_id(key: &[u8; 32]) -> String {
+    let digest = sha2::Sha256::digest(key);
+    digest[..4].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn write_private_file(path: &Path, content: &[u8]) -> Result<(), String> {
+    super::atomic_file::write_atomic_private(path, content)
+        .map_err(|e| format!("failed_to_write_key_file: {}", e))
+}
+
+/// 读取或创建数据目录中的随机主密钥
+fn load_or_create_local_master_key(data_dir: &Path) -> Result<[u8; 32], String> {
+    let path = data_dir.join(LOCAL_MASTER_KEY_FILE);
+    if path.exists() {
+        let content =
+            fs::read_to_string(&path).map_err(|e| format!("failed_to_read_master_key: {}", e))?;
+        let bytes = general_purpose::STANDARD
+            .decode(content.trim())
+            .map_err(|e| format!("invalid_master_key_file: {}", e))?;
+        if bytes.len() != 32 {
+            return Err("invalid_master_key_length".to_string());
+        }
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&bytes);
+        return Ok(key);
+    }
+
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    write_private_file(&path, general_purpose::STANDARD.encode(key).as_bytes())?;
+    crate::modules::logger::log_warn(&format!(
+        "machine_uid unavailable, generated local master key at {:?}. Set {} to keep secrets recoverable outside this directory.",
+        path, ENCRYPTION_KEY_ENV
+    ));
+    Ok(key)
+}
+
+/// 主密钥解析顺序：环境变量 > machine_uid > 数据目录中的本地密钥文件
+fn resolve_master_key(data_dir: &Path) -> Result<([u8; 32], MasterKeySource), String> {
+    if let Ok(value) = std::env::var(ENCRYPTION_KEY_ENV) {
+        if !value.trim().is_empty() {
+            return Ok((derive_key(value.trim()), MasterKeySource::Env));
+        }
+    }
+    if let Some(key) = machine_key() {
+        return Ok((key, MasterKeySource::MachineId));
+    }
+    load_or_create_local_master_key(data_dir).map(|key| (key, MasterKeySource::LocalKeyFile))
+}
+
+fn read_key_ring_file(data_dir: &Path) -> Result<KeyRingFile, String> {
+    let path = data_dir.join(KEY_RING_FILE);
+    if !path.exists() {
+        return Ok(KeyRingFile::default());
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("failed_to_read_key_ring: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_key_ring: {}", e))
+}
+
+fn write_key_ring_file(data_dir: &Path, file: &KeyRingFile) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(file)
+        .map_err(|e| format!("failed_to_serialize_key_ring: {}", e))?;
+    write_private_file(&data_dir.join(KEY_RING_FILE), content.as_bytes())
+}
+
+/// 用给定的主密钥加载指定数据目录中的密钥环
+fn load_key_ring_in_dir(
+    data_dir: &Path,
+    master: [u8; 32],
+    master_source: MasterKeySource,
+) -> Result<KeyRing, String> {
+    let mut keys = vec![master];
+    let mut active = master;
+
+    let file = read_key_ring_file(data_dir)?;
+    for stored in &file.keys {
+        let unwrapped = open_envelope(&stored.wrapped_key, &[master]).and_then(|bytes| {
+            <[u8; 32]>::try_from(bytes.as_slice()).map_err(|_| "invalid_data_key_length".to_string())
+        });
+        match unwrapped {
+            Ok(key) => {
+                if file.active_key_id.as_deref() == Some(stored.id.as_str()) {
+                    active = key;
+                }
+                keys.push(key);
+            }
+            // 活动密钥无法解包时拒绝回退到主密钥，避免新数据用错误的密钥加密
+            Err(e) if file.active_key_id.as_deref() == Some(stored.id.as_str()) => {
+                return Err(format!(
+                    "failed_to_unwrap_active_data_key: {} (master key changed?): {}",
+                    stored.id, e
+                ));
+            }
+            Err(e) => crate::modules::logger::log_error(&format!(
+                "Failed to unwrap data key {} (master key changed?): {}",
+                stored.id, e
+            )),
+        }
+    }
+
+    // 历史数据兼容：设备密钥与旧版 "default" 密钥仅作为解密候选
+    if let Some(machine) = machine_key() {
+        if !keys.contains(&machine) {
+            keys.push(machine);
+        }
+    }
+    let legacy = legacy_default_key();
+    if !keys.contains(&legacy) {
+        keys.push(legacy);
+    }
+
+    Ok(KeyRing {
+        master_source,
+        active,
+        keys,
+    })
+}
+
+/// 在指定数据目录中生成新的数据密钥并设为当前密钥
+fn rotate_key_in_dir(data_dir: &Path, master: [u8; 32]) -> Result<String, String> {
+    let mut file = read_key_ring_file(data_dir)?;
+
+    let mut data_key = [0u8; 32];
+    OsRng.fill_bytes(&mut data_key);
+    let kid = key_id(&data_key);
+
+    file.keys.push(StoredKey {
+        id: kid.clone(),
+        wrapped_key: seal_envelope(&master, &data_key)?,
+        created_at: chrono::Utc::now().timestamp(),
+    });
+    file.active_key_id = Some(kid.clone());
+    write_key_ring_file(data_dir, &file)?;
+
+    Ok(kid)
+}
+
+/// 删除除当前密钥外的所有数据密钥
+fn retire_inactive_keys_in_dir(data_dir: &Path) -> Result<usize, String> {
+    let mut file = read_key_ring_file(data_dir)?;
+    let before = file.keys.len();
+    let active = file.active_key_id.clone();
+    file.keys.retain(|k| Some(&k.id) == active.as_ref());
+    let removed = before - file.keys.len();
+    if removed > 0 {
+        write_key_ring_file(data_dir, &file)?;
+    }
+    Ok(removed)
+}
+
+fn key_ring() -> Result<Arc<KeyRing>, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    if let Some((dir, ring)) = KEY_RING.read().as_ref() {
+        if *dir == data_dir {
+            return Ok(ring.clone());
+        }
+    }
+
+    let (master, source) = resolve_master_key(&data_dir)?;
+    let ring = Arc::new(load_key_ring_in_dir(&data_dir, master, source)?);
+    *KEY_RING.write() = Some((data_dir, ring.clone()));
+    Ok(ring)
+}
+
+fn reset_key_ring() {
+    *KEY_RING.write() = None;
+}
+
+fn seal_envelope(key: &[u8; 32], plaintext: &[u8]) -> Result<String, String> {
+    let cipher = Aes256Gcm::new(key.into());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let header = format!("{}{}", ENVELOPE_V2_PREFIX, key_id(key));
+
+    // 密钥头作为 AAD 参与认证，防止篡改密钥标识
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: header.as_bytes(),
+            },
+        )
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+
+    let mut blob = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+
+    Ok(format!("{}:{}", header, general_purpose::STANDARD.encode(blob)))
+}
+
+fn open_envelope(value: &str, keys: &[[u8; 32]]) -> Result<Vec<u8>, String> {
+    let body = value
+        .strip_prefix(ENVELOPE_V2_PREFIX)
+        .ok_or("Not a v2 encrypted value")?;
+    let (kid, encoded) = body.split_once(':').ok_or("Malformed encryption header")?;
+    let key = keys
+        .iter()
+        .find(|k| key_id(k) == kid)
+        .ok_or_else(|| format!("Unknown encryption key: {}", kid))?;
+
+    let blob = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Base64 decode failed: {}", e))?;
+    if blob.len() <= NONCE_LEN {
+        return Err("Ciphertext too short".to_string());
+    }
+    let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+
+    let cipher = Aes256Gcm::new(key.into());
+    let header = format!("{}{}", ENVELOPE_V2_PREFIX, kid);
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header.as_bytes(),
+            },
+        )
+        .map_err(|e| format!("Decryption failed: {}", e))
+}
+
+/// 判断字符串是否为 v2 信封格式的密文
+pub fn is_encrypted_secret(value: &str) -> bool {
+    value.starts_with(ENVELOPE_V2_PREFIX)
+}
+
+/// 是否需要重新加密：明文、旧版格式或非当前密钥加密的数据
+pub fn needs_reencryption(value: &str) -> bool {
+    if value.is_empty() {
+        return false;
+    }
+    let Some(body) = value.strip_prefix(ENVELOPE_V2_PREFIX) else {
+        return true;
+    };
+    match (body.split_once(':'), key_ring()) {
+        (Some((kid, _)), Ok(ring)) => kid != key_id(&ring.active),
+        _ => true,
+    }
+}
+
+/// 使用当前密钥和随机 nonce 加密敏感数据（如 refresh_token），输出带密钥头的 v2 信封
+pub fn encrypt_secret(plaintext: &str) -> Result<String, String> {
+    let ring = key_ring()?;
+    seal_envelope(&ring.active, plaintext.as_bytes())
+}
+
+/// 解密 v2 信封格式的密文
+pub fn decrypt_secret(value: &str) -> Result<String, String> {
+    let ring = key_ring()?;
+    let body = value
+        .strip_prefix(ENVELOPE_V2_PREFIX)
+        .ok_or("Not a v2 encrypted value")?;
+    let kid = body.split_once(':').map(|(kid, _)| kid).unwrap_or_default();
+    if ring.find(kid).is_none() {
+        return Err(format!("Unknown encryption key: {}", kid));
+    }
+    let plaintext = open_envelope(value, &ring.keys)?;
+    String::from_utf8(plaintext).map_err(|e| format!("UTF-8 conversion failed: {}", e))
+}
+
+/// 生成新的数据密钥并设为当前加密密钥。
+/// 旧密钥保留用于解密，调用方在重新加密全部数据后应调用 `retire_inactive_keys`。
+pub fn rotate_encryption_key() -> Result<String, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    let (master, _) = resolve_master_key(&data_dir)?;
+    let kid = rotate_key_in_dir(&data_dir, master)?;
+    reset_key_ring();
+    crate::modules::logger::log_info(&format!("Encryption key rotated, active key: {}", kid));
+    Ok(kid)
+}
+
+/// 移除除当前密钥外的所有数据密钥
+pub fn retire_inactive_keys() -> Result<usize, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    let removed = retire_inactive_keys_in_dir(&data_dir)?;
+    reset_key_ring();
+    Ok(removed)
+}
+
+/// 当前密钥状态
+pub fn key_status() -> Result<KeyStatus, String> {
+    let ring = key_ring()?;
+    Ok(KeyStatus {
+        active_key_id: key_id(&ring.active),
+        master_source: ring.master_source,
+    })
+}
+
 pub fn serialize_password<S>(password: &str, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -41,11 +410,9 @@ where
         return Ok(raw);
     }
 
-    // [FIX #1738] 检查魔术前缀
+    // [FIX #1738] 检查魔术前缀（同时覆盖 v2 与旧版 ag_enc_ 格式）
     if raw.starts_with(ENCRYPTED_PREFIX) {
-        // 新版格式：去前缀后解密
-        let ciphertext = &raw[ENCRYPTED_PREFIX.len()..];
-        match decrypt_string_internal(ciphertext) {
+        match decrypt_string(&raw) {
             Ok(plaintext) => Ok(plaintext),
             Err(_) => {
                 // 解密失败（如密钥变更），返回原始密文以防止数据丢失
@@ -54,10 +421,10 @@ where
         }
     } else {
         // 兼容旧版：尝试直接解密
-        match decrypt_string_internal(&raw) {
+        match decrypt_legacy(&raw) {
             Ok(plaintext) => {
                 // 只有当解密出有效的 UTF-8 且看起来像合理个字符串时才认为是旧版密文
-                // 这里 decrypt_string_internal 已经保证了 UTF-8，
+                // 这里 decrypt_legacy 已经保证了 UTF-8，
                 // 如果是用户输入的明文，通常解密会失败（Base64 错误或 Tag 校验错误）。
                 Ok(plaintext)
             }
@@ -69,71 +436,106 @@ where
     }
 }
 
+/// 加密字符串，输出 v2 信封格式（随机 nonce + 密钥头）
 pub fn encrypt_string(password: &str) -> Result<String, String> {
-    let key = get_encryption_key();
-    let cipher = Aes256Gcm::new(&key.into());
-    // In production, we should use a random nonce and prepend it to the ciphertext
-    // For simplicity in this demo, we use a fixed nonce (NOT SECURE for repeats)
-    // improving security: use random nonce
-    let nonce = Nonce::from_slice(FIXED_NONCE);
-
-    let ciphertext = cipher
-        .encrypt(nonce, password.as_bytes())
-        .map_err(|e| format!("Encryption failed: {}", e))?;
-
-    let base64_ciphertext = general_purpose::STANDARD.encode(ciphertext);
-    // [FIX #1738] 添加魔术前缀
-    Ok(format!("{}{}", ENCRYPTED_PREFIX, base64_ciphertext))
+    encrypt_secret(password)
 }
 
-/// 内部解密函数 (输入必须是纯 Base64 密文，不含前缀)
-fn decrypt_string_internal(encrypted_base64: &str) -> Result<String, String> {
-    let key = get_encryption_key();
-    let cipher = Aes256Gcm::new(&key.into());
-    let nonce = Nonce::from_slice(FIXED_NONCE);
+/// 旧版解密 (输入必须是纯 Base64 密文，不含前缀；固定 nonce，依次尝试所有候选密钥)
+fn decrypt_legacy(encrypted_base64: &str) -> Result<String, String> {
+    let ring = key_ring()?;
+    let nonce = Nonce::from_slice(LEGACY_FIXED_NONCE);
 
     let ciphertext = general_purpose::STANDARD
         .decode(encrypted_base64)
         .map_err(|e| format!("Base64 decode failed: {}", e))?;
 
-    let plaintext = cipher
-        .decrypt(nonce, ciphertext.as_ref())
-        .map_err(|e| format!("Decryption failed: {}", e))?;
+    let plaintext = ring
+        .keys
+        .iter()
+        .find_map(|key| Aes256Gcm::new(key.into()).decrypt(nonce, ciphertext.as_ref()).ok())
+        .ok_or_else(|| "Decryption failed: no matching key".to_string())?;
 
     String::from_utf8(plaintext).map_err(|e| format!("UTF-8 conversion failed: {}", e))
 }
 
+/// 解密任意受支持的格式：ag_enc_v2_（当前）、ag_enc_（旧版固定 nonce）及无前缀旧版密文
 pub fn decrypt_string(encrypted: &str) -> Result<String, String> {
-    if encrypted.starts_with(ENCRYPTED_PREFIX) {
-        decrypt_string_internal(&encrypted[ENCRYPTED_PREFIX.len()..])
+    if is_encrypted_secret(encrypted) {
+        decrypt_secret(encrypted)
+    } else if let Some(legacy) = encrypted.strip_prefix(ENCRYPTED_PREFIX) {
+        decrypt_legacy(legacy)
     } else {
-        decrypt_string_internal(encrypted)
+        decrypt_legacy(encrypted)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::modules::test_support::{EnvOverride, TestDataDir};
 
     #[test]
     fn test_encrypt_decrypt_cycle() {
+        // 密钥环按环境变量定位数据目录与主密钥，不能碰真实数据目录
+        let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
         let password = "my_secret_password";
         let encrypted = encrypt_string(password).unwrap();
-        
-        assert!(encrypted.starts_with(ENCRYPTED_PREFIX));
+
+        assert!(encrypted.starts_with(ENVELOPE_V2_PREFIX));
         assert_ne!(password, encrypted);
 
         let decrypted = decrypt_string(&encrypted).unwrap();
         assert_eq!(password, decrypted);
     }
 
+    #[test]
+    fn test_secret_envelope_roundtrip() {
+        let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
+        let secret = "1//0g-refresh-token";
+        let first = encrypt_secret(secret).unwrap();
+        let second = encrypt_secret(secret).unwrap();
+
+        assert!(is_encrypted_secret(&first));
+        // 随机 nonce：相同明文两次加密结果不同
+        assert_ne!(first, second);
+        assert_eq!(decrypt_secret(&first).unwrap(), secret);
+        assert_eq!(decrypt_secret(&second).unwrap(), secret);
+        assert!(!needs_reencryption(&first));
+        assert!(needs_reencryption(secret));
+    }
+
+    #[test]
+    fn test_secret_envelope_rejects_tampering() {
+        let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
+        let encrypted = encrypt_secret("secret").unwrap();
+
+        // 篡改密钥头
+        let (_, body) = encrypted.split_once(':').unwrap();
+        let forged = format!("{}deadbeef:{}", ENVELOPE_V2_PREFIX, body);
+        assert!(decrypt_secret(&forged).is_err());
+
+        // 篡改密文
+        let mut blob = general_purpose::STANDARD.decode(body).unwrap();
+        let last = blob.len() - 1;
+        blob[last] ^= 0x01;
+        let (header, _) = encrypted.split_once(':').unwrap();
+        let tampered = format!("{}:{}", header, general_purpose::STANDARD.encode(blob));
+        assert!(decrypt_secret(&tampered).is_err());
+    }
+
     #[test]
     fn test_legacy_compatibility() {
-        // 模拟旧版加密（手动调用内部逻辑生成无前缀密文）
+        let dir = TestDataDir::new();
+        let _env = EnvOverride::data_dir(&dir);
+        // 模拟旧版加密（固定 nonce，ag_enc_ 前缀与无前缀两种形式）
         let password = "legacy_password";
-        let key = get_encryption_key();
+        let key = machine_key().unwrap_or_else(legacy_default_key);
         let cipher = Aes256Gcm::new(&key.into());
-        let nonce = Nonce::from_slice(FIXED_NONCE);
+        let nonce = Nonce::from_slice(LEGACY_FIXED_NONCE);
         let ciphertext = cipher.encrypt(nonce, password.as_bytes()).unwrap();
         let legacy_encrypted = general_purpose::STANDARD.encode(ciphertext);
 
@@ -142,5 +544,52 @@ mod tests {
         // 使用新版解密逻辑
         let decrypted = decrypt_string(&legacy_encrypted).unwrap();
         assert_eq!(password, decrypted);
+
+        let prefixed = format!("{}{}", ENCRYPTED_PREFIX, legacy_encrypted);
+        assert_eq!(decrypt_string(&prefixed).unwrap(), password);
+        assert!(needs_reencryption(&prefixed));
+    }
+
+    #[test]
+    fn test_key_rotation_in_dir() {
+        let dir = TestDataDir::new();
+        let master = derive_key("test-master");
+
+        let before = load_key_ring_in_dir(dir.path(), master, MasterKeySource::Env).unwrap();
+        assert_eq!(before.active, master);
+        let old_secret = seal_envelope(&before.active, b"rotate-me").unwrap();
+
+        let kid = rotate_key_in_dir(dir.path(), master).unwrap();
+        let after = load_key_ring_in_dir(dir.path(), master, MasterKeySource::Env).unwrap();
+        assert_eq!(key_id(&after.active), kid);
+        assert_ne!(after.active, master);
+
+        // 旧密文仍可解密，新密文使用新密钥
+        assert_eq!(open_envelope(&old_secret, &after.keys).unwrap(), b"rotate-me");
+        let new_secret = seal_envelope(&after.active, b"rotate-me").unwrap();
+        assert!(new_secret.starts_with(&format!("{}{}:", ENVELOPE_V2_PREFIX, kid)));
+
+        // 二次轮换后清理旧数据密钥
+        let second = rotate_key_in_dir(dir.path(), master).unwrap();
+        assert_eq!(retire_inactive_keys_in_dir(dir.path()).unwrap(), 1);
+        let file = read_key_ring_file(dir.path()).unwrap();
+        assert_eq!(file.keys.len(), 1);
+        assert_eq!(file.active_key_id.as_deref(), Some(second.as_str()));
+
+        // 主密钥不匹配时无法解包活动数据密钥，直接报错而不是回退
+        let wrong = load_key_ring_in_dir(dir.path(), derive_key("other"), MasterKeySource::Env);
+        assert!(wrong
+            .err()
+            .unwrap()
+            .starts_with("failed_to_unwrap_active_data_key"));
+    }
+
+    #[test]
+    fn test_local_master_key_is_persisted() {
+        let dir = TestDataDir::new();
+        let first = load_or_create_local_master_key(dir.path()).unwrap();
+        let second = load_or_create_local_master_key(dir.path()).unwrap();
+        assert_eq!(first, second);
+        assert_ne!(first, legacy_default_key());
     }
 }
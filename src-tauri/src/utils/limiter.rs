@@ -0,0 +1,162 @@
+//! 自适应并发限制（AIMD）
+//!
+//! 成功时加性增长（每累计 `limit` 次成功上限加一），遇到限流时乘性减半。
+//! 两次减半至少间隔 `DECREASE_INTERVAL`，避免同一波 429 把并发直接压到下限。
+
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+const DECREASE_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+struct LimiterState {
+    limit: usize,
+    min: usize,
+    max: usize,
+    in_flight: usize,
+    successes: usize,
+    last_decrease: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct AdaptiveLimiter {
+    state: Mutex<LimiterState>,
+    notify: Notify,
+}
+
+/// 持有期间占用一个并发名额
+pub struct LimiterPermit {
+    limiter: Arc<AdaptiveLimiter>,
+}
+
+impl Drop for LimiterPermit {
+    fn drop(&mut self) {
+        self.limiter.state.lock().in_flight -= 1;
+        self.limiter.notify.notify_waiters();
+    }
+}
+
+impl AdaptiveLimiter {
+    /// 以上限作为初始并发
+    pub fn new(min: usize, max: usize) -> Arc<Self> {
+        let max = max.max(1);
+        Arc::new(Self {
+            state: Mutex::new(LimiterState {
+                limit: max,
+                min: min.clamp(1, max),
+                max,
+                in_flight: 0,
+                successes: 0,
+                last_decrease: None,
+            }),
+            notify: Notify::new(),
+        })
+    }
+
+    /// 配置变化时更新上下限，当前并发随之收敛到新区间
+    pub fn set_bounds(&self, min: usize, max: usize) {
+        let max = max.max(1);
+        {
+            let mut state = self.state.lock();
+            state.max = max;
+            state.min = min.clamp(1, max);
+            state.limit = state.limit.clamp(state.min, state.max);
+        }
+        self.notify.notify_waiters();
+    }
+
+    pub fn limit(&self) -> usize {
+        self.state.lock().limit
+    }
+
+    pub async fn acquire(self: &Arc<Self>) -> LimiterPermit {
+        loop {
+            // 先登记等待再检查名额，避免错过释放通知
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut state = self.state.lock();
+                if state.in_flight < state.limit {
+                    state.in_flight += 1;
+                    return LimiterPermit {
+                        limiter: self.clone(),
+                    };
+                }
+            }
+            notified.await;
+        }
+    }
+
+    pub fn on_success(&self) {
+        let grown = {
+            let mut state = self.state.lock();
+            state.successes += 1;
+            if state.successes < state.limit || state.limit >= state.max {
+                false
+            } else {
+                state.successes = 0;
+                state.limit += 1;
+                true
+            }
+        };
+        if grown {
+            self.notify.notify_waiters();
+        }
+    }
+
+    /// 遇到 429/5xx，返回调整后的并发（本次未调整时为 None）
+    pub fn on_throttle(&self) -> Option<usize> {
+        self.throttle_at(Instant::now())
+    }
+
+    fn throttle_at(&self, now: Instant) -> Option<usize> {
+        let mut state = self.state.lock();
+        if state
+            .last_decrease
+            .is_some_and(|at| now.duration_since(at) < DECREASE_INTERVAL)
+        {
+            return None;
+        }
+        state.limit = (state.limit / 2).max(state.min);
+        state.successes = 0;
+        state.last_decrease = Some(now);
+        Some(state.limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aimd() {
+        let limiter = AdaptiveLimiter::new(2, 8);
+        assert_eq!(limiter.limit(), 8);
+        // 已在上限时成功不再增长
+        limiter.on_success();
+        assert_eq!(limiter.limit(), 8);
+
+        let start = Instant::now();
+        assert_eq!(limiter.throttle_at(start), Some(4));
+        // 同一波限流只减半一次
+        assert_eq!(limiter.throttle_at(start + Duration::from_secs(1)), None);
+        assert_eq!(limiter.throttle_at(start + DECREASE_INTERVAL), Some(2));
+        assert_eq!(limiter.throttle_at(start + DECREASE_INTERVAL * 2), Some(2));
+
+        // 每累计 limit 次成功加一
+        limiter.on_success();
+        assert_eq!(limiter.limit(), 2);
+        limiter.on_success();
+        assert_eq!(limiter.limit(), 3);
+        for _ in 0..3 {
+            limiter.on_success();
+        }
+        assert_eq!(limiter.limit(), 4);
+
+        limiter.set_bounds(1, 3);
+        assert_eq!(limiter.limit(), 3);
+    }
+}
@@ -1,3 +1,6 @@
 pub mod http;
 pub mod protobuf;
 pub mod crypto;
+pub mod atomic_file;
+pub mod retry;
+pub mod limiter;
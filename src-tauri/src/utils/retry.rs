@@ -0,0 +1,399 @@
+//! 上游请求的重试与熔断
+//!
+//! - 指数退避 + 全抖动（full jitter）；服务端给出 `Retry-After` 时以其为准，均不超过 `max_delay`
+//! - 只重试网络错误、429 和 5xx，其余 4xx（401/403 等）直接交给调用方处理
+//! - 按 host 熔断：网络错误与 5xx 连续达到阈值后在冷却期内直接拒绝，冷却结束放行一次试探请求；
+//!   429 只是单个账号被限流，不计入熔断（批量刷新的并发由 `AdaptiveLimiter` 调整）
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rand::Rng;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::error::AppError;
+use crate::models::config::RefreshPolicyConfig;
+
+/// 按 host 记录的熔断状态
+static BREAKERS: Lazy<Mutex<HashMap<String, Breaker>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+tokio::task_local! {
+    /// 当前 [`count_throttles`] 作用域内收到的 429/5xx 次数
+    static THROTTLES: Cell<u32>;
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 总尝试次数（含首次）
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &RefreshPolicyConfig) -> Self {
+        Self {
+            max_attempts: config.max_retries + 1,
+            base_delay: Duration::from_millis(config.base_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms),
+            breaker_threshold: config.breaker_threshold.max(1),
+            breaker_cooldown: Duration::from_secs(config.breaker_cooldown_secs),
+        }
+    }
+
+    /// 从应用配置读取策略，读取失败时使用默认值
+    pub fn load() -> Self {
+        let config = crate::modules::config::load_app_config()
+            .map(|config| config.refresh_policy)
+            .unwrap_or_default();
+        Self::from_config(&config)
+    }
+
+    /// 第 `attempt` 次（从 1 开始）失败后的退避上限
+    fn backoff_cap(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// 第 `attempt` 次失败后的等待时间
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max_delay);
+        }
+        let cap = self.backoff_cap(attempt).as_millis() as u64;
+        Duration::from_millis(rand::thread_rng().gen_range(0..=cap))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::from_config(&RefreshPolicyConfig::default())
+    }
+}
+
+/// 429 与 5xx 可以重试，其余状态码重试也不会有不同结果
+pub fn is_retryable_status(status: u16) -> bool {
+    status == 429 || trips_breaker(status)
+}
+
+/// 只有 5xx 说明 host 本身有问题；429 时 host 仍在正常响应
+fn trips_breaker(status: u16) -> bool {
+    (500..600).contains(&status)
+}
+
+/// 解析 `Retry-After`：秒数或 HTTP 日期
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&Utc) - now).to_std().unwrap_or_default())
+}
+
+/// 执行异步任务并返回其间（仅本任务内）的请求收到的 429/5xx 次数
+///
+/// 批量刷新据此调整并发；并发执行的其他任务互不影响。
+pub async fn count_throttles<F: Future>(fut: F) -> (F::Output, u32) {
+    THROTTLES
+        .scope(Cell::new(0), async {
+            let output = fut.await;
+            (output, THROTTLES.with(Cell::get))
+        })
+        .await
+}
+
+fn note_throttles(count: u32) {
+    if count > 0 {
+        let _ = THROTTLES.try_with(|c| c.set(c.get() + count));
+    }
+}
+
+/// URL 中的 host（含非默认端口），用作熔断的键
+pub fn host_of(url: &str) -> String {
+    let Ok(parsed) = url::Url::parse(url) else {
+        return url.to_string();
+    };
+    match (parsed.host_str(), parsed.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => url.to_string(),
+    }
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>,
+    /// 冷却结束后放行试探请求的时间；试探被取消时超过一个冷却期再放行下一次
+    probing_since: Option<Instant>,
+}
+
+impl Breaker {
+    /// 是否放行请求；拒绝时返回建议等待时间
+    fn allow(&mut self, now: Instant, cooldown: Duration) -> Result<(), Duration> {
+        match self.open_until {
+            Some(until) if now < until => Err(until - now),
+            Some(_) => match self.probing_since {
+                Some(since) if now < since + cooldown => Err(since + cooldown - now),
+                _ => {
+                    self.probing_since = Some(now);
+                    Ok(())
+                }
+            },
+            None => Ok(()),
+        }
+    }
+
+    fn on_success(&mut self) {
+        *self = Breaker::default();
+    }
+
+    /// 记录一次失败，返回是否（重新）进入熔断
+    fn on_failure(&mut self, now: Instant, threshold: u32, cooldown: Duration) -> bool {
+        self.failures += 1;
+        self.probing_since = None;
+        if self.open_until.is_some() || self.failures >= threshold {
+            self.open_until = Some(now + cooldown);
+            return true;
+        }
+        false
+    }
+}
+
+fn record_success(host: &str) {
+    if let Some(breaker) = BREAKERS.lock().get_mut(host) {
+        breaker.on_success();
+    }
+}
+
+fn record_failure(host: &str, policy: &RetryPolicy) {
+    let opened = BREAKERS
+        .lock()
+        .entry(host.to_string())
+        .or_default()
+        .on_failure(
+            Instant::now(),
+            policy.breaker_threshold,
+            policy.breaker_cooldown,
+        );
+    if opened {
+        crate::modules::logger::log_warn(&format!(
+            "[Retry] Circuit open for {} ({}s)",
+            host,
+            policy.breaker_cooldown.as_secs()
+        ));
+    }
+}
+
+/// 按策略发送请求
+///
+/// 返回最终响应（可能是不再重试的错误状态，由调用方处理）；
+/// 网络错误重试耗尽或 host 处于熔断时返回 Err。
+/// 本次请求收到的 429/5xx 次数计入外层的 [`count_throttles`]。
+pub async fn send_with_retry<F, Fut>(
+    host: &str,
+    policy: &RetryPolicy,
+    send: F,
+) -> Result<rquest::Response, AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<rquest::Response, rquest::Error>>,
+{
+    let (result, throttled) = send_counted(host, policy, send).await;
+    note_throttles(throttled);
+    result
+}
+
+/// [`send_with_retry`] 的实现，同时返回本次请求收到的 429/5xx 次数
+async fn send_counted<F, Fut>(
+    host: &str,
+    policy: &RetryPolicy,
+    mut send: F,
+) -> (Result<rquest::Response, AppError>, u32)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<rquest::Response, rquest::Error>>,
+{
+    let mut attempt = 0;
+    let mut throttled = 0;
+    loop {
+        attempt += 1;
+        let allowed = BREAKERS
+            .lock()
+            .entry(host.to_string())
+            .or_default()
+            .allow(Instant::now(), policy.breaker_cooldown);
+        if let Err(wait) = allowed {
+            let error = AppError::Network(
+                format!("circuit_open: {} (retry in {}s)", host, wait.as_secs()),
+                None,
+            );
+            return (Err(error), throttled);
+        }
+
+        let (error, retry_after) = match send().await {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                if status >= 400 {
+                    crate::modules::metrics::record_api_error(host, Some(status));
+                }
+                if trips_breaker(status) {
+                    record_failure(host, policy);
+                } else {
+                    record_success(host);
+                }
+                if !is_retryable_status(status) {
+                    return (Ok(response), throttled);
+                }
+                throttled += 1;
+                if attempt >= policy.max_attempts {
+                    return (Ok(response), throttled);
+                }
+                let retry_after = response
+                    .headers()
+                    .get(rquest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| parse_retry_after(v, Utc::now()));
+                (format!("HTTP {}", status), retry_after)
+            }
+            Err(e) => {
+                crate::modules::metrics::record_api_error(host, None);
+                record_failure(host, policy);
+                if attempt >= policy.max_attempts {
+                    return (Err(AppError::from(e)), throttled);
+                }
+                (e.to_string(), None)
+            }
+        };
+
+        let delay = policy.delay(attempt, retry_after);
+        crate::modules::logger::log_warn(&format!(
+            "[Retry] {} failed: {} (Attempt {}/{}), retrying in {}ms",
+            host,
+            error,
+            attempt,
+            policy.max_attempts,
+            delay.as_millis()
+        ));
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(3),
+            breaker_threshold: 3,
+            breaker_cooldown: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn test_delay_and_retry_after() {
+        let policy = policy();
+        assert_eq!(policy.backoff_cap(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff_cap(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff_cap(10), Duration::from_secs(3));
+        for attempt in 1..6 {
+            assert!(policy.delay(attempt, None) <= policy.backoff_cap(attempt));
+        }
+        // Retry-After 优先，但不超过 max_delay
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(2))),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(120))),
+            Duration::from_secs(3)
+        );
+
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:27:30Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(parse_retry_after(" 7 ", now), Some(Duration::from_secs(7)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+
+        assert!(is_retryable_status(429) && is_retryable_status(503));
+        assert!(!is_retryable_status(401) && !is_retryable_status(403));
+    }
+
+    #[test]
+    fn test_breaker_opens_and_probes() {
+        let cooldown = Duration::from_secs(30);
+        let start = Instant::now();
+        let mut breaker = Breaker::default();
+
+        assert!(!breaker.on_failure(start, 3, cooldown));
+        breaker.on_success();
+        assert!(!breaker.on_failure(start, 3, cooldown));
+        assert!(!breaker.on_failure(start, 3, cooldown));
+        assert!(breaker.on_failure(start, 3, cooldown));
+        assert_eq!(
+            breaker.allow(start + Duration::from_secs(10), cooldown),
+            Err(Duration::from_secs(20))
+        );
+
+        // 冷却结束只放行一次试探，试探失败立即重新熔断
+        let later = start + cooldown;
+        assert_eq!(breaker.allow(later, cooldown), Ok(()));
+        assert!(breaker.allow(later, cooldown).is_err());
+        assert!(breaker.on_failure(later, 3, cooldown));
+        assert!(breaker
+            .allow(later + Duration::from_secs(1), cooldown)
+            .is_err());
+
+        // 试探请求被取消（没有结果）时，一个冷却期后放行下一次试探
+        let later = later + cooldown;
+        assert_eq!(breaker.allow(later, cooldown), Ok(()));
+        assert!(breaker.allow(later + Duration::from_secs(29), cooldown).is_err());
+        let later = later + cooldown;
+        assert_eq!(breaker.allow(later, cooldown), Ok(()));
+        breaker.on_success();
+        assert_eq!(breaker.allow(later, cooldown), Ok(()));
+        assert_eq!(breaker.failures, 0);
+
+        assert!(trips_breaker(503) && !trips_breaker(429) && !trips_breaker(404));
+    }
+
+    #[test]
+    fn test_throttle_counts_are_per_scope() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let throttled = |n: u32| async move {
+                tokio::task::yield_now().await;
+                note_throttles(n);
+                tokio::task::yield_now().await;
+                note_throttles(n);
+            };
+            let (a, b) = tokio::join!(
+                count_throttles(throttled(1)),
+                count_throttles(throttled(0))
+            );
+            assert_eq!((a.1, b.1), (2, 0));
+            // 作用域外不计数
+            note_throttles(3);
+            assert_eq!(count_throttles(async {}).await.1, 0);
+        });
+    }
+}